@@ -0,0 +1,490 @@
+use crate::{
+    error::{ErrorKind, Result},
+    meta::{MetaFlags, MetaPosition, APE_HEADER_SIZE},
+    options::WriteOptions,
+    storage::Truncate,
+    tag::{read_from, Tag},
+    util::APE_PREAMBLE,
+};
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWriteExt, Cursor as AsyncCursor};
+use std::{
+    future::Future,
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+    str,
+};
+
+const ID3V1_SIZE: u64 = 128;
+// Lyrics3 v2.00 size digits followed by the end marker.
+const LYRICS3V2_FOOTER_SIZE: u64 = 15;
+
+/// An asynchronous storage providing positioned reads, required to read a tag.
+///
+/// Implemented for any `futures-io` reader, so async-std or smol files can be used directly.
+/// Methods are prefixed like in [`ApeStorage`](trait.ApeStorage.html).
+pub trait AsyncApeRead {
+    /// Reads bytes starting at the given offset, returns a number of bytes read.
+    fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> impl Future<Output = io::Result<usize>>;
+
+    /// Returns the length of the storage.
+    fn ape_len(&mut self) -> impl Future<Output = io::Result<u64>>;
+}
+
+/// An asynchronous storage which can be written and truncated, required to write or remove a tag.
+///
+/// `futures-io` has no way to change a length of a file, so implement it for a file type of your runtime,
+/// it is implemented for `futures_util::io::Cursor<Vec<u8>>`.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{AsyncApeRead, AsyncApeWrite};
+/// use std::io::Result;
+///
+/// struct Storage(Vec<u8>);
+///
+/// impl AsyncApeRead for Storage {
+///     async fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize> {
+///         let data = self.0.get(offset as usize..).unwrap_or_default();
+///         let len = buf.len().min(data.len());
+///         buf[..len].copy_from_slice(&data[..len]);
+///         Ok(len)
+///     }
+///
+///     async fn ape_len(&mut self) -> Result<u64> {
+///         Ok(self.0.len() as u64)
+///     }
+/// }
+///
+/// impl AsyncApeWrite for Storage {
+///     async fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> Result<usize> {
+///         let end = offset as usize + buf.len();
+///         if self.0.len() < end {
+///             self.0.resize(end, 0);
+///         }
+///         self.0[offset as usize..end].copy_from_slice(buf);
+///         Ok(buf.len())
+///     }
+///
+///     async fn ape_truncate(&mut self, len: u64) -> Result<()> {
+///         self.0.resize(len as usize, 0);
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait AsyncApeWrite: AsyncApeRead {
+    /// Writes bytes starting at the given offset, returns a number of bytes written.
+    fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> impl Future<Output = io::Result<usize>>;
+
+    /// Truncates or extends the storage to the given length.
+    fn ape_truncate(&mut self, len: u64) -> impl Future<Output = io::Result<()>>;
+
+    /// Flushes written data to a durable storage.
+    ///
+    /// It is used by [`WriteOptions::sync`](struct.WriteOptions.html#method.sync), does nothing by default.
+    fn ape_sync_data(&mut self) -> impl Future<Output = io::Result<()>> {
+        async { Ok(()) }
+    }
+}
+
+impl<T: AsyncRead + AsyncSeek + Unpin> AsyncApeRead for T {
+    async fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.seek(SeekFrom::Start(offset)).await?;
+        self.read(buf).await
+    }
+
+    async fn ape_len(&mut self) -> io::Result<u64> {
+        self.seek(SeekFrom::End(0)).await
+    }
+}
+
+impl AsyncApeWrite for AsyncCursor<Vec<u8>> {
+    async fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.seek(SeekFrom::Start(offset)).await?;
+        self.write(buf).await
+    }
+
+    async fn ape_truncate(&mut self, len: u64) -> io::Result<()> {
+        self.get_mut().resize(len as usize, 0);
+        Ok(())
+    }
+}
+
+/// Attempts to read an APE tag from an asynchronous reader.
+///
+/// Works with any runtime implementing `futures-io` traits (async-std, smol, etc.)
+/// or any other [`AsyncApeRead`](trait.AsyncApeRead.html) storage.
+/// Only the tag region is read asynchronously, the tag is parsed in memory.
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_from_async;
+/// use futures_util::io::Cursor;
+///
+/// # async fn run() {
+/// let mut reader = Cursor::new(Vec::<u8>::new());
+/// let tag = read_from_async(&mut reader).await.unwrap();
+/// # }
+/// ```
+pub async fn read_from_async<R: AsyncApeRead>(reader: &mut R) -> Result<Tag> {
+    let len = reader.ape_len().await?;
+    let (start, end) = locate(reader, len).await?;
+    let buf = read_at(reader, start, (end - start) as usize).await?;
+    read_from(&mut Cursor::new(buf))
+}
+
+/// Attempts to write the APE tag to an asynchronous storage.
+///
+/// Only the end of the storage starting at the existing tag is read and rewritten,
+/// unless there is a tag at the start or the options need the rest of the storage.
+///
+/// # Errors
+///
+/// See [`write_to_path`](fn.write_to_path.html)
+///
+/// # Examples
+///
+/// ```
+/// use ape::{read_from_async, write_to_async, Item, Tag};
+/// use futures_util::io::Cursor;
+///
+/// # futures_executor::block_on(async {
+/// let mut tag = Tag::new();
+/// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// let mut storage = Cursor::new(vec![0; 200]);
+/// write_to_async(&tag, &mut storage).await.unwrap();
+/// assert!(read_from_async(&mut storage).await.unwrap().item("artist").is_some());
+/// # });
+/// ```
+pub async fn write_to_async<F: AsyncApeWrite>(tag: &Tag, file: &mut F) -> Result<()> {
+    WriteOptions::new().write_to_async(tag, file).await
+}
+
+/// Attempts to remove an APE tag from an asynchronous storage.
+///
+/// # Errors
+///
+/// See [`remove_from_path`](fn.remove_from_path.html)
+pub async fn remove_from_async<F: AsyncApeWrite>(file: &mut F) -> Result<()> {
+    WriteOptions::new().remove_from_async(file).await
+}
+
+/// Applies a modification made by the blocking write path to an asynchronous storage.
+///
+/// The modification runs on a [`Window`], it runs again on the whole storage
+/// when it needs the part which has not been loaded.
+pub(crate) async fn modify_async<F, M>(file: &mut F, options: &WriteOptions, modify: M) -> Result<()>
+where
+    F: AsyncApeWrite,
+    M: Fn(&mut Window) -> Result<()>,
+{
+    let mut window = Window::load(file, options, false).await?;
+    let mut result = modify(&mut window);
+    if window.missed {
+        window = Window::load(file, options, true).await?;
+        result = modify(&mut window);
+    }
+    result?;
+    window.store(file).await
+}
+
+/// The end of a storage loaded into memory together with the first bytes checked for a tag at the start.
+///
+/// Accessing bytes in between fails and marks the window as missed.
+pub(crate) struct Window {
+    head: Vec<u8>,
+    base: u64,
+    tail: Vec<u8>,
+    original: Vec<u8>,
+    position: u64,
+    missed: bool,
+}
+
+impl Window {
+    async fn load<F: AsyncApeRead>(file: &mut F, options: &WriteOptions, whole: bool) -> Result<Window> {
+        let len = file.ape_len().await?;
+        let base = match whole {
+            true => 0,
+            false => match locate(file, len).await {
+                Ok((start, _)) => start,
+                Err(err) if err.kind() == ErrorKind::TagNotFound => trailers_start(file, len).await?,
+                Err(err) => return Err(err),
+            }
+            .saturating_sub(options.garbage_window()),
+        };
+        let head = read_at(file, 0, base.min(APE_HEADER_SIZE) as usize).await?;
+        let tail = read_at(file, base, (len - base) as usize).await?;
+        Ok(Window {
+            head,
+            base,
+            original: tail.clone(),
+            tail,
+            position: 0,
+            missed: false,
+        })
+    }
+
+    /// Writes the changed part of the window back and updates the length of the storage.
+    async fn store<F: AsyncApeWrite>(self, file: &mut F) -> Result<()> {
+        let changed = self
+            .tail
+            .iter()
+            .zip(&self.original)
+            .position(|(new, old)| new != old)
+            .unwrap_or_else(|| self.tail.len().min(self.original.len()));
+        write_at(file, &self.tail[changed..], self.base + changed as u64).await?;
+        if self.tail.len() != self.original.len() {
+            file.ape_truncate(self.base + self.tail.len() as u64).await?;
+        }
+        Ok(())
+    }
+
+    fn miss(&mut self) -> io::Error {
+        self.missed = true;
+        io::Error::new(io::ErrorKind::Unsupported, "the part of the storage is not loaded")
+    }
+}
+
+impl Read for Window {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (data, start) = if self.position >= self.base {
+            (&self.tail, self.base)
+        } else if self.position < self.head.len() as u64 {
+            (&self.head, 0)
+        } else {
+            return Err(self.miss());
+        };
+        let data = data.get((self.position - start) as usize..).unwrap_or_default();
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl Write for Window {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.position < self.base {
+            return Err(self.miss());
+        }
+        let start = (self.position - self.base) as usize;
+        let end = start + buf.len();
+        if self.tail.len() < end {
+            self.tail.resize(end, 0);
+        }
+        self.tail[start..end].copy_from_slice(buf);
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Window {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.base + self.tail.len() as u64;
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(self.position)
+    }
+}
+
+impl Truncate for Window {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        if len < self.base {
+            return Err(self.miss());
+        }
+        self.tail.resize((len - self.base) as usize, 0);
+        Ok(())
+    }
+}
+
+/// Returns the region occupied by the tag including header and footer.
+async fn locate<R: AsyncApeRead>(reader: &mut R, len: u64) -> Result<(u64, u64)> {
+    let mut pos = None;
+    for candidate in [len.checked_sub(APE_HEADER_SIZE), Some(0)].into_iter().flatten() {
+        if probe_ape(reader, candidate, len).await? {
+            pos = Some(candidate);
+            break;
+        }
+    }
+    // When located at the end of an MP3 file, an APE tag should be placed after
+    // the last frame, just before the ID3v1 tag (if any).
+    if pos.is_none() {
+        let end = trailers_start(reader, len).await?;
+        if end < len && end >= APE_HEADER_SIZE && probe_ape(reader, end - APE_HEADER_SIZE, len).await? {
+            pos = Some(end - APE_HEADER_SIZE);
+        }
+    }
+    let pos = pos.ok_or(ErrorKind::TagNotFound)?;
+    let block = read_at(reader, pos, APE_HEADER_SIZE as usize).await?;
+    let size = u32::from_le_bytes([block[12], block[13], block[14], block[15]]) as u64;
+    let flags = MetaFlags::from_raw(u32::from_le_bytes([block[20], block[21], block[22], block[23]]));
+    Ok(match flags.position {
+        MetaPosition::Header => (pos, (pos + APE_HEADER_SIZE + size).min(len)),
+        MetaPosition::Footer => {
+            let mut start = (pos + APE_HEADER_SIZE).saturating_sub(size);
+            if flags.has_header {
+                start = start.saturating_sub(APE_HEADER_SIZE);
+            }
+            (start, pos + APE_HEADER_SIZE)
+        }
+    })
+}
+
+/// Returns the offset of ID3v1 and LYRICS3v2 tags at the end of a file, the file length if there are none.
+async fn trailers_start<R: AsyncApeRead>(reader: &mut R, len: u64) -> Result<u64> {
+    if len < ID3V1_SIZE || &read_at(reader, len - ID3V1_SIZE, 3).await?[..] != b"TAG" {
+        return Ok(len);
+    }
+    let mut end = len - ID3V1_SIZE;
+    if !probe_ape(reader, end.saturating_sub(APE_HEADER_SIZE), len).await? && end >= LYRICS3V2_FOOTER_SIZE {
+        // ID3v1 tag maybe preceded by Lyrics3v2: http://id3.org/Lyrics3v2
+        let footer = read_at(reader, end - LYRICS3V2_FOOTER_SIZE, LYRICS3V2_FOOTER_SIZE as usize).await?;
+        if &footer[6..] == b"LYRICS200" {
+            let size = str::from_utf8(&footer[..6])?.parse::<u64>()?;
+            end = end.saturating_sub(size + LYRICS3V2_FOOTER_SIZE);
+        }
+    }
+    Ok(end)
+}
+
+async fn probe_ape<R: AsyncApeRead>(reader: &mut R, pos: u64, len: u64) -> Result<bool> {
+    if pos + APE_HEADER_SIZE > len {
+        return Ok(false);
+    }
+    Ok(read_at(reader, pos, APE_PREAMBLE.len()).await? == APE_PREAMBLE)
+}
+
+async fn read_at<R: AsyncApeRead>(reader: &mut R, pos: u64, len: usize) -> Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    let mut done = 0;
+    while done < len {
+        match reader.ape_read_at(&mut buf[done..], pos + done as u64).await? {
+            0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            read => done += read,
+        }
+    }
+    Ok(buf)
+}
+
+async fn write_at<F: AsyncApeWrite>(file: &mut F, buf: &[u8], pos: u64) -> Result<()> {
+    let mut done = 0;
+    while done < buf.len() {
+        match file.ape_write_at(&buf[done..], pos + done as u64).await? {
+            0 => return Err(io::Error::from(io::ErrorKind::WriteZero).into()),
+            written => done += written,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_from_async, remove_from_async, write_to_async};
+    use crate::{
+        item::{Item, ItemValue},
+        meta::Layout,
+        options::WriteOptions,
+        tag::{remove_from, serialize, write_to, write_to_path, Tag},
+    };
+    use futures_executor::block_on;
+    use futures_util::io::Cursor;
+    use std::{
+        fs::{read, remove_file, File},
+        io::Write,
+    };
+
+    fn tagged_data(path: &str, trailer: &[u8]) -> Vec<u8> {
+        File::create(path).unwrap().write_all(&[0; 200]).unwrap();
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to_path(&tag, path).unwrap();
+        let mut data = read(path).unwrap();
+        data.extend(trailer);
+        remove_file(path).unwrap();
+        data
+    }
+
+    fn read_value(data: Vec<u8>) -> String {
+        let tag = block_on(read_from_async(&mut Cursor::new(data))).unwrap();
+        match tag.item("key").unwrap().value {
+            ItemValue::Text(ref val) => val.clone(),
+            _ => panic!("Invalid value"),
+        }
+    }
+
+    #[test]
+    fn read_at_end() {
+        assert_eq!(read_value(tagged_data("data/async-read-end.apev2", &[])), "value");
+    }
+
+    #[test]
+    fn read_before_id3v1() {
+        let mut id3 = b"TAG".to_vec();
+        id3.extend([0; 125]);
+        assert_eq!(read_value(tagged_data("data/async-read-id3v1.apev2", &id3)), "value");
+        let mut lyrics = vec![0; 20];
+        lyrics.extend(b"000020LYRICS200");
+        lyrics.extend(id3);
+        assert_eq!(
+            read_value(tagged_data("data/async-read-lyrics.apev2", &lyrics)),
+            "value"
+        );
+    }
+
+    fn assert_same_as_blocking(data: Vec<u8>) {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "new value").unwrap());
+        let mut expected = std::io::Cursor::new(data.clone());
+        write_to(&tag, &mut expected).unwrap();
+        let mut storage = Cursor::new(data.clone());
+        block_on(write_to_async(&tag, &mut storage)).unwrap();
+        assert_eq!(storage.get_ref(), expected.get_ref());
+
+        remove_from(&mut expected).unwrap();
+        block_on(remove_from_async(&mut storage)).unwrap();
+        assert_eq!(storage.get_ref(), expected.get_ref());
+    }
+
+    #[test]
+    fn write_and_remove() {
+        assert_same_as_blocking(vec![1; 200]);
+        assert_same_as_blocking(tagged_data("data/async-write-end.apev2", &[]));
+        let mut id3 = b"TAG".to_vec();
+        id3.extend([0; 125]);
+        assert_same_as_blocking(tagged_data("data/async-write-id3v1.apev2", &id3));
+    }
+
+    #[test]
+    fn write_tag_at_start() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let layout = Layout {
+            header: true,
+            footer: true,
+            at_start: true,
+        };
+        let start = serialize(&tag, &WriteOptions::new(), 0, layout).unwrap();
+        let data = [&start[..], &[1; 200]].concat();
+        assert_eq!(read_value(data.clone()), "value");
+        // The whole storage is needed to move the audio data
+        assert_same_as_blocking(data);
+    }
+
+    #[test]
+    fn not_found() {
+        let err = block_on(read_from_async(&mut Cursor::new(vec![1; 200]))).unwrap_err();
+        assert_eq!(err.to_string(), "APE tag does not exists");
+    }
+}
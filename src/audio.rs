@@ -0,0 +1,164 @@
+use crate::{error::Result, layout::audio_range};
+use std::{
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+/// A reader over the audio data of a file.
+///
+/// APE, Lyrics3v2 and ID3v1 blocks are hidden, so a decoder or a checksum never sees tag bytes.
+/// Offsets are relative to the start of the audio data.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::AudioDataReader;
+/// use std::{fs::File, io::Read};
+///
+/// let file = File::open("path/to/file").unwrap();
+/// let mut reader = AudioDataReader::new(file).unwrap();
+/// let mut audio = Vec::new();
+/// reader.read_to_end(&mut audio).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AudioDataReader<R> {
+    inner: R,
+    range: Range<u64>,
+    pos: u64,
+}
+
+impl<R: Read + Seek> AudioDataReader<R> {
+    /// Creates a new reader locating metadata blocks of the given one.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let range = audio_range(&mut inner)?;
+        inner.seek(SeekFrom::Start(range.start))?;
+        Ok(AudioDataReader { inner, range, pos: 0 })
+    }
+}
+
+impl<R> AudioDataReader<R> {
+    /// Returns the span of the audio data in the underlying reader.
+    pub fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    /// Unwraps this reader, returning the underlying one.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn len(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+}
+
+impl<R: Read + Seek> Read for AudioDataReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len().saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let limit = remaining.min(buf.len() as u64) as usize;
+        self.inner.seek(SeekFrom::Start(self.range.start + self.pos))?;
+        let count = self.inner.read(&mut buf[..limit])?;
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> Seek for AudioDataReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match target {
+            Some(target) => {
+                self.pos = target;
+                Ok(target)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// Feeds the audio data of a file into a hasher skipping all metadata blocks.
+///
+/// Any [`Write`](https://doc.rust-lang.org/std/io/trait.Write.html) implementation may be used as a hasher,
+/// e.g. a `Digest` from `sha2` crate. Files containing the same audio and different tags get the same hash.
+/// Returns the number of bytes written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::hash_audio_data;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// let mut hasher = Vec::new();
+/// hash_audio_data(&mut file, &mut hasher).unwrap();
+/// ```
+pub fn hash_audio_data<R: Read + Seek, W: Write>(reader: &mut R, hasher: &mut W) -> Result<u64> {
+    let mut reader = AudioDataReader::new(reader)?;
+    Ok(io::copy(&mut reader, hasher)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash_audio_data, AudioDataReader};
+    use crate::{
+        tag::{relocate_tag, write_to},
+        Item, MetaPosition, Tag,
+    };
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    #[test]
+    fn skip_tags() {
+        let audio = (0..200).map(|x| x as u8).collect::<Vec<_>>();
+        let mut data = Cursor::new(audio.clone());
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut data).unwrap();
+        relocate_tag(&mut data, MetaPosition::Header).unwrap();
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        data.get_mut().extend_from_slice(&id3v1);
+
+        let mut reader = AudioDataReader::new(data).unwrap();
+        let mut result = Vec::new();
+        reader.read_to_end(&mut result).unwrap();
+        assert_eq!(result, audio);
+
+        assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 190);
+        let mut buf = [0; 20];
+        assert_eq!(reader.read(&mut buf).unwrap(), 10);
+        assert_eq!(&buf[..10], &audio[190..]);
+        assert_eq!(reader.seek(SeekFrom::Start(5)).unwrap(), 5);
+        reader.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(&buf[..2], &[5, 6]);
+        assert!(reader.seek(SeekFrom::Current(-10)).is_err());
+    }
+
+    #[test]
+    fn hash_without_tags() {
+        let audio = vec![7; 100];
+        let mut first = Cursor::new(audio.clone());
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut first).unwrap();
+        let mut second = Cursor::new(audio.clone());
+        tag.set_item(Item::from_text("key", "other value").unwrap());
+        write_to(&tag, &mut second).unwrap();
+
+        let mut first_hash = Vec::new();
+        assert_eq!(hash_audio_data(&mut first, &mut first_hash).unwrap(), 100);
+        let mut second_hash = Vec::new();
+        assert_eq!(hash_audio_data(&mut second, &mut second_hash).unwrap(), 100);
+        assert_eq!(first_hash, audio);
+        assert_eq!(second_hash, audio);
+    }
+}
@@ -0,0 +1,171 @@
+//! Consistency checks of tags of several files.
+
+use crate::{
+    error::{ErrorKind, Result},
+    key::ItemKey,
+    metadata::Metadata,
+    options::ReadOptions,
+    tag::Tag,
+};
+use std::path::{Path, PathBuf};
+
+/// A function returning a value of a field which has to be the same for the whole album.
+type FieldFn = fn(&Metadata) -> Option<String>;
+
+/// A problem found by [`check_album`](fn.check_album.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Inconsistency {
+    /// Files have different values of a field which is the same for the whole album.
+    ///
+    /// `Disc` values are totals of discs.
+    Mismatch {
+        /// Key of the field.
+        key: ItemKey,
+        /// Paths of all files with their values, `None` if a file has no value.
+        values: Vec<(PathBuf, Option<String>)>,
+    },
+    /// Several files have the same track number on the same disc.
+    DuplicateTrack {
+        /// Disc number, 1 if files have no disc numbers.
+        disc: u32,
+        /// Track number.
+        track: u32,
+        /// Paths of files with the number.
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Checks that files belong to the same album, e.g. before publishing a rip.
+///
+/// Reports fields which differ between files: `Album`, `Album Artist`, `Year` and the total of discs,
+/// and track numbers used by several files of the same disc. Files without a tag are checked as empty ones.
+///
+/// # Errors
+///
+/// Fails if a tag can not be read, see [`read_from_path`](../fn.read_from_path.html).
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::audit::{check_album, Inconsistency};
+///
+/// for inconsistency in check_album(["01.ape", "02.ape"]).unwrap() {
+///     if let Inconsistency::DuplicateTrack { track, paths, .. } = inconsistency {
+///         println!("Track {} is used by {:?}", track, paths);
+///     }
+/// }
+/// ```
+pub fn check_album<I, P>(paths: I) -> Result<Vec<Inconsistency>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let tag = match ReadOptions::new().read_from_path(path) {
+            Err(err) if err.kind() == ErrorKind::TagNotFound => Tag::new(),
+            result => result?,
+        };
+        files.push((path.to_path_buf(), Metadata::from_tag(&tag)));
+    }
+    Ok(check_metadata(&files))
+}
+
+/// Checks metadata of files.
+fn check_metadata(files: &[(PathBuf, Metadata)]) -> Vec<Inconsistency> {
+    let mut inconsistencies = Vec::new();
+    let fields: [(ItemKey, FieldFn); 4] = [
+        (ItemKey::Album, |metadata| metadata.album.clone()),
+        (ItemKey::AlbumArtist, |metadata| metadata.album_artist.clone()),
+        (ItemKey::Year, |metadata| metadata.year.clone()),
+        (ItemKey::Disc, |metadata| {
+            metadata.disc_total.map(|total| total.to_string())
+        }),
+    ];
+    for (key, field) in fields {
+        let values = files
+            .iter()
+            .map(|(path, metadata)| (path.clone(), field(metadata)))
+            .collect::<Vec<_>>();
+        if values.iter().any(|(_, value)| *value != values[0].1) {
+            inconsistencies.push(Inconsistency::Mismatch { key, values });
+        }
+    }
+    let mut tracks = files
+        .iter()
+        .filter_map(|(path, metadata)| Some(((metadata.disc.unwrap_or(1), metadata.track?), path)))
+        .collect::<Vec<_>>();
+    // Sorting is stable, so paths of a track are kept in the given order
+    tracks.sort_by_key(|&(number, _)| number);
+    for group in tracks.chunk_by(|a, b| a.0 == b.0).filter(|group| group.len() > 1) {
+        let (disc, track) = group[0].0;
+        inconsistencies.push(Inconsistency::DuplicateTrack {
+            disc,
+            track,
+            paths: group.iter().map(|&(_, path)| path.clone()).collect(),
+        });
+    }
+    inconsistencies
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_album, Inconsistency};
+    use crate::{error::ErrorKind, key::ItemKey, tag::Tag};
+    use std::{
+        fs::{remove_file, write},
+        path::PathBuf,
+    };
+
+    #[test]
+    fn album() {
+        let paths = ["data/audit-1.apev2", "data/audit-2.apev2", "data/audit-3.apev2"];
+        let values = [("1/2", "1", "2001"), ("1/2", "01", "2001"), ("2/3", "1", "2001")];
+        for (path, (disc, track, year)) in paths.iter().zip(values) {
+            let mut tag = Tag::new();
+            tag.set_text("Album", "Album").unwrap();
+            tag.set_text("Disc", disc).unwrap();
+            tag.set_text("Track", track).unwrap();
+            tag.set_text("Year", year).unwrap();
+            write(path, tag.to_bytes().unwrap()).unwrap();
+        }
+        assert_eq!(
+            check_album(paths).unwrap(),
+            vec![
+                Inconsistency::Mismatch {
+                    key: ItemKey::Disc,
+                    values: vec![
+                        (PathBuf::from(paths[0]), Some(String::from("2"))),
+                        (PathBuf::from(paths[1]), Some(String::from("2"))),
+                        (PathBuf::from(paths[2]), Some(String::from("3"))),
+                    ],
+                },
+                Inconsistency::DuplicateTrack {
+                    disc: 1,
+                    track: 1,
+                    paths: vec![PathBuf::from(paths[0]), PathBuf::from(paths[1])],
+                },
+            ]
+        );
+        assert!(check_album(&paths[..1]).unwrap().is_empty());
+
+        write(paths[2], [0; 16]).unwrap();
+        let inconsistencies = check_album(paths).unwrap();
+        assert_eq!(inconsistencies.len(), 4);
+        assert!(matches!(
+            inconsistencies[0],
+            Inconsistency::Mismatch {
+                key: ItemKey::Album,
+                ..
+            }
+        ));
+        for path in paths {
+            remove_file(path).unwrap();
+        }
+        let err = check_album(paths).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert_eq!(err.path(), Some(paths[0].as_ref()));
+    }
+}
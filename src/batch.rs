@@ -0,0 +1,392 @@
+//! Processing tags of all audio files in a directory.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::batch::{self, BatchOptions};
+//!
+//! let results = batch::process("path/to/music", &BatchOptions::new(), |_, mut tag| {
+//!     tag.remove_items("Comment");
+//!     Some(tag)
+//! });
+//! for result in results {
+//!     if let Err(err) = result.result {
+//!         eprintln!("{}: {}", result.path.display(), err);
+//!     }
+//! }
+//! ```
+
+use crate::{
+    error::{Error, ErrorKind, Result},
+    options::{ReadOptions, WriteOptions},
+    tag::Tag,
+};
+#[cfg(feature = "rayon")]
+use std::io::Error as IoError;
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Extensions of files processed by default.
+const AUDIO_EXTENSIONS: [&str; 6] = ["ape", "mp3", "mpc", "mp+", "mpp", "wv"];
+
+/// Options and flags which can be used to configure how files are processed.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    read_options: ReadOptions,
+    write_options: WriteOptions,
+    extensions: Vec<String>,
+    recursive: bool,
+    timeout: Option<Duration>,
+    #[cfg(feature = "rayon")]
+    threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> BatchOptions {
+        let mut read_options = ReadOptions::new();
+        read_options.reserved_items(true).tolerate_item_count(true);
+        BatchOptions {
+            read_options,
+            write_options: WriteOptions::new(),
+            extensions: AUDIO_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            recursive: true,
+            timeout: None,
+            #[cfg(feature = "rayon")]
+            threads: 0,
+        }
+    }
+}
+
+impl BatchOptions {
+    /// Creates a new set of options.
+    ///
+    /// Files of Monkey's Audio, MPEG, Musepack and WavPack are processed recursively,
+    /// tags are read tolerating reserved items and a wrong number of items.
+    pub fn new() -> BatchOptions {
+        Self::default()
+    }
+
+    /// Sets options used to read tags.
+    pub fn read_options(&mut self, options: ReadOptions) -> &mut BatchOptions {
+        self.read_options = options;
+        self
+    }
+
+    /// Sets options used to write tags.
+    pub fn write_options(&mut self, options: WriteOptions) -> &mut BatchOptions {
+        self.write_options = options;
+        self
+    }
+
+    /// Sets extensions of processed files, compared case-insensitively.
+    pub fn extensions<I: IntoIterator<Item = S>, S: Into<String>>(&mut self, extensions: I) -> &mut BatchOptions {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets whether subdirectories are processed, `true` by default.
+    pub fn recursive(&mut self, recursive: bool) -> &mut BatchOptions {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Sets the maximum time spent on a file, there is no limit by default.
+    ///
+    /// The time is checked after the tag is read and after the callback returns, so the callback is not called
+    /// and the tag is not written when the time is up. Reading and the callback themselves are not interrupted,
+    /// while a running write is cancelled the same way as by [`WriteOptions::should_cancel`](../struct.WriteOptions.html#method.should_cancel).
+    /// The file gets [`ErrorKind::TimedOut`](../enum.ErrorKind.html#variant.TimedOut) in all cases.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut BatchOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of threads used by [`process_parallel`](fn.process_parallel.html).
+    ///
+    /// Zero, the default, uses the global thread pool of rayon.
+    #[cfg(feature = "rayon")]
+    pub fn threads(&mut self, threads: usize) -> &mut BatchOptions {
+        self.threads = threads;
+        self
+    }
+
+    fn is_processed(&self, path: &Path) -> bool {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => self.extensions.iter().any(|x| x.eq_ignore_ascii_case(ext)),
+            None => false,
+        }
+    }
+}
+
+/// A result of processing a file or reading a directory.
+#[derive(Debug)]
+pub struct FileResult {
+    /// Path of the file or the directory.
+    pub path: PathBuf,
+    /// Whether a tag has been written, or an error occured with the file.
+    pub result: Result<bool>,
+}
+
+/// Processes tags of files in a directory.
+///
+/// The callback receives a path and a tag of every file, an empty one if the file has no tag.
+/// When it returns a tag, the tag is written unless it is not changed.
+/// Symbolic links to directories are not followed.
+/// Files are processed in the order of their paths. A failure does not stop processing,
+/// results of all files and errors of unreadable directories are returned in the same order.
+pub fn process<P, F>(dir: P, options: &BatchOptions, mut callback: F) -> Vec<FileResult>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, Tag) -> Option<Tag>,
+{
+    let mut results = Vec::new();
+    let mut files = Vec::new();
+    collect_files(dir.as_ref(), options, &mut files, &mut results);
+    for path in files {
+        let result = process_file(&path, options, &mut callback);
+        results.push(FileResult { path, result });
+    }
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    results
+}
+
+/// Processes tags of files in a directory across threads.
+///
+/// The same as [`process`](fn.process.html) except that files are processed in parallel,
+/// so the callback may be called for several files at once. Results of all threads are collected
+/// and returned in the order of paths, a failure does not stop processing of other files.
+///
+/// # Errors
+///
+/// If a thread pool with the number of threads set by [`BatchOptions::threads`](struct.BatchOptions.html#method.threads)
+/// can not be created, every file gets an error.
+#[cfg(feature = "rayon")]
+pub fn process_parallel<P, F>(dir: P, options: &BatchOptions, callback: F) -> Vec<FileResult>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path, Tag) -> Option<Tag> + Sync,
+{
+    use rayon::{prelude::*, ThreadPoolBuilder};
+
+    let mut results = Vec::new();
+    let mut files = Vec::new();
+    collect_files(dir.as_ref(), options, &mut files, &mut results);
+    let process = |files: Vec<PathBuf>| {
+        files
+            .into_par_iter()
+            .map(|path| {
+                let result = process_file(&path, options, &callback);
+                FileResult { path, result }
+            })
+            .collect::<Vec<_>>()
+    };
+    if options.threads == 0 {
+        results.extend(process(files));
+    } else {
+        match ThreadPoolBuilder::new().num_threads(options.threads).build() {
+            Ok(pool) => results.extend(pool.install(|| process(files))),
+            Err(err) => results.extend(files.into_iter().map(|path| FileResult {
+                result: Err(Error::from(IoError::other(err.to_string())).with_path(&path)),
+                path,
+            })),
+        }
+    }
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    results
+}
+
+/// Collects paths of processed files, errors of reading directories are added to results.
+fn collect_files(dir: &Path, options: &BatchOptions, files: &mut Vec<PathBuf>, results: &mut Vec<FileResult>) {
+    // Symbolic links are not followed, so a link to a parent directory does not cause a loop
+    let entries = read_dir(dir).and_then(|entries| {
+        entries
+            .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?.is_dir()))))
+            .collect::<std::io::Result<Vec<_>>>()
+    });
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            results.push(FileResult {
+                path: dir.to_path_buf(),
+                result: Err(Error::from(err).with_path(dir)),
+            });
+            return;
+        }
+    };
+    entries.sort();
+    for (path, is_dir) in entries {
+        if is_dir {
+            if options.recursive {
+                collect_files(&path, options, files, results);
+            }
+        } else if options.is_processed(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// Reads a tag, passes it to the callback and writes a returned one.
+fn process_file<F>(path: &Path, options: &BatchOptions, callback: F) -> Result<bool>
+where
+    F: FnOnce(&Path, Tag) -> Option<Tag>,
+{
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let timed_out = || Error::from(ErrorKind::TimedOut).with_path(path);
+    let is_late = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+    let tag = match options.read_options.read_from_path(path) {
+        Err(err) if err.kind() == ErrorKind::TagNotFound => Tag::new(),
+        result => result?,
+    };
+    if is_late() {
+        return Err(timed_out());
+    }
+    let tag = match callback(path, tag) {
+        Some(tag) => tag,
+        None => return Ok(false),
+    };
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return options.write_options.write_to_path_if_changed(&tag, path),
+    };
+    if is_late() {
+        return Err(timed_out());
+    }
+    let cancel = options.write_options.clone();
+    let mut write_options = options.write_options.clone();
+    write_options.should_cancel(move || cancel.is_cancelled() || Instant::now() >= deadline);
+    match write_options.write_to_path_if_changed(&tag, path) {
+        Err(err) if err.kind() == ErrorKind::Cancelled && Instant::now() >= deadline => Err(timed_out()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{process, BatchOptions};
+    use crate::{
+        error::ErrorKind,
+        item::ItemValue,
+        tag::{read_from_path, Tag},
+    };
+    use std::{
+        fs::{create_dir_all, read, remove_dir_all, write},
+        path::Path,
+        time::Duration,
+    };
+
+    #[test]
+    fn process_dir() {
+        let dir = Path::new("data/batch");
+        create_dir_all(dir.join("sub")).unwrap();
+        let mut tag = Tag::new();
+        tag.set_text("Title", "Title").unwrap();
+        write(dir.join("a.ape"), tag.to_bytes().unwrap()).unwrap();
+        write(dir.join("sub/b.MP3"), vec![0; 16]).unwrap();
+        write(dir.join("sub/c.wv"), b"APETAGEX").unwrap();
+        write(dir.join("cover.jpg"), vec![0; 16]).unwrap();
+
+        let mut seen = Vec::new();
+        let results = process(dir, &BatchOptions::new(), |path, mut tag| {
+            seen.push(path.to_path_buf());
+            if tag.item("title").is_some() {
+                return Some(tag);
+            }
+            tag.set_text("Title", "New").unwrap();
+            Some(tag)
+        });
+        assert_eq!(seen, vec![dir.join("a.ape"), dir.join("sub/b.MP3")]);
+        let results = results
+            .iter()
+            .map(|x| (x.path.as_path(), x.result.as_ref().map_err(|err| err.kind()).copied()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                (dir.join("a.ape").as_path(), Ok(false)),
+                (dir.join("sub/b.MP3").as_path(), Ok(true)),
+                (dir.join("sub/c.wv").as_path(), Err(ErrorKind::Io)),
+            ]
+        );
+        let tag = read_from_path(dir.join("sub/b.MP3")).unwrap();
+        assert_eq!(tag["title"].value, ItemValue::Text(String::from("New")));
+
+        let results = process(dir, BatchOptions::new().recursive(false).extensions(["jpg"]), |_, _| {
+            None
+        });
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].result.as_ref().unwrap());
+
+        let results = process(dir.join("missing"), &BatchOptions::new(), |_, tag| Some(tag));
+        assert_eq!(results[0].result.as_ref().unwrap_err().kind(), ErrorKind::Io);
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timeout() {
+        let dir = Path::new("data/batch-timeout");
+        create_dir_all(dir).unwrap();
+        write(dir.join("a.ape"), vec![0; 16]).unwrap();
+        let set_title = |_: &Path, mut tag: Tag| {
+            tag.set_text("Title", "Title").unwrap();
+            Some(tag)
+        };
+
+        let results = process(dir, BatchOptions::new().timeout(Duration::ZERO), set_title);
+        assert_eq!(results[0].result.as_ref().unwrap_err().kind(), ErrorKind::TimedOut);
+        assert_eq!(read(dir.join("a.ape")).unwrap(), vec![0; 16]);
+        let results = process(dir, BatchOptions::new().timeout(Duration::ZERO), |_, _| {
+            panic!("called")
+        });
+        assert_eq!(results[0].result.as_ref().unwrap_err().kind(), ErrorKind::TimedOut);
+
+        let results = process(dir, BatchOptions::new().timeout(Duration::from_secs(60)), set_title);
+        assert!(results[0].result.as_ref().unwrap());
+        assert!(read_from_path(dir.join("a.ape")).unwrap().item("title").is_some());
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop() {
+        let dir = Path::new("data/batch-symlink");
+        create_dir_all(dir).unwrap();
+        write(dir.join("a.mp3"), vec![0; 16]).unwrap();
+        std::os::unix::fs::symlink(".", dir.join("loop")).unwrap();
+        let mut calls = 0;
+        let results = process(dir, &BatchOptions::new(), |_, _| {
+            calls += 1;
+            None
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(results.len(), 1);
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn process_parallel() {
+        let dir = Path::new("data/batch-parallel");
+        create_dir_all(dir).unwrap();
+        for idx in 0..8 {
+            write(dir.join(format!("{}.ape", idx)), vec![0; 16]).unwrap();
+        }
+        write(dir.join("broken.ape"), b"APETAGEX").unwrap();
+        let results = super::process_parallel(dir, BatchOptions::new().threads(2), |path, mut tag| {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            tag.set_text("Title", name).unwrap();
+            Some(tag)
+        });
+        assert_eq!(results.len(), 9);
+        for (idx, result) in results.iter().take(8).enumerate() {
+            assert_eq!(result.path, dir.join(format!("{}.ape", idx)));
+            assert!(result.result.as_ref().unwrap());
+            let tag = read_from_path(&result.path).unwrap();
+            assert_eq!(tag["title"].value, ItemValue::Text(idx.to_string()));
+        }
+        assert!(results[8].result.is_err());
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+//! A command line tool for reading and writing APE tags.
+
+use ape::{read_from_path, remove_from_path, write_to_path, Error as ApeError, ErrorKind, Item, ItemValue, Tag};
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Reads and writes APE tags.
+#[derive(Parser)]
+#[command(name = "ape-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints all items of a tag.
+    Read {
+        /// Path to a file.
+        path: PathBuf,
+    },
+    /// Sets an item replacing all items with the same key.
+    Set {
+        /// Path to a file.
+        path: PathBuf,
+        /// Item key.
+        key: String,
+        /// Item value.
+        value: String,
+        /// Store the value as a locator (URL, path, etc.) instead of text.
+        #[arg(long)]
+        locator: bool,
+    },
+    /// Removes all items with the given key.
+    RemoveItem {
+        /// Path to a file.
+        path: PathBuf,
+        /// Item key.
+        key: String,
+    },
+    /// Removes the tag.
+    Delete {
+        /// Path to a file.
+        path: PathBuf,
+    },
+    /// Writes the items of a tag as JSON.
+    ExportJson {
+        /// Path to a file.
+        path: PathBuf,
+        /// Path to a JSON file, stdout by default.
+        output: Option<PathBuf>,
+    },
+    /// Replaces the tag with items read from JSON.
+    ImportJson {
+        /// Path to a file.
+        path: PathBuf,
+        /// Path to a JSON file, stdin by default.
+        input: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("ape-cli: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Read { path } => {
+            let mut stdout = io::stdout().lock();
+            for item in read_from_path(path)?.iter() {
+                match item.value {
+                    ItemValue::Binary(ref value) => writeln!(stdout, "{}: <{} bytes>", item.key, value.len())?,
+                    ItemValue::Reserved(ref value) => writeln!(stdout, "{}: <{} bytes>", item.key, value.len())?,
+                    ItemValue::Locator(ref value) | ItemValue::Text(ref value) => {
+                        writeln!(stdout, "{}: {}", item.key, value)?
+                    }
+                }
+            }
+        }
+        Command::Set {
+            path,
+            key,
+            value,
+            locator,
+        } => {
+            let mut tag = read_or_default(&path)?;
+            let item = if locator {
+                Item::from_locator(key, value)?
+            } else {
+                Item::from_text(key, value)?
+            };
+            tag.set_item(item);
+            write_to_path(&tag, path)?;
+        }
+        Command::RemoveItem { path, key } => {
+            let mut tag = read_from_path(&path)?;
+            if tag.remove_items(key) > 0 {
+                write_to_path(&tag, path)?;
+            }
+        }
+        Command::Delete { path } => remove_from_path(path)?,
+        Command::ExportJson { path, output } => {
+            let json = serde_json::to_string_pretty(&to_json(&read_from_path(path)?))?;
+            match output {
+                Some(output) => fs::write(output, json + "\n")?,
+                None => println!("{json}"),
+            }
+        }
+        Command::ImportJson { path, input } => {
+            let json = match input {
+                Some(input) => fs::read_to_string(input)?,
+                None => {
+                    let mut json = String::new();
+                    io::stdin().read_to_string(&mut json)?;
+                    json
+                }
+            };
+            write_to_path(&from_json(&serde_json::from_str(&json)?)?, path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a tag, a file without a tag gets an empty one.
+fn read_or_default(path: &Path) -> Result<Tag, ApeError> {
+    match read_from_path(path) {
+        Err(err) if err.kind() == ErrorKind::TagNotFound => Ok(Tag::new()),
+        result => result,
+    }
+}
+
+/// Converts a tag to an array of `{"key": ..., "type": ..., "value": ..., "read_only": ...}` objects.
+///
+/// Binary values are hex-encoded.
+fn to_json(tag: &Tag) -> Value {
+    Value::Array(
+        tag.iter()
+            .map(|item| {
+                let (kind, value) = match item.value {
+                    ItemValue::Binary(ref value) => ("binary", encode_hex(value)),
+                    ItemValue::Reserved(ref value) => ("reserved", encode_hex(value)),
+                    ItemValue::Locator(ref value) => ("locator", value.clone()),
+                    ItemValue::Text(ref value) => ("text", value.clone()),
+                };
+                json!({"key": &*item.key, "type": kind, "value": value, "read_only": item.read_only})
+            })
+            .collect(),
+    )
+}
+
+/// Converts the output of [`to_json`] back to a tag.
+///
+/// A missing `read_only` field means the item is writable.
+fn from_json(json: &Value) -> Result<Tag, Box<dyn Error>> {
+    let items = json.as_array().ok_or("expected an array of items")?;
+    let mut tag = Tag::new();
+    for item in items {
+        let field = |name: &str| {
+            item.get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("item field {name:?} is missing or is not a string"))
+        };
+        let (key, value) = (field("key")?, field("value")?);
+        let read_only = match item.get("read_only") {
+            None => false,
+            Some(value) => value.as_bool().ok_or("item field \"read_only\" is not a boolean")?,
+        };
+        let mut item = match field("type")? {
+            "binary" => Item::from_binary(key, decode_hex(value)?)?,
+            "locator" => Item::from_locator(key, value)?,
+            "text" => Item::from_text(key, value)?,
+            "reserved" => {
+                let mut item = Item::from_binary(key, Vec::new())?;
+                item.value = ItemValue::Reserved(decode_hex(value)?);
+                item
+            }
+            kind => return Err(format!("unknown item type {kind:?}").into()),
+        };
+        item.read_only = read_only;
+        tag.add_item(item);
+    }
+    Ok(tag)
+}
+
+fn encode_hex(value: &[u8]) -> String {
+    value.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !value.len().is_multiple_of(2) {
+        return Err("hex value has an odd length".into());
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|idx| {
+            Ok(u8::from_str_radix(
+                value.get(idx..idx + 2).ok_or("invalid hex value")?,
+                16,
+            )?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_json, to_json};
+    use ape::{Item, Tag};
+
+    #[test]
+    fn json_round_trip() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_locator("url", "http://example.com").unwrap());
+        tag.add_item(Item::from_binary("cover", vec![0, 1, 254, 255]).unwrap());
+        tag.add_item(Item::builder("artist").text("Artist").read_only(true).build().unwrap());
+        let json = to_json(&tag);
+        assert_eq!(json[2]["value"], "0001feff");
+        assert_eq!(json[2]["read_only"], false);
+        assert_eq!(json[3]["read_only"], true);
+        let result = from_json(&json).unwrap();
+        assert_eq!(result.iter().collect::<Vec<_>>(), tag.iter().collect::<Vec<_>>());
+
+        let result = from_json(&serde_json::json!([{"key": "title", "type": "text", "value": "Title"}])).unwrap();
+        assert!(!result["title"].read_only);
+        assert!(from_json(&serde_json::json!([{"key": "title"}])).is_err());
+        let read_only = serde_json::json!([{"key": "title", "type": "text", "value": "Title", "read_only": 1}]);
+        assert!(from_json(&read_only).is_err());
+        assert!(from_json(&serde_json::json!([{"key": "cover", "type": "binary", "value": "0"}])).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Technical properties of audio streams which usually carry APE tags.
+
+use crate::{
+    error::Result,
+    meta::{Meta, MetaPosition},
+};
+use std::io::{Read, Seek, SeekFrom};
+
+pub mod monkeys_audio;
+pub mod mpeg;
+pub mod musepack;
+pub mod wavpack;
+
+static ID3V2_HEADER: &[u8] = b"ID3";
+
+/// Size of ID3v2 header and footer.
+const ID3V2_HEADER_SIZE: u64 = 10;
+
+/// Flag indicating that ID3v2 tag contains a footer.
+const ID3V2_HAS_FOOTER: u8 = 0x10;
+
+/// Kind of an audio container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerKind {
+    /// Monkey's Audio.
+    MonkeysAudio,
+    /// Musepack, SV7 or SV8.
+    Musepack,
+    /// WavPack.
+    WavPack,
+    /// MPEG audio, e.g. MP3.
+    Mp3,
+    /// Any other format.
+    Unknown,
+}
+
+impl ContainerKind {
+    /// Whether an APE tag at the start of a stream breaks decoders of the format.
+    pub fn forbids_header_tag(self) -> bool {
+        self != ContainerKind::Unknown
+    }
+}
+
+/// Detects a kind of an audio stream by its first bytes.
+///
+/// ID3v2 and APE tags at the start of a reader are skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::container::{detect_container, ContainerKind};
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// assert_eq!(detect_container(&mut file).unwrap(), ContainerKind::MonkeysAudio);
+/// ```
+pub fn detect_container<R: Read + Seek>(reader: &mut R) -> Result<ContainerKind> {
+    let start = stream_start(reader)?;
+    let mut magic = Vec::with_capacity(4);
+    reader.seek(SeekFrom::Start(start))?;
+    reader.take(4).read_to_end(&mut magic)?;
+    Ok(match magic.as_slice() {
+        b"MAC " => ContainerKind::MonkeysAudio,
+        b"MPCK" => ContainerKind::Musepack,
+        [b'M', b'P', b'+', version] if version & 0x0f == 7 => ContainerKind::Musepack,
+        b"wvpk" => ContainerKind::WavPack,
+        // MPEG frame sync: 11 bits set
+        [0xff, second, ..] if second & 0xe0 == 0xe0 => ContainerKind::Mp3,
+        _ => ContainerKind::Unknown,
+    })
+}
+
+/// Returns the offset of an audio stream,
+/// i.e. skips an ID3v2 tag and an APE tag at the start of a reader (if any).
+fn stream_start<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+    let start = skip_id3v2(reader)?;
+    match Meta::read_at(reader, start) {
+        Ok(meta) if meta.position == MetaPosition::Header => {
+            let (offset, size) = meta.region()?;
+            Ok(offset + size)
+        }
+        _ => Ok(start),
+    }
+}
+
+/// Returns the size of an ID3v2 tag at the start of a reader or zero.
+fn skip_id3v2<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+    let mut header = Vec::with_capacity(ID3V2_HEADER_SIZE as usize);
+    reader.seek(SeekFrom::Start(0))?;
+    reader.take(ID3V2_HEADER_SIZE).read_to_end(&mut header)?;
+    if header.len() < ID3V2_HEADER_SIZE as usize || &header[..3] != ID3V2_HEADER {
+        return Ok(0);
+    }
+    // The size is a synchsafe integer, i.e. the most significant bit of every byte is zero
+    let size = header[6..]
+        .iter()
+        .fold(0u64, |size, byte| (size << 7) | u64::from(byte & 0x7f));
+    let mut start = ID3V2_HEADER_SIZE + size;
+    if header[5] & ID3V2_HAS_FOOTER != 0 {
+        start += ID3V2_HEADER_SIZE;
+    }
+    Ok(start)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect_container, stream_start, ContainerKind};
+    use crate::{
+        tag::{relocate_tag, write_to},
+        ErrorKind, Item, MetaPosition, Tag,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn skip_id3v2() {
+        assert_eq!(stream_start(&mut Cursor::new(b"MAC ")).unwrap(), 0);
+        let mut data = b"ID3\x04\x00\x00\x00\x00\x01\x7f".to_vec();
+        assert_eq!(stream_start(&mut Cursor::new(&data)).unwrap(), 10 + 255);
+        data[5] = 0x10;
+        assert_eq!(stream_start(&mut Cursor::new(&data)).unwrap(), 20 + 255);
+    }
+
+    #[test]
+    fn detect() {
+        let kind = |data: &[u8]| detect_container(&mut Cursor::new(data)).unwrap();
+        assert_eq!(kind(b"MAC \x96\x0f"), ContainerKind::MonkeysAudio);
+        assert_eq!(kind(b"MPCK"), ContainerKind::Musepack);
+        assert_eq!(kind(b"MP+\x07"), ContainerKind::Musepack);
+        assert_eq!(kind(b"wvpk"), ContainerKind::WavPack);
+        assert_eq!(kind(b"\xff\xfb\x90\x00"), ContainerKind::Mp3);
+        assert_eq!(kind(b"ID3\x04\x00\x00\x00\x00\x00\x00\xff\xfb"), ContainerKind::Mp3);
+        assert_eq!(kind(b"fLaC"), ContainerKind::Unknown);
+        assert_eq!(kind(b""), ContainerKind::Unknown);
+    }
+
+    #[test]
+    fn refuse_header_tag() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let mut data = Cursor::new(b"MAC \x96\x0f".to_vec());
+        write_to(&tag, &mut data).unwrap();
+        let before = data.get_ref().clone();
+        assert_eq!(
+            relocate_tag(&mut data, MetaPosition::Header).unwrap_err().kind(),
+            ErrorKind::UnsafeTagPlacement
+        );
+        assert_eq!(data.get_ref(), &before);
+
+        // A stream following a tag at the start is detected as well
+        let mut data = Cursor::new(b"fLaC".to_vec());
+        write_to(&tag, &mut data).unwrap();
+        relocate_tag(&mut data, MetaPosition::Header).unwrap();
+        let len = data.get_ref().len();
+        data.get_mut()[len - 4..].copy_from_slice(b"wvpk");
+        assert_eq!(detect_container(&mut data).unwrap(), ContainerKind::WavPack);
+    }
+}
@@ -0,0 +1,212 @@
+//! Monkey's Audio (`.ape`) stream header.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::{container::monkeys_audio, read_from};
+//! use std::fs::File;
+//!
+//! let mut file = File::open("path/to/file.ape").unwrap();
+//! let info = monkeys_audio::read_stream_info(&mut file).unwrap();
+//! let tag = read_from(&mut file).unwrap();
+//! println!("{} Hz, {:?}, {} items", info.sample_rate, info.duration(), tag.iter().count());
+//! ```
+
+use super::stream_start;
+use crate::error::{ErrorKind, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    time::Duration,
+};
+
+static MAC_PREAMBLE: &[u8] = b"MAC ";
+
+/// The first version using a descriptor followed by a header.
+const DESCRIPTOR_VERSION: u16 = 3980;
+
+/// Compression level "extra high".
+const COMPRESSION_EXTRA_HIGH: u16 = 4000;
+
+/// Format flags of the old header.
+const FLAG_8_BIT: u16 = 1;
+const FLAG_24_BIT: u16 = 8;
+
+/// Technical properties of a Monkey's Audio stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// Version of the encoder, e.g. 3990 for 3.99.
+    pub version: u16,
+    /// Compression level: 1000 (fast), 2000 (normal), 3000 (high), 4000 (extra high) or 5000 (insane).
+    pub compression_level: u16,
+    /// Number of samples per second.
+    pub sample_rate: u32,
+    /// Number of channels.
+    pub channels: u16,
+    /// Number of bits per sample.
+    pub bits_per_sample: u16,
+    /// Number of samples per channel.
+    pub total_samples: u64,
+}
+
+impl StreamInfo {
+    /// Returns the duration of the stream.
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.total_samples as f64 / f64::from(self.sample_rate))
+    }
+}
+
+/// Reads the header of a Monkey's Audio stream.
+///
+/// ID3v2 and APE tags at the start of a reader are skipped.
+///
+/// # Errors
+///
+/// It is considered a error when a reader does not start with `MAC ` header.
+pub fn read_stream_info<R: Read + Seek>(reader: &mut R) -> Result<StreamInfo> {
+    let start = stream_start(reader)?;
+    reader.seek(SeekFrom::Start(start))?;
+    let mut preamble = [0; 4];
+    reader.read_exact(&mut preamble)?;
+    if preamble != MAC_PREAMBLE {
+        return Err(ErrorKind::BadStreamHeader.into());
+    }
+    let version = reader.read_u16::<LittleEndian>()?;
+    if version >= DESCRIPTOR_VERSION {
+        read_header(reader, start, version)
+    } else {
+        read_old_header(reader, version)
+    }
+}
+
+/// Reads a descriptor and a header written by 3.98 and later.
+fn read_header<R: Read + Seek>(reader: &mut R, start: u64, version: u16) -> Result<StreamInfo> {
+    // Skip padding
+    reader.read_u16::<LittleEndian>()?;
+    let descriptor_size = reader.read_u32::<LittleEndian>()?;
+    reader.seek(SeekFrom::Start(start + u64::from(descriptor_size)))?;
+    let compression_level = reader.read_u16::<LittleEndian>()?;
+    // Skip format flags
+    reader.read_u16::<LittleEndian>()?;
+    let blocks_per_frame = reader.read_u32::<LittleEndian>()?;
+    let final_frame_blocks = reader.read_u32::<LittleEndian>()?;
+    let total_frames = reader.read_u32::<LittleEndian>()?;
+    let bits_per_sample = reader.read_u16::<LittleEndian>()?;
+    let channels = reader.read_u16::<LittleEndian>()?;
+    let sample_rate = reader.read_u32::<LittleEndian>()?;
+    Ok(StreamInfo {
+        version,
+        compression_level,
+        sample_rate,
+        channels,
+        bits_per_sample,
+        total_samples: total_samples(total_frames, blocks_per_frame, final_frame_blocks),
+    })
+}
+
+/// Reads a header written by versions prior to 3.98.
+fn read_old_header<R: Read>(reader: &mut R, version: u16) -> Result<StreamInfo> {
+    let compression_level = reader.read_u16::<LittleEndian>()?;
+    let format_flags = reader.read_u16::<LittleEndian>()?;
+    let channels = reader.read_u16::<LittleEndian>()?;
+    let sample_rate = reader.read_u32::<LittleEndian>()?;
+    // Skip WAV header and terminating data sizes
+    reader.read_u32::<LittleEndian>()?;
+    reader.read_u32::<LittleEndian>()?;
+    let total_frames = reader.read_u32::<LittleEndian>()?;
+    let final_frame_blocks = reader.read_u32::<LittleEndian>()?;
+    let bits_per_sample = if format_flags & FLAG_8_BIT != 0 {
+        8
+    } else if format_flags & FLAG_24_BIT != 0 {
+        24
+    } else {
+        16
+    };
+    let blocks_per_frame = if version >= 3950 {
+        73728 * 4
+    } else if version >= 3900 || (version >= 3800 && compression_level == COMPRESSION_EXTRA_HIGH) {
+        73728
+    } else {
+        9216
+    };
+    Ok(StreamInfo {
+        version,
+        compression_level,
+        sample_rate,
+        channels,
+        bits_per_sample,
+        total_samples: total_samples(total_frames, blocks_per_frame, final_frame_blocks),
+    })
+}
+
+fn total_samples(total_frames: u32, blocks_per_frame: u32, final_frame_blocks: u32) -> u64 {
+    match total_frames {
+        0 => 0,
+        _ => u64::from(total_frames - 1) * u64::from(blocks_per_frame) + u64::from(final_frame_blocks),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_stream_info, StreamInfo};
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use std::{io::Cursor, time::Duration};
+
+    #[test]
+    fn new_header() {
+        let mut data = b"MAC ".to_vec();
+        data.write_u16::<LittleEndian>(3990).unwrap();
+        data.write_u16::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(52).unwrap();
+        data.resize(52, 0);
+        data.write_u16::<LittleEndian>(2000).unwrap();
+        data.write_u16::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(73728).unwrap();
+        data.write_u32::<LittleEndian>(14400).unwrap();
+        data.write_u32::<LittleEndian>(2).unwrap();
+        data.write_u16::<LittleEndian>(16).unwrap();
+        data.write_u16::<LittleEndian>(2).unwrap();
+        data.write_u32::<LittleEndian>(44100).unwrap();
+        let info = read_stream_info(&mut Cursor::new(data)).unwrap();
+        assert_eq!(
+            info,
+            StreamInfo {
+                version: 3990,
+                compression_level: 2000,
+                sample_rate: 44100,
+                channels: 2,
+                bits_per_sample: 16,
+                total_samples: 88128,
+            }
+        );
+        assert_eq!(info.duration(), Duration::from_secs_f64(88128.0 / 44100.0));
+    }
+
+    #[test]
+    fn old_header() {
+        let mut data = b"MAC ".to_vec();
+        data.write_u16::<LittleEndian>(3970).unwrap();
+        data.write_u16::<LittleEndian>(3000).unwrap();
+        data.write_u16::<LittleEndian>(8).unwrap();
+        data.write_u16::<LittleEndian>(1).unwrap();
+        data.write_u32::<LittleEndian>(48000).unwrap();
+        data.write_u32::<LittleEndian>(44).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(1).unwrap();
+        data.write_u32::<LittleEndian>(48000).unwrap();
+        let info = read_stream_info(&mut Cursor::new(data)).unwrap();
+        assert_eq!(info.bits_per_sample, 24);
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.total_samples, 48000);
+        assert_eq!(info.duration(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn not_monkeys_audio() {
+        let err = read_stream_info(&mut Cursor::new(b"fLaC\0\0\0\0".to_vec())).unwrap_err();
+        assert_eq!(err.to_string(), "invalid audio stream header");
+    }
+}
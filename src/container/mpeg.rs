@@ -0,0 +1,183 @@
+//! MPEG audio (`.mp3`) frame validation.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::container::mpeg::{check_frames, FrameCheck};
+//! use std::fs::File;
+//!
+//! let mut file = File::open("path/to/file.mp3").unwrap();
+//! if let FrameCheck::Junk { offset, len } = check_frames(&mut file).unwrap() {
+//!     println!("{} bytes of junk at {}", len, offset);
+//! }
+//! ```
+
+use crate::{error::Result, layout::audio_range};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Number of bytes preceding the end of audio data searched for frames.
+const SEARCH_WINDOW: u64 = 65536;
+
+/// Size of a frame header.
+const HEADER_SIZE: usize = 4;
+
+/// Bitrates in kbps indexed by a header field.
+const BITRATES_V1_L1: [u32; 15] = [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448];
+const BITRATES_V1_L2: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384];
+const BITRATES_V1_L3: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const BITRATES_V2_L1: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+const BITRATES_V2_L23: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// Sample rates of MPEG-1 indexed by a header field, halved for MPEG-2 and quartered for MPEG-2.5.
+const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Result of [`check_frames`](fn.check_frames.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameCheck {
+    /// The last frame ends exactly where metadata starts.
+    Clean,
+    /// The last frame starting at the given offset is truncated,
+    /// i.e. metadata starts inside the frame.
+    TruncatedFrame {
+        /// Offset of the frame header.
+        offset: u64,
+    },
+    /// Bytes which are not frames are located between the last frame and metadata.
+    Junk {
+        /// Offset of the first byte following the last frame.
+        offset: u64,
+        /// Number of bytes.
+        len: u64,
+    },
+    /// No frames are found near the end of audio data.
+    NoFrames,
+}
+
+/// Checks whether the end of audio data (i.e. the position of an APE tag) follows the last valid MPEG frame.
+///
+/// Frames are searched backwards from the end of audio data, see [`audio_range`](../../fn.audio_range.html).
+/// A frame is trusted only when it is followed by another one, so sync bytes in audio data are ignored.
+pub fn check_frames<R: Read + Seek>(reader: &mut R) -> Result<FrameCheck> {
+    let range = audio_range(reader)?;
+    let start = range.end.saturating_sub(SEARCH_WINDOW).max(range.start);
+    let mut buff = Vec::with_capacity((range.end - start) as usize);
+    reader.seek(SeekFrom::Start(start))?;
+    reader.take(range.end - start).read_to_end(&mut buff)?;
+
+    let end = buff.len();
+    let confirmed = (0..end.saturating_sub(HEADER_SIZE)).rev().find(|&pos| {
+        frame_len(&buff[pos..])
+            .map(|len| pos + len + HEADER_SIZE <= end && frame_len(&buff[pos + len..]).is_some())
+            .unwrap_or(false)
+    });
+    let mut pos = match confirmed {
+        Some(pos) => pos,
+        None => return Ok(FrameCheck::NoFrames),
+    };
+    while pos < end {
+        match frame_len(&buff[pos..]) {
+            Some(len) if pos + len > end => {
+                return Ok(FrameCheck::TruncatedFrame {
+                    offset: start + pos as u64,
+                })
+            }
+            Some(len) => pos += len,
+            None => {
+                return Ok(FrameCheck::Junk {
+                    offset: start + pos as u64,
+                    len: (end - pos) as u64,
+                })
+            }
+        }
+    }
+    Ok(FrameCheck::Clean)
+}
+
+/// Returns the length of a frame starting with a valid header.
+fn frame_len(data: &[u8]) -> Option<usize> {
+    if data.len() < HEADER_SIZE || data[0] != 0xff || data[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    // 3 - MPEG-1, 2 - MPEG-2, 0 - MPEG-2.5
+    let version = (data[1] >> 3) & 0x03;
+    // 3 - Layer I, 2 - Layer II, 1 - Layer III
+    let layer = (data[1] >> 1) & 0x03;
+    let bitrate_index = usize::from(data[2] >> 4);
+    let sample_rate_index = usize::from((data[2] >> 2) & 0x03);
+    let padding = u32::from((data[2] >> 1) & 0x01);
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+    let bitrates = match (version, layer) {
+        (3, 3) => &BITRATES_V1_L1,
+        (3, 2) => &BITRATES_V1_L2,
+        (3, _) => &BITRATES_V1_L3,
+        (_, 3) => &BITRATES_V2_L1,
+        _ => &BITRATES_V2_L23,
+    };
+    let bitrate = bitrates[bitrate_index] * 1000;
+    let sample_rate = match version {
+        3 => SAMPLE_RATES[sample_rate_index],
+        2 => SAMPLE_RATES[sample_rate_index] / 2,
+        _ => SAMPLE_RATES[sample_rate_index] / 4,
+    };
+    let len = match (version, layer) {
+        (_, 3) => (12 * bitrate / sample_rate + padding) * 4,
+        (3, _) | (_, 2) => 144 * bitrate / sample_rate + padding,
+        _ => 72 * bitrate / sample_rate + padding,
+    };
+    Some(len as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_frames, frame_len, FrameCheck};
+    use std::io::Cursor;
+
+    /// MPEG-1 Layer III, 128 kbps, 44100 Hz, no padding: 417 bytes.
+    fn frame() -> Vec<u8> {
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+        frame
+    }
+
+    #[test]
+    fn frame_lengths() {
+        assert_eq!(frame_len(&frame()), Some(417));
+        assert_eq!(frame_len(&[0xff, 0xfb, 0x92, 0x00]), Some(418));
+        // MPEG-2 Layer III, 64 kbps, 22050 Hz
+        assert_eq!(frame_len(&[0xff, 0xf3, 0x80, 0x00]), Some(208));
+        // MPEG-1 Layer I, 32 kbps, 48000 Hz
+        assert_eq!(frame_len(&[0xff, 0xff, 0x14, 0x00]), Some(32));
+        assert_eq!(frame_len(&[0xff, 0xfb, 0xf0, 0x00]), None);
+        assert_eq!(frame_len(&[0xff, 0xfb]), None);
+    }
+
+    #[test]
+    fn check() {
+        let frames = [frame(), frame(), frame()].concat();
+        assert_eq!(
+            check_frames(&mut Cursor::new(frames.clone())).unwrap(),
+            FrameCheck::Clean
+        );
+
+        let mut data = frames.clone();
+        data.truncate(data.len() - 10);
+        assert_eq!(
+            check_frames(&mut Cursor::new(data)).unwrap(),
+            FrameCheck::TruncatedFrame { offset: 834 }
+        );
+
+        let mut data = frames.clone();
+        data.extend_from_slice(&[1; 20]);
+        assert_eq!(
+            check_frames(&mut Cursor::new(data)).unwrap(),
+            FrameCheck::Junk { offset: 1251, len: 20 }
+        );
+
+        assert_eq!(
+            check_frames(&mut Cursor::new(vec![0; 1000])).unwrap(),
+            FrameCheck::NoFrames
+        );
+    }
+}
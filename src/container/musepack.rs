@@ -0,0 +1,239 @@
+//! Musepack (`.mpc`) stream header, both SV7 and SV8.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::{container::musepack, read_from};
+//! use std::fs::File;
+//!
+//! let mut file = File::open("path/to/file.mpc").unwrap();
+//! let info = musepack::read_stream_info(&mut file).unwrap();
+//! let tag = read_from(&mut file).unwrap();
+//! println!("SV{}, {:?}, {} items", info.version, info.duration(), tag.iter().count());
+//! ```
+
+use super::stream_start;
+use crate::error::{ErrorKind, Result};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    time::Duration,
+};
+
+static SV7_PREAMBLE: &[u8] = b"MP+";
+static SV8_PREAMBLE: &[u8] = b"MPCK";
+
+/// Number of samples in a frame.
+const FRAME_SAMPLES: u64 = 1152;
+
+/// Sample rates indexed by a header field.
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// Loudness reference used to store SV8 gains.
+const GAIN_REFERENCE: f32 = 64.82;
+
+/// Maximum number of SV8 packets read while searching for a stream header.
+const MAX_PACKETS: usize = 64;
+
+/// Technical properties of a Musepack stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamInfo {
+    /// Stream version, 7 or 8.
+    pub version: u8,
+    /// Number of samples per second.
+    pub sample_rate: u32,
+    /// Number of channels.
+    pub channels: u8,
+    /// Number of samples per channel.
+    pub total_samples: u64,
+    /// ReplayGain of the track in dB.
+    pub title_gain: Option<f32>,
+    /// ReplayGain of the album in dB.
+    pub album_gain: Option<f32>,
+}
+
+impl StreamInfo {
+    /// Returns the duration of the stream.
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.total_samples as f64 / f64::from(self.sample_rate))
+    }
+}
+
+/// Reads the header of a Musepack stream.
+///
+/// ID3v2 and APE tags at the start of a reader are skipped.
+///
+/// # Errors
+///
+/// It is considered a error when a reader does not start with SV7 or SV8 header.
+pub fn read_stream_info<R: Read + Seek>(reader: &mut R) -> Result<StreamInfo> {
+    let start = stream_start(reader)?;
+    reader.seek(SeekFrom::Start(start))?;
+    let mut preamble = [0; 4];
+    reader.read_exact(&mut preamble)?;
+    if preamble == SV8_PREAMBLE {
+        read_sv8(reader)
+    } else if &preamble[..3] == SV7_PREAMBLE && preamble[3] & 0x0f == 7 {
+        read_sv7(reader)
+    } else {
+        Err(ErrorKind::BadStreamHeader.into())
+    }
+}
+
+/// Reads the rest of SV7 header following the preamble.
+fn read_sv7<R: Read>(reader: &mut R) -> Result<StreamInfo> {
+    let frame_count = reader.read_u32::<LittleEndian>()?;
+    let flags = reader.read_u32::<LittleEndian>()?;
+    let title = reader.read_u32::<LittleEndian>()?;
+    let album = reader.read_u32::<LittleEndian>()?;
+    let last_frame = reader.read_u32::<LittleEndian>()?;
+    let total_samples = if last_frame >> 31 == 1 && frame_count > 0 {
+        // True gapless, the last frame contains fewer samples
+        u64::from(frame_count - 1) * FRAME_SAMPLES + u64::from((last_frame >> 20) & 0x07ff)
+    } else {
+        u64::from(frame_count) * FRAME_SAMPLES
+    };
+    // Gains are stored in 0.01 dB in the high halves
+    let gain = |value: u32| match (value >> 16) as i16 {
+        0 => None,
+        gain => Some(f32::from(gain) / 100.0),
+    };
+    Ok(StreamInfo {
+        version: 7,
+        sample_rate: SAMPLE_RATES[((flags >> 16) & 0x03) as usize],
+        channels: 2,
+        total_samples,
+        title_gain: gain(title),
+        album_gain: gain(album),
+    })
+}
+
+/// Reads SV8 packets up to the first audio packet.
+fn read_sv8<R: Read + Seek>(reader: &mut R) -> Result<StreamInfo> {
+    let mut info = None;
+    for _ in 0..MAX_PACKETS {
+        let mut key = [0; 2];
+        reader.read_exact(&mut key)?;
+        if &key == b"AP" || &key == b"SE" {
+            break;
+        }
+        let (size, size_len) = read_varint(reader)?;
+        let payload = size.checked_sub(2 + size_len).ok_or(ErrorKind::BadStreamHeader)?;
+        let next = reader.stream_position()? + payload;
+        match &key {
+            b"SH" => {
+                // Skip CRC
+                reader.read_u32::<BigEndian>()?;
+                if reader.read_u8()? != 8 {
+                    return Err(ErrorKind::BadStreamHeader.into());
+                }
+                let (sample_count, _) = read_varint(reader)?;
+                let (silence, _) = read_varint(reader)?;
+                let rate = reader.read_u8()?;
+                let channels = reader.read_u8()?;
+                info = Some(StreamInfo {
+                    version: 8,
+                    sample_rate: SAMPLE_RATES[usize::from((rate >> 5) & 0x03)],
+                    channels: (channels >> 4) + 1,
+                    total_samples: sample_count.saturating_sub(silence),
+                    title_gain: None,
+                    album_gain: None,
+                });
+            }
+            b"RG" => {
+                if let Some(ref mut info) = info {
+                    // Skip version
+                    reader.read_u8()?;
+                    let gain = |value: u16| match value {
+                        0 => None,
+                        gain => Some(GAIN_REFERENCE - f32::from(gain) / 256.0),
+                    };
+                    info.title_gain = gain(reader.read_u16::<BigEndian>()?);
+                    reader.read_u16::<BigEndian>()?;
+                    info.album_gain = gain(reader.read_u16::<BigEndian>()?);
+                }
+            }
+            _ => {}
+        }
+        reader.seek(SeekFrom::Start(next))?;
+    }
+    info.ok_or_else(|| ErrorKind::BadStreamHeader.into())
+}
+
+/// Reads a variable length integer, returns its value and the number of bytes read.
+fn read_varint<R: Read>(reader: &mut R) -> Result<(u64, u64)> {
+    let mut value = 0u64;
+    for len in 1..=9 {
+        let byte = reader.read_u8()?;
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok((value, len));
+        }
+    }
+    Err(ErrorKind::BadStreamHeader.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_stream_info;
+    use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+    use std::{io::Cursor, time::Duration};
+
+    #[test]
+    fn sv7() {
+        let mut data = b"MP+\x17".to_vec();
+        data.write_u32::<LittleEndian>(100).unwrap();
+        data.write_u32::<LittleEndian>(1 << 16).unwrap();
+        data.write_u32::<LittleEndian>(((-650i16 as u16 as u32) << 16) | 1000)
+            .unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>((1 << 31) | (576 << 20)).unwrap();
+        let info = read_stream_info(&mut Cursor::new(data)).unwrap();
+        assert_eq!(info.version, 7);
+        assert_eq!(info.sample_rate, 48000);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.total_samples, 99 * 1152 + 576);
+        assert_eq!(info.title_gain, Some(-6.5));
+        assert_eq!(info.album_gain, None);
+    }
+
+    #[test]
+    fn sv8() {
+        let mut data = b"MPCK".to_vec();
+        // Unknown packet
+        data.extend_from_slice(b"XX\x05\x00\x00");
+        // Stream header: 2 key + 1 size + 4 CRC + 1 version + 3 + 1 + 2
+        data.extend_from_slice(b"SH\x0e");
+        data.write_u32::<BigEndian>(0).unwrap();
+        data.write_u8(8).unwrap();
+        // 88200 samples
+        data.extend_from_slice(&[0x85, 0xb1, 0x08]);
+        data.write_u8(0).unwrap();
+        data.write_u8(0).unwrap();
+        data.write_u8(1 << 4).unwrap();
+        // ReplayGain
+        data.extend_from_slice(b"RG\x0c\x01");
+        data.write_u16::<BigEndian>((64.82 * 256.0) as u16).unwrap();
+        data.write_u16::<BigEndian>(0).unwrap();
+        data.write_u16::<BigEndian>(0).unwrap();
+        data.write_u16::<BigEndian>(0).unwrap();
+        data.extend_from_slice(b"AP\x02");
+        let info = read_stream_info(&mut Cursor::new(data)).unwrap();
+        assert_eq!(info.version, 8);
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.total_samples, 88200);
+        assert_eq!(info.duration(), Duration::from_secs(2));
+        assert!(info.title_gain.unwrap().abs() < 0.01);
+        assert_eq!(info.album_gain, None);
+    }
+
+    #[test]
+    fn not_musepack() {
+        let err = read_stream_info(&mut Cursor::new(b"MAC \0\0\0\0".to_vec())).unwrap_err();
+        assert_eq!(err.to_string(), "invalid audio stream header");
+    }
+}
@@ -0,0 +1,250 @@
+//! WavPack (`.wv`) block headers.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::container::wavpack;
+//! use std::fs::File;
+//!
+//! let mut file = File::open("path/to/file.wv").unwrap();
+//! let info = wavpack::read_stream_info(&mut file).unwrap();
+//! println!("{} Hz, {:?}", info.sample_rate, info.duration());
+//! assert!(wavpack::tag_placement_is_safe(&mut file).unwrap());
+//! ```
+
+use super::stream_start;
+use crate::{
+    error::{ErrorKind, Result},
+    layout::audio_range,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    time::Duration,
+};
+
+static BLOCK_PREAMBLE: &[u8] = b"wvpk";
+
+/// Size of a block header.
+const BLOCK_HEADER_SIZE: u64 = 32;
+
+/// Size of the preamble and the block size fields, which are not counted in the block size.
+const BLOCK_SIZE_OFFSET: u64 = 8;
+
+/// Supported stream versions.
+const MIN_VERSION: u16 = 0x402;
+const MAX_VERSION: u16 = 0x410;
+
+/// Total number of samples is unknown.
+const UNKNOWN_SAMPLES: u32 = u32::MAX;
+
+const FLAG_BYTES_PER_SAMPLE: u32 = 0x03;
+const FLAG_MONO: u32 = 0x04;
+const FLAG_FINAL_BLOCK: u32 = 0x1000;
+const FLAG_SAMPLE_RATE_SHIFT: u32 = 23;
+const FLAG_SAMPLE_RATE_MASK: u32 = 0x0f;
+
+/// Sample rates indexed by a header field, the last one means a custom rate.
+const SAMPLE_RATES: [u32; 15] = [
+    6000, 8000, 9600, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 64000, 88200, 96000, 192000,
+];
+
+/// Technical properties of a WavPack stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// Stream version, e.g. 0x407.
+    pub version: u16,
+    /// Number of samples per second, zero when a custom rate is used.
+    pub sample_rate: u32,
+    /// Number of channels.
+    pub channels: u16,
+    /// Number of bits per sample.
+    pub bits_per_sample: u16,
+    /// Number of samples per channel, if known.
+    pub total_samples: Option<u64>,
+}
+
+impl StreamInfo {
+    /// Returns the duration of the stream, if known.
+    pub fn duration(&self) -> Option<Duration> {
+        match (self.total_samples, self.sample_rate) {
+            (Some(samples), rate) if rate > 0 => Some(Duration::from_secs_f64(samples as f64 / f64::from(rate))),
+            _ => None,
+        }
+    }
+}
+
+/// A WavPack block header.
+#[derive(Debug)]
+struct BlockHeader {
+    size: u64,
+    version: u16,
+    total_samples: u32,
+    total_samples_high: u8,
+    flags: u32,
+}
+
+impl BlockHeader {
+    /// Reads a block header at the current position.
+    fn read<R: Read>(reader: &mut R) -> Result<BlockHeader> {
+        let mut preamble = [0; 4];
+        reader.read_exact(&mut preamble)?;
+        if preamble != BLOCK_PREAMBLE {
+            return Err(ErrorKind::BadStreamHeader.into());
+        }
+        let size = u64::from(reader.read_u32::<LittleEndian>()?) + BLOCK_SIZE_OFFSET;
+        let version = reader.read_u16::<LittleEndian>()?;
+        if !(MIN_VERSION..=MAX_VERSION).contains(&version) || size < BLOCK_HEADER_SIZE {
+            return Err(ErrorKind::BadStreamHeader.into());
+        }
+        // Skip the high bits of the block index
+        reader.read_u8()?;
+        let total_samples_high = reader.read_u8()?;
+        let total_samples = reader.read_u32::<LittleEndian>()?;
+        // Skip block index and number of samples in the block
+        reader.read_u32::<LittleEndian>()?;
+        reader.read_u32::<LittleEndian>()?;
+        let flags = reader.read_u32::<LittleEndian>()?;
+        // Skip CRC
+        reader.read_u32::<LittleEndian>()?;
+        Ok(BlockHeader {
+            size,
+            version,
+            total_samples,
+            total_samples_high,
+            flags,
+        })
+    }
+}
+
+/// Reads the first block headers of a WavPack stream.
+///
+/// ID3v2 and APE tags at the start of a reader are skipped.
+/// Multichannel streams consist of several blocks, channels of all of them are counted.
+///
+/// # Errors
+///
+/// It is considered a error when a reader does not start with a WavPack block.
+pub fn read_stream_info<R: Read + Seek>(reader: &mut R) -> Result<StreamInfo> {
+    let mut offset = stream_start(reader)?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let first = BlockHeader::read(reader)?;
+    let sample_rate = SAMPLE_RATES
+        .get(((first.flags >> FLAG_SAMPLE_RATE_SHIFT) & FLAG_SAMPLE_RATE_MASK) as usize)
+        .copied()
+        .unwrap_or(0);
+    let total_samples = match first.total_samples {
+        UNKNOWN_SAMPLES => None,
+        samples => Some((u64::from(first.total_samples_high) << 32) | u64::from(samples)),
+    };
+    let mut channels = 0;
+    let mut header = first;
+    loop {
+        channels += if header.flags & FLAG_MONO != 0 { 1 } else { 2 };
+        offset += header.size;
+        if header.flags & FLAG_FINAL_BLOCK != 0 {
+            break;
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        header = BlockHeader::read(reader)?;
+    }
+    reader.seek(SeekFrom::Start(offset))?;
+    Ok(StreamInfo {
+        version: header.version,
+        sample_rate,
+        channels,
+        bits_per_sample: ((header.flags & FLAG_BYTES_PER_SAMPLE) as u16 + 1) * 8,
+        total_samples,
+    })
+}
+
+/// Checks whether an APE tag may be appended to the end of a WavPack stream or removed from it.
+///
+/// Walks all blocks preceding metadata (see [`audio_range`](../../fn.audio_range.html))
+/// and returns `false` when the last block is truncated or followed by bytes
+/// which are not a block, i.e. a tag would land inside a block.
+pub fn tag_placement_is_safe<R: Read + Seek>(reader: &mut R) -> Result<bool> {
+    let end = audio_range(reader)?.end;
+    let mut offset = stream_start(reader)?;
+    while offset < end {
+        if end - offset < BLOCK_HEADER_SIZE {
+            return Ok(false);
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        match BlockHeader::read(reader) {
+            Ok(header) => offset += header.size,
+            Err(err) if err.kind() == ErrorKind::BadStreamHeader => return Ok(false),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(offset == end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_stream_info, tag_placement_is_safe, StreamInfo};
+    use crate::{tag::write_to, Item, Tag};
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use std::{io::Cursor, time::Duration};
+
+    fn block(data: &mut Vec<u8>, payload: u32, flags: u32) {
+        data.extend_from_slice(b"wvpk");
+        data.write_u32::<LittleEndian>(24 + payload).unwrap();
+        data.write_u16::<LittleEndian>(0x407).unwrap();
+        data.write_u8(0).unwrap();
+        data.write_u8(0).unwrap();
+        data.write_u32::<LittleEndian>(441000).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(22050).unwrap();
+        data.write_u32::<LittleEndian>(flags).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.resize(data.len() + payload as usize, 0);
+    }
+
+    #[test]
+    fn multichannel() {
+        let mut data = Vec::new();
+        // 16 bit, 44100 Hz, stereo + mono
+        block(&mut data, 10, (9 << 23) | 0x01);
+        block(&mut data, 10, (9 << 23) | 0x01 | 0x04 | 0x1000);
+        let info = read_stream_info(&mut Cursor::new(data)).unwrap();
+        assert_eq!(
+            info,
+            StreamInfo {
+                version: 0x407,
+                sample_rate: 44100,
+                channels: 3,
+                bits_per_sample: 16,
+                total_samples: Some(441000),
+            }
+        );
+        assert_eq!(info.duration(), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn tag_placement() {
+        let mut data = Vec::new();
+        block(&mut data, 10, 0x1000);
+        block(&mut data, 10, 0x1000);
+        let mut data = Cursor::new(data);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut data).unwrap();
+        assert!(tag_placement_is_safe(&mut data).unwrap());
+
+        // Truncated block followed by a tag
+        let mut data = Vec::new();
+        block(&mut data, 10, 0x1000);
+        block(&mut data, 10, 0x1000);
+        data.truncate(data.len() - 5);
+        let mut data = Cursor::new(data);
+        write_to(&tag, &mut data).unwrap();
+        assert!(!tag_placement_is_safe(&mut data).unwrap());
+    }
+
+    #[test]
+    fn not_wavpack() {
+        let err = read_stream_info(&mut Cursor::new(vec![0; 64])).unwrap_err();
+        assert_eq!(err.to_string(), "invalid audio stream header");
+    }
+}
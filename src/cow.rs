@@ -0,0 +1,420 @@
+//! Tags borrowing values from a buffer.
+//!
+//! # Examples
+//!
+//! ```
+//! use ape::{CowItem, CowTag, CowValue, Item, Tag};
+//!
+//! let mut tag = Tag::new();
+//! tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+//! tag.set_item(Item::from_binary("cover", vec![0; 1024]).unwrap());
+//! let bytes = tag.to_bytes().unwrap();
+//!
+//! // Only the edited item is owned, the cover is copied from the buffer on write
+//! let mut tag = CowTag::parse(&bytes).unwrap();
+//! tag.set_item(CowItem::new("artist", CowValue::Text("Another Artist".into())).unwrap());
+//! let bytes = tag.to_bytes().unwrap();
+//! assert_eq!(Tag::parse(&bytes).unwrap().iter().count(), 2);
+//! ```
+
+use crate::{
+    error::{ErrorKind, Result},
+    item::{validate_key, write_item, Item, ItemValue, KIND_BINARY, KIND_LOCATOR, KIND_RESERVED, KIND_TEXT},
+    meta::{Layout, Meta, APE_VERSION, APE_VERSION_1},
+    options::ReadOptions,
+    tag::{assemble, decode_text_value, item_count, read_item_header, size_order, Tag},
+};
+use std::{borrow::Cow, io::Cursor, mem, ops::Range, str};
+
+/// A value which is either borrowed or owned, see [`ItemValue`](enum.ItemValue.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CowValue<'a> {
+    /// Binary data.
+    Binary(Cow<'a, [u8]>),
+    /// A link to external stored information.
+    Locator(Cow<'a, str>),
+    /// UTF-8 text.
+    Text(Cow<'a, str>),
+    /// Raw bytes of an item with the reserved type.
+    Reserved(Cow<'a, [u8]>),
+}
+
+/// An item which key and value are either borrowed or owned, see [`Item`](struct.Item.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CowItem<'a> {
+    /// Item key.
+    pub key: Cow<'a, str>,
+    /// Item value.
+    pub value: CowValue<'a>,
+    /// Whether the item is marked as read only, see [`Item::read_only`](struct.Item.html#structfield.read_only).
+    pub read_only: bool,
+}
+
+impl<'a> CowItem<'a> {
+    /// Creates an item.
+    ///
+    /// # Errors
+    ///
+    /// Keys are validated the same way as by [`Item`](struct.Item.html) constructors.
+    pub fn new<K: Into<Cow<'a, str>>>(key: K, value: CowValue<'a>) -> Result<CowItem<'a>> {
+        let key = key.into();
+        validate_key(&key)?;
+        Ok(CowItem {
+            key,
+            value,
+            read_only: false,
+        })
+    }
+
+    /// Whether the value is borrowed from a buffer.
+    pub fn is_borrowed(&self) -> bool {
+        matches!(
+            self.value,
+            CowValue::Binary(Cow::Borrowed(_))
+                | CowValue::Locator(Cow::Borrowed(_))
+                | CowValue::Text(Cow::Borrowed(_))
+                | CowValue::Reserved(Cow::Borrowed(_))
+        )
+    }
+
+    /// Converts the item to an owned one, borrowed data is copied.
+    pub fn into_owned(self) -> Item {
+        let value = match self.value {
+            CowValue::Binary(value) => ItemValue::Binary(value.into()),
+            CowValue::Locator(value) => ItemValue::Locator(value.into_owned()),
+            CowValue::Text(value) => ItemValue::Text(value.into_owned()),
+            CowValue::Reserved(value) => ItemValue::Reserved(value.into_owned()),
+        };
+        Item {
+            key: self.key.into(),
+            value,
+            read_only: self.read_only,
+        }
+    }
+
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let read_only = u32::from(self.read_only);
+        match self.value {
+            CowValue::Binary(ref value) => write_item(&mut bytes, &self.key, KIND_BINARY << 1 | read_only, value)?,
+            CowValue::Locator(ref value) => {
+                write_item(&mut bytes, &self.key, KIND_LOCATOR << 1 | read_only, value.as_bytes())?
+            }
+            CowValue::Text(ref value) => {
+                write_item(&mut bytes, &self.key, KIND_TEXT << 1 | read_only, value.as_bytes())?
+            }
+            CowValue::Reserved(ref value) => write_item(&mut bytes, &self.key, KIND_RESERVED << 1 | read_only, value)?,
+        }
+        Ok(bytes)
+    }
+}
+
+impl From<Item> for CowItem<'_> {
+    fn from(item: Item) -> Self {
+        let value = match item.value {
+            ItemValue::Binary(value) => CowValue::Binary(Cow::Owned(value.to_vec())),
+            ItemValue::Locator(value) => CowValue::Locator(Cow::Owned(value)),
+            ItemValue::Text(value) => CowValue::Text(Cow::Owned(value)),
+            ItemValue::Reserved(value) => CowValue::Reserved(Cow::Owned(value)),
+        };
+        CowItem {
+            key: Cow::Owned(item.key.to_string()),
+            value,
+            read_only: item.read_only,
+        }
+    }
+}
+
+/// A tag parsed from a buffer without copying items, see [`Tag`](struct.Tag.html).
+///
+/// Items may be inspected, replaced or removed, only new items are owned.
+#[derive(Clone, Debug, Default)]
+pub struct CowTag<'a>(Vec<CowItem<'a>>);
+
+impl<'a> CowTag<'a> {
+    /// Parses a tag from a buffer containing either a whole file or the tag only.
+    ///
+    /// Text and Locator values must be valid UTF-8,
+    /// see [`ReadOptions::parse_cow`](struct.ReadOptions.html#method.parse_cow) to read other ones.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_from_path`](fn.read_from_path.html)
+    pub fn parse(bytes: &'a [u8]) -> Result<CowTag<'a>> {
+        ReadOptions::new().parse_cow(bytes)
+    }
+
+    /// Returns a first found item by key.
+    pub fn item<K: AsRef<str>>(&self, key: K) -> Option<&CowItem<'a>> {
+        let key = key.as_ref();
+        self.0.iter().find(|item| item.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Sets a new item.
+    ///
+    /// If there is an item with the same key, it will be removed.
+    pub fn set_item(&mut self, item: CowItem<'a>) {
+        self.remove_items(&item.key);
+        self.add_item(item)
+    }
+
+    /// Adds a new item.
+    ///
+    /// Unlike `set_item`, existing items with the same key are not removed.
+    pub fn add_item(&mut self, item: CowItem<'a>) {
+        self.0.push(item)
+    }
+
+    /// Removes all items by key.
+    ///
+    /// Returns a number of deleted items
+    pub fn remove_items<K: AsRef<str>>(&mut self, key: K) -> usize {
+        let key = key.as_ref();
+        let count = self.0.len();
+        self.0.retain(|item| !item.key.eq_ignore_ascii_case(key));
+        count - self.0.len()
+    }
+
+    /// Returns an iterator over the tag.
+    pub fn iter(&self) -> std::slice::Iter<'_, CowItem<'a>> {
+        self.0.iter()
+    }
+
+    /// Serializes the tag to bytes, the same ones [`Tag::to_bytes`](struct.Tag.html#method.to_bytes) returns.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut items = self.0.iter().map(CowItem::to_vec).collect::<Result<Vec<_>>>()?;
+        let order = size_order(
+            self.0
+                .iter()
+                .zip(&items)
+                .map(|(item, bytes)| (item.key.as_ref(), bytes.len() as u64)),
+        );
+        let items = order.into_iter().map(|idx| mem::take(&mut items[idx])).collect();
+        assemble(items, APE_VERSION, 0, Layout::default())
+    }
+
+    /// Converts the tag to an owned one, borrowed data is copied.
+    pub fn into_owned(self) -> Tag {
+        Tag(self.0.into_iter().map(CowItem::into_owned).collect(), false)
+    }
+}
+
+/// Location of an item within a buffer.
+#[derive(Debug)]
+pub(crate) struct RawItem {
+    pub(crate) key: Range<usize>,
+    pub(crate) kind: u32,
+    pub(crate) value: Range<usize>,
+    pub(crate) read_only: bool,
+    /// A Text or Locator value which is not UTF-8 and can not be borrowed.
+    pub(crate) decoded: Option<String>,
+}
+
+impl RawItem {
+    /// Borrows the validated item from the buffer it was located in.
+    fn borrow(self, data: &[u8]) -> CowItem<'_> {
+        // Keys and text values have been validated by `locate_items`
+        let text = |range: Range<usize>| Cow::Borrowed(str::from_utf8(&data[range]).unwrap_or_default());
+        let value = match (self.kind, self.decoded) {
+            (KIND_BINARY, _) => CowValue::Binary(Cow::Borrowed(&data[self.value])),
+            (KIND_RESERVED, _) => CowValue::Reserved(Cow::Borrowed(&data[self.value])),
+            (KIND_LOCATOR, Some(decoded)) => CowValue::Locator(Cow::Owned(decoded)),
+            (KIND_LOCATOR, None) => CowValue::Locator(text(self.value)),
+            (_, Some(decoded)) => CowValue::Text(Cow::Owned(decoded)),
+            (_, None) => CowValue::Text(text(self.value)),
+        };
+        CowItem {
+            key: text(self.key),
+            value,
+            read_only: self.read_only,
+        }
+    }
+}
+
+pub(crate) fn parse_cow<'a>(bytes: &'a [u8], options: &ReadOptions) -> Result<CowTag<'a>> {
+    Ok(CowTag(
+        locate_items(bytes, options)?
+            .into_iter()
+            .map(|raw| raw.borrow(bytes))
+            .collect(),
+    ))
+}
+
+/// Locates and validates items of tags in a buffer.
+pub(crate) fn locate_items(data: &[u8], options: &ReadOptions) -> Result<Vec<RawItem>> {
+    let all = Meta::read_all(&mut Cursor::new(data), options.garbage_window())?;
+    let mut items = read_items(data, &all[0], options)?;
+    if let Some(start) = all.get(1) {
+        // The tag at the end takes precedence, only items with other keys are taken from the start
+        let count = items.len();
+        for item in read_items(data, start, options)? {
+            let key = &data[item.key.clone()];
+            if !items[..count]
+                .iter()
+                .any(|x| data[x.key.clone()].eq_ignore_ascii_case(key))
+            {
+                items.push(item);
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Locates and validates items of the tag described by the metadata.
+fn read_items(data: &[u8], meta: &Meta, options: &ReadOptions) -> Result<Vec<RawItem>> {
+    let end = (meta.end_pos as usize).min(data.len());
+    let mut reader = Cursor::new(&data[..end]);
+    let item_count = item_count(&mut reader, meta, options)?;
+    reader.set_position(meta.start_pos);
+    let mut items = Vec::with_capacity(meta.items_capacity(item_count));
+    for index in 0..item_count {
+        let offset = reader.position();
+        items.push(locate_item(&mut reader, meta.version, options).map_err(|err| err.at_item(index, offset))?);
+    }
+    // Items may be followed by zero padding
+    if data[reader.position() as usize..end].iter().any(|&byte| byte != 0) {
+        return Err(ErrorKind::BadTagSize.into());
+    }
+    Ok(items)
+}
+
+/// Locates and validates an item at the position of the reader, which is moved past the item.
+fn locate_item(reader: &mut Cursor<&[u8]>, version: u32, options: &ReadOptions) -> Result<RawItem> {
+    let start = reader.position() as usize;
+    let header = read_item_header(reader, version, options)?;
+    let key = start + 8..start + 8 + header.key.len();
+    let data = *reader.get_ref();
+    let value = reader.position() as usize
+        ..(reader.position() as usize)
+            .checked_add(header.size as usize)
+            .filter(|&value_end| value_end <= data.len())
+            .ok_or(ErrorKind::BadTagSize)?;
+    reader.set_position(value.end as u64);
+
+    let decoded = match header.kind() {
+        KIND_LOCATOR | KIND_TEXT => {
+            let text = &data[value.clone()];
+            // Only UTF-8 text can be borrowed, other encodings are decoded into an owned string
+            match str::from_utf8(text) {
+                Ok(_) if version != APE_VERSION_1 || text.is_ascii() => None,
+                _ => Some(decode_text_value(text.to_vec(), version, options)?),
+            }
+        }
+        _ => None,
+    };
+    Ok(RawItem {
+        key,
+        kind: header.kind(),
+        value,
+        read_only: header.is_read_only(),
+        decoded,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CowItem, CowTag, CowValue};
+    use crate::{
+        error::ErrorKind,
+        item::Item,
+        options::{ApeVersion, ReadOptions, WriteOptions},
+        tag::Tag,
+    };
+    use std::{borrow::Cow, io::Cursor};
+
+    #[test]
+    fn huge_item_count() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        let mut bytes = tag.to_bytes().unwrap();
+        let count = bytes.len() - 16;
+        bytes[count..count + 4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert!(CowTag::parse(&bytes).is_err());
+        assert!(ReadOptions::new().parse_cow(&bytes).is_err());
+    }
+
+    #[test]
+    fn parse_and_modify() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_locator("url", "http://example.com").unwrap());
+        tag.add_item(Item::from_binary("cover", vec![0, 1, 2]).unwrap());
+        let bytes = [vec![1; 100], tag.to_bytes().unwrap()].concat();
+
+        let mut cow = CowTag::parse(&bytes).unwrap();
+        assert!(cow.iter().all(CowItem::is_borrowed));
+        assert_eq!(
+            cow.item("COVER").unwrap().value,
+            CowValue::Binary(Cow::Borrowed(&[0, 1, 2]))
+        );
+        cow.set_item(CowItem::new("title", CowValue::Text("New Title".into())).unwrap());
+        assert_eq!(cow.remove_items("url"), 1);
+        cow.add_item(Item::from_text("artist", "Artist").unwrap().into());
+        assert!(CowItem::new("x", CowValue::Text("".into())).is_err());
+
+        let result = Tag::parse(&cow.to_bytes().unwrap()).unwrap();
+        assert!(result.has_same_items(&cow.clone().into_owned()));
+        assert_eq!(
+            result.item("title").unwrap(),
+            &Item::from_text("title", "New Title").unwrap()
+        );
+        assert_eq!(result.iter().count(), 3);
+
+        assert_eq!(CowTag::parse(&[1; 100]).unwrap_err().kind(), ErrorKind::TagNotFound);
+    }
+
+    #[test]
+    fn parse_ape_version_1() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Café").unwrap());
+        tag.add_item(Item::from_text("artist", "Artist").unwrap());
+        let mut data = Cursor::new(Vec::new());
+        WriteOptions::new()
+            .version(ApeVersion::V1)
+            .write_to(&tag, &mut data)
+            .unwrap();
+
+        let bytes = data.into_inner();
+        let cow = CowTag::parse(&bytes).unwrap();
+        // ISO-8859-1 text is decoded, ASCII text is borrowed
+        assert_eq!(
+            cow.item("title").unwrap().value,
+            CowValue::Text(Cow::Owned("Café".into()))
+        );
+        assert!(cow.item("artist").unwrap().is_borrowed());
+        assert!(cow.into_owned().has_same_items(&tag));
+    }
+
+    #[test]
+    fn read_only() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::builder("title").text("Title").read_only(true).build().unwrap());
+        tag.add_item(
+            Item::builder("cover")
+                .binary(vec![0, 1, 2])
+                .read_only(true)
+                .build()
+                .unwrap(),
+        );
+        tag.add_item(Item::from_text("artist", "Artist").unwrap());
+        let bytes = tag.to_bytes().unwrap();
+
+        let mut cow = CowTag::parse(&bytes).unwrap();
+        assert!(cow.item("title").unwrap().read_only);
+        assert!(cow.item("cover").unwrap().read_only);
+        assert!(!cow.item("artist").unwrap().read_only);
+        assert!(cow.clone().into_owned().has_same_items(&tag));
+
+        // Only the edited item is serialized again, the others are copied
+        cow.set_item(
+            Item::builder("artist")
+                .text("Other")
+                .read_only(true)
+                .build()
+                .unwrap()
+                .into(),
+        );
+        let result = Tag::parse(&cow.to_bytes().unwrap()).unwrap();
+        assert!(result.iter().all(|item| item.read_only));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    item::{Item, ItemValue},
+    key::ItemKey,
+    tag::Tag,
+};
+use std::time::Duration;
+
+/// Number of CD frames per second.
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// A minimal representation of an embedded cuesheet.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cuesheet {
+    /// Album title.
+    pub title: Option<String>,
+    /// Album performer.
+    pub performer: Option<String>,
+    /// Tracks in order of appearance.
+    pub tracks: Vec<CueTrack>,
+}
+
+/// A track of a cuesheet.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CueTrack {
+    /// Track number.
+    pub number: u32,
+    /// Track title.
+    pub title: Option<String>,
+    /// Track performer.
+    pub performer: Option<String>,
+    /// Index points of the track.
+    pub indexes: Vec<CueIndex>,
+}
+
+/// An index point of a track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CueIndex {
+    /// Index number, `1` is the start of a track and `0` is the start of a pregap.
+    pub number: u32,
+    /// Position from the start of the file in CD frames (1/75 of a second).
+    pub frames: u64,
+}
+
+impl CueIndex {
+    /// Returns the position from the start of the file.
+    pub fn time(&self) -> Duration {
+        // Whole seconds are split off, so a large number of frames does not overflow
+        let micros = self.frames % FRAMES_PER_SECOND * 1_000_000 / FRAMES_PER_SECOND;
+        Duration::from_secs(self.frames / FRAMES_PER_SECOND) + Duration::from_micros(micros)
+    }
+}
+
+impl CueTrack {
+    /// Returns the start of the track (index 01).
+    pub fn start(&self) -> Option<Duration> {
+        self.indexes.iter().find(|index| index.number == 1).map(CueIndex::time)
+    }
+}
+
+impl Cuesheet {
+    /// Parses a cuesheet.
+    ///
+    /// Only `TITLE`, `PERFORMER`, `TRACK` and `INDEX` commands are taken into account,
+    /// other commands and malformed lines are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::Cuesheet;
+    /// use std::time::Duration;
+    ///
+    /// let cuesheet = Cuesheet::parse("FILE \"album.ape\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 03:15:30\n");
+    /// assert_eq!(cuesheet.tracks.len(), 2);
+    /// assert_eq!(cuesheet.tracks[1].start(), Some(Duration::from_millis(195400)));
+    /// ```
+    pub fn parse(cuesheet: &str) -> Cuesheet {
+        let mut result = Cuesheet::default();
+        for line in cuesheet.lines() {
+            let args = split_args(line);
+            let value = args.get(1).cloned();
+            match args.first().map(|cmd| cmd.to_ascii_uppercase()).as_deref() {
+                Some("TITLE") => match result.tracks.last_mut() {
+                    Some(track) => track.title = value,
+                    None => result.title = value,
+                },
+                Some("PERFORMER") => match result.tracks.last_mut() {
+                    Some(track) => track.performer = value,
+                    None => result.performer = value,
+                },
+                Some("TRACK") => {
+                    if let Some(number) = value.and_then(|value| value.parse().ok()) {
+                        result.tracks.push(CueTrack {
+                            number,
+                            ..CueTrack::default()
+                        });
+                    }
+                }
+                Some("INDEX") => {
+                    let number = value.and_then(|value| value.parse().ok());
+                    let frames = args.get(2).and_then(|value| parse_msf(value));
+                    if let (Some(track), Some(number), Some(frames)) = (result.tracks.last_mut(), number, frames) {
+                        track.indexes.push(CueIndex { number, frames });
+                    }
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+/// Splits a line into arguments taking quotes into account.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            args.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut arg = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+            args.push(arg);
+        }
+    }
+    args
+}
+
+/// Parses a position in `mm:ss:ff` format.
+fn parse_msf(value: &str) -> Option<u64> {
+    let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+    match (parts.next()??, parts.next()??, parts.next()??, parts.next()) {
+        (minutes, seconds, frames, None) if seconds < 60 && frames < FRAMES_PER_SECOND => minutes
+            .checked_mul(60)?
+            .checked_add(seconds)?
+            .checked_mul(FRAMES_PER_SECOND)?
+            .checked_add(frames),
+        _ => None,
+    }
+}
+
+impl Tag {
+    /// Returns a cuesheet stored in the Cuesheet item.
+    pub fn cuesheet(&self) -> Option<&str> {
+        match self.item(ItemKey::Cuesheet)?.value {
+            ItemValue::Text(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Sets a cuesheet to the Cuesheet item.
+    pub fn set_cuesheet<S: Into<String>>(&mut self, cuesheet: S) {
+        self.set_item(Item {
+            key: ItemKey::Cuesheet.into(),
+            value: ItemValue::Text(cuesheet.into()),
+            read_only: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_msf, CueIndex, Cuesheet};
+    use crate::tag::Tag;
+    use std::time::Duration;
+
+    static CUESHEET: &str = r#"REM GENRE Rock
+PERFORMER "Album Artist"
+TITLE "Album Title"
+FILE "album.ape" WAVE
+  TRACK 01 AUDIO
+    TITLE "First Track"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second Track"
+    PERFORMER Guest
+    INDEX 00 04:01:70
+    INDEX 01 04:03:00
+    INDEX 02 bad
+"#;
+
+    #[test]
+    fn parse() {
+        let cuesheet = Cuesheet::parse(CUESHEET);
+        assert_eq!(cuesheet.title.as_deref(), Some("Album Title"));
+        assert_eq!(cuesheet.performer.as_deref(), Some("Album Artist"));
+        assert_eq!(cuesheet.tracks.len(), 2);
+        let track = &cuesheet.tracks[0];
+        assert_eq!(track.number, 1);
+        assert_eq!(track.title.as_deref(), Some("First Track"));
+        assert_eq!(track.performer, None);
+        assert_eq!(track.start(), Some(Duration::ZERO));
+        let track = &cuesheet.tracks[1];
+        assert_eq!(track.number, 2);
+        assert_eq!(track.performer.as_deref(), Some("Guest"));
+        assert_eq!(
+            track.indexes,
+            vec![
+                CueIndex {
+                    number: 0,
+                    frames: 241 * 75 + 70
+                },
+                CueIndex {
+                    number: 1,
+                    frames: 243 * 75
+                },
+            ]
+        );
+        assert_eq!(track.start(), Some(Duration::from_secs(243)));
+    }
+
+    #[test]
+    fn parse_oversized_index() {
+        let cuesheet = Cuesheet::parse(
+            "TRACK 01 AUDIO
+INDEX 01 99999999999999999:00:00
+INDEX 02 18446744073709551615:59:74
+",
+        );
+        assert!(cuesheet.tracks[0].indexes.is_empty());
+        assert_eq!(parse_msf("1000000000000:00:01"), Some(4_500_000_000_000_001));
+        let index = CueIndex {
+            number: 1,
+            frames: u64::MAX,
+        };
+        assert_eq!(index.time().as_secs(), u64::MAX / 75);
+    }
+
+    #[test]
+    fn tag_cuesheet() {
+        let mut tag = Tag::new();
+        assert!(tag.cuesheet().is_none());
+        tag.set_cuesheet(CUESHEET);
+        assert_eq!(&*tag.item("cuesheet").unwrap().key, "Cuesheet");
+        assert_eq!(tag.cuesheet(), Some(CUESHEET));
+    }
+}
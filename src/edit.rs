@@ -0,0 +1,270 @@
+use crate::{item::Item, tag::Tag};
+
+/// An editing session over a tag with undo/redo support.
+///
+/// Every change is recorded in a command log,
+/// so it can be undone and redone in the same order.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{EditSession, Item, Tag};
+///
+/// let mut session = EditSession::new(Tag::new());
+/// session.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// assert!(session.is_dirty());
+/// session.undo();
+/// assert!(session.tag().item("artist").is_none());
+/// session.redo();
+/// assert!(session.tag().item("artist").is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EditSession {
+    tag: Tag,
+    done: Vec<Edit>,
+    undone: Vec<Edit>,
+    // Length of the command log when the tag was marked as clean.
+    clean: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Set { item: Item, removed: Vec<(usize, Item)> },
+    Add { item: Item },
+    Remove { key: String, removed: Vec<(usize, Item)> },
+    Move { from: usize, to: usize },
+}
+
+impl EditSession {
+    /// Starts a new session over the given tag.
+    pub fn new(tag: Tag) -> EditSession {
+        EditSession {
+            tag,
+            done: Vec::new(),
+            undone: Vec::new(),
+            clean: Some(0),
+        }
+    }
+
+    /// Returns the current state of the tag.
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// Finishes the session and returns the tag.
+    pub fn into_tag(self) -> Tag {
+        self.tag
+    }
+
+    /// Sets a new item, see [`Tag::set_item`](struct.Tag.html#method.set_item).
+    pub fn set_item(&mut self, item: Item) {
+        self.apply(Edit::Set {
+            item,
+            removed: Vec::new(),
+        });
+    }
+
+    /// Adds a new item, see [`Tag::add_item`](struct.Tag.html#method.add_item).
+    pub fn add_item(&mut self, item: Item) {
+        self.apply(Edit::Add { item });
+    }
+
+    /// Removes all items by key.
+    ///
+    /// Returns a number of deleted items.
+    /// Nothing is recorded when there are no items with the given key.
+    pub fn remove_items<K: AsRef<str>>(&mut self, key: K) -> usize {
+        let key = key.as_ref();
+        let count = self.tag.items(key).len();
+        if count > 0 {
+            self.apply(Edit::Remove {
+                key: key.to_string(),
+                removed: Vec::new(),
+            });
+        }
+        count
+    }
+
+    /// Moves an item from one position to another.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is out of bounds.
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        let len = self.tag.0.len();
+        assert!(from < len && to < len, "item index out of bounds");
+        if from != to {
+            self.apply(Edit::Move { from, to });
+        }
+    }
+
+    /// Whether there is a change to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    /// Whether there is a change to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Reverts the last change.
+    ///
+    /// Returns `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.done.pop() {
+            Some(edit) => {
+                edit.revert(&mut self.tag);
+                self.undone.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the last reverted change again.
+    ///
+    /// Returns `false` when there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some(mut edit) => {
+                edit.perform(&mut self.tag);
+                self.done.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the tag has been changed since the session start
+    /// or the last call of [`mark_clean`](#method.mark_clean).
+    pub fn is_dirty(&self) -> bool {
+        self.clean != Some(self.done.len())
+    }
+
+    /// Marks the current state as clean, e.g. after the tag has been written.
+    pub fn mark_clean(&mut self) {
+        self.clean = Some(self.done.len());
+    }
+
+    fn apply(&mut self, mut edit: Edit) {
+        edit.perform(&mut self.tag);
+        if matches!(self.clean, Some(clean) if clean > self.done.len()) {
+            // The clean state has been undone and can not be reached anymore
+            self.clean = None;
+        }
+        self.done.push(edit);
+        self.undone.clear();
+    }
+}
+
+impl Edit {
+    fn perform(&mut self, tag: &mut Tag) {
+        match self {
+            Edit::Set { item, removed } => {
+                *removed = take_items(tag, &item.key);
+                tag.0.push(item.clone());
+            }
+            Edit::Add { item } => tag.0.push(item.clone()),
+            Edit::Remove { key, removed } => *removed = take_items(tag, key),
+            Edit::Move { from, to } => {
+                let item = tag.0.remove(*from);
+                tag.0.insert(*to, item);
+            }
+        }
+    }
+
+    fn revert(&self, tag: &mut Tag) {
+        match self {
+            Edit::Set { removed, .. } => {
+                tag.0.pop();
+                restore_items(tag, removed);
+            }
+            Edit::Add { .. } => {
+                tag.0.pop();
+            }
+            Edit::Remove { removed, .. } => restore_items(tag, removed),
+            Edit::Move { from, to } => {
+                let item = tag.0.remove(*to);
+                tag.0.insert(*from, item);
+            }
+        }
+    }
+}
+
+fn take_items(tag: &mut Tag, key: &str) -> Vec<(usize, Item)> {
+    let mut removed = Vec::new();
+    let mut kept = Vec::with_capacity(tag.0.len());
+    for (idx, item) in tag.0.drain(..).enumerate() {
+        if item.key.eq_ignore_ascii_case(key) {
+            removed.push((idx, item));
+        } else {
+            kept.push(item);
+        }
+    }
+    tag.0 = kept;
+    removed
+}
+
+fn restore_items(tag: &mut Tag, removed: &[(usize, Item)]) {
+    for (idx, item) in removed {
+        tag.0.insert(*idx, item.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EditSession;
+    use crate::{item::Item, tag::Tag};
+
+    fn keys(session: &EditSession) -> Vec<&str> {
+        session.tag().iter().map(|item| &*item.key).collect()
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("a1", "1").unwrap());
+        tag.add_item(Item::from_text("key", "2").unwrap());
+        tag.add_item(Item::from_text("a3", "3").unwrap());
+        tag.add_item(Item::from_text("key", "4").unwrap());
+        let mut session = EditSession::new(tag);
+        assert!(!session.can_undo());
+        assert!(!session.undo());
+
+        session.set_item(Item::from_text("key", "5").unwrap());
+        assert_eq!(keys(&session), vec!["a1", "a3", "key"]);
+        session.move_item(2, 0);
+        assert_eq!(keys(&session), vec!["key", "a1", "a3"]);
+        assert_eq!(session.remove_items("A1"), 1);
+        assert_eq!(session.remove_items("missing"), 0);
+        session.add_item(Item::from_text("a5", "6").unwrap());
+        assert_eq!(keys(&session), vec!["key", "a3", "a5"]);
+
+        while session.undo() {}
+        assert_eq!(keys(&session), vec!["a1", "key", "a3", "key"]);
+        assert!(session.can_redo());
+
+        while session.redo() {}
+        assert_eq!(keys(&session), vec!["key", "a3", "a5"]);
+        assert_eq!(session.into_tag().iter().count(), 3);
+    }
+
+    #[test]
+    fn dirty() {
+        let mut session = EditSession::new(Tag::new());
+        assert!(!session.is_dirty());
+        session.add_item(Item::from_text("key", "1").unwrap());
+        assert!(session.is_dirty());
+        session.mark_clean();
+        assert!(!session.is_dirty());
+        session.undo();
+        assert!(session.is_dirty());
+        session.redo();
+        assert!(!session.is_dirty());
+        session.undo();
+        session.add_item(Item::from_text("key", "2").unwrap());
+        assert!(session.is_dirty());
+        assert!(!session.can_redo());
+    }
+}
@@ -1,77 +1,274 @@
 use std::{
-    error::Error as StdError, fmt, io::Error as IoError, num::ParseIntError, result::Result as StdResult,
+    error::Error as StdError,
+    fmt,
+    io::Error as IoError,
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    result::Result as StdResult,
     str::Utf8Error,
 };
 
 /// A specialized Result type for metadata operations.
 pub type Result<T> = StdResult<T, Error>;
 
-/// Describes all errors that may occur.
-#[derive(Debug)]
-pub enum Error {
-    /// An IO error occured.
-    Io(IoError),
+/// Describes all kinds of errors that may occur.
+///
+/// New kinds may be added in the future, so matching must have a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An IO error occured, it is available as the source of the error.
+    Io,
     /// An error when attempting to interpret a sequence of u8 as a string.
-    FromUtf8(Utf8Error),
+    FromUtf8,
     /// An error when parsing an integer.
-    ParseInt(ParseIntError),
+    ParseInt,
     /// Unexpected item kind given while parsing a tag.
     BadItemKind,
+    /// An operation has been cancelled, the file is left unchanged.
+    Cancelled,
     /// APE header contains invalid tag size.
     BadTagSize,
-    /// Invalid APE version. It works with APEv2 tags only.
+    /// An audio stream header is missing or malformed.
+    BadStreamHeader,
+    /// Invalid APE version. Only versions 1000 (APEv1) and 2000 (APEv2) are supported.
     InvalidApeVersion,
     /// Item keys can have a length of 2 (including) up to 255 (including) characters.
     InvalidItemKeyLen,
-    /// Item key contains non-ascii characters.
+    /// Item key contains non-ascii characters,
+    /// or characters outside of the printable range when validated strictly.
     InvalidItemKeyValue,
     /// Not allowed are the following keys: ID3, TAG, OggS and MP+.
     ItemKeyDenied,
     /// There is no APE tag in a file.
     TagNotFound,
+    /// A tag does not fit into the given region.
+    TagTooLarge,
+    /// A tag at the start of a file would corrupt the audio stream.
+    UnsafeTagPlacement,
+    /// A tag would be placed inside a truncated MPEG frame.
+    TruncatedFrame,
+    /// Junk would be left between the last MPEG frame and a tag.
+    JunkBeforeTag,
+    /// A tag contains several items with the same key,
+    /// see [`ReadOptions::duplicate_keys`](struct.ReadOptions.html#method.duplicate_keys).
+    DuplicateKey,
+    /// A pattern contains an empty or unclosed field or unbalanced brackets, see [`Pattern`](struct.Pattern.html).
+    InvalidPattern,
+    /// A tag lacks spilled Binary items, so writing it would remove them, see [`Tag::is_partial`](struct.Tag.html#method.is_partial).
+    PartialTag,
+    /// Text of an APEv1 tag contains characters outside of ISO-8859-1,
+    /// see [`WriteOptions::version`](struct.WriteOptions.html#method.version).
+    UnencodableText,
+    /// Processing of a file has taken too long, the file is left unchanged,
+    /// see [`BatchOptions::timeout`](batch/struct.BatchOptions.html#method.timeout).
+    TimedOut,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(match *self {
+            ErrorKind::Io => "IO error",
+            ErrorKind::FromUtf8 => "invalid UTF-8",
+            ErrorKind::ParseInt => "invalid integer",
+            ErrorKind::BadItemKind => "unexpected item kind",
+            ErrorKind::Cancelled => "operation cancelled",
+            ErrorKind::BadTagSize => "APE header contains invalid tag size",
+            ErrorKind::BadStreamHeader => "invalid audio stream header",
+            ErrorKind::InvalidApeVersion => "invalid APE version",
+            ErrorKind::InvalidItemKeyLen => "item keys can have a length of 2 up to 255 characters",
+            ErrorKind::InvalidItemKeyValue => "item key contains invalid characters",
+            ErrorKind::ItemKeyDenied => "not allowed are the following keys: ID3, TAG, OggS and MP+",
+            ErrorKind::TagNotFound => "APE tag does not exists",
+            ErrorKind::TagTooLarge => "APE tag does not fit into the given region",
+            ErrorKind::UnsafeTagPlacement => "APE tag at the start of a file would corrupt the audio stream",
+            ErrorKind::TruncatedFrame => "APE tag would be placed inside a truncated MPEG frame",
+            ErrorKind::JunkBeforeTag => "junk would be left between the last MPEG frame and APE tag",
+            ErrorKind::DuplicateKey => "APE tag contains several items with the same key",
+            ErrorKind::InvalidPattern => "invalid pattern syntax",
+            ErrorKind::PartialTag => "APE tag lacks spilled items and can not be written",
+            ErrorKind::UnencodableText => "text can not be encoded as ISO-8859-1",
+            ErrorKind::TimedOut => "processing of a file timed out",
+        })
+    }
+}
+
+/// An error with its kind, an underlying error (if any) and a context describing where it occured.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{read_from_path, ErrorKind, Tag};
+///
+/// let tag = match read_from_path("path/to/file") {
+///     Err(err) if err.kind() == ErrorKind::TagNotFound => Tag::new(),
+///     Err(err) => panic!("{err}, offset: {:?}", err.offset()),
+///     Ok(tag) => tag,
+/// };
+/// ```
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    source: Option<Box<dyn StdError + Send + Sync>>,
+    context: Option<Box<Context>>,
+}
+
+/// Describes where an error occured.
+#[derive(Debug, Default)]
+struct Context {
+    path: Option<PathBuf>,
+    item: Option<(u32, u64)>,
+    key: Option<String>,
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the path of a file the error occured with.
+    ///
+    /// It is known for all functions accepting a path, so the failed file is known in batch jobs.
+    pub fn path(&self) -> Option<&Path> {
+        self.context.as_ref()?.path.as_deref()
+    }
+
+    /// Returns the zero-based index of a malformed item in a tag.
+    pub fn item_index(&self) -> Option<u32> {
+        Some(self.context.as_ref()?.item?.0)
+    }
+
+    /// Returns the offset of the first byte of a malformed item in a reader or a buffer.
+    ///
+    /// It allows to inspect the corrupt region, e.g. with a hex editor.
+    pub fn offset(&self) -> Option<u64> {
+        Some(self.context.as_ref()?.item?.1)
+    }
+
+    /// Returns the key of an item the error occured with.
+    pub fn key(&self) -> Option<&str> {
+        self.context.as_ref()?.key.as_deref()
+    }
+
+    fn context_mut(&mut self) -> &mut Context {
+        self.context.get_or_insert_with(Box::default)
+    }
+
+    /// Attaches the location of an item which caused the error unless it is already known.
+    pub(crate) fn at_item(mut self, index: u32, offset: u64) -> Error {
+        self.context_mut().item.get_or_insert((index, offset));
+        self
+    }
+
+    /// Attaches the key of an item which caused the error unless it is already known.
+    pub(crate) fn with_key(mut self, key: &str) -> Error {
+        let context = self.context_mut();
+        if context.key.is_none() {
+            context.key = Some(key.to_string());
+        }
+        self
+    }
+
+    /// Attaches the path unless the error already has one.
+    pub(crate) fn with_path(mut self, path: &Path) -> Error {
+        let context = self.context_mut();
+        if context.path.is_none() {
+            context.path = Some(path.to_path_buf());
+        }
+        self
+    }
 }
 
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
-        match *self {
-            Error::Io(ref err) => Some(err),
-            Error::ParseInt(ref err) => Some(err),
-            _ => None,
-        }
+        self.source.as_deref().map(|source| source as &(dyn StdError + 'static))
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::Io(ref err) => write!(out, "{err}"),
-            Error::ParseInt(ref err) => write!(out, "{err}"),
-            Error::FromUtf8(ref err) => write!(out, "{err}"),
-            Error::BadItemKind => write!(out, "unexpected item kind"),
-            Error::BadTagSize => write!(out, "APE header contains invalid tag size"),
-            Error::InvalidApeVersion => write!(out, "invalid APE version"),
-            Error::InvalidItemKeyLen => write!(out, "item keys can have a length of 2 up to 255 characters"),
-            Error::InvalidItemKeyValue => write!(out, "item key contains non-ascii characters"),
-            Error::ItemKeyDenied => write!(out, "not allowed are the following keys: ID3, TAG, OggS and MP+"),
-            Error::TagNotFound => write!(out, "APE tag does not exists"),
+        if let Some(path) = self.path() {
+            write!(out, "{}: ", path.display())?;
+        }
+        if let Some((index, offset)) = self.context.as_ref().and_then(|context| context.item) {
+            write!(out, "item {index} at offset {offset}: ")?;
+        }
+        if let Some(key) = self.key() {
+            write!(out, "key {key:?}: ")?;
+        }
+        match self.source {
+            Some(ref source) => write!(out, "{source}"),
+            None => write!(out, "{}", self.kind),
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            source: None,
+            context: None,
         }
     }
 }
 
 impl From<IoError> for Error {
     fn from(error: IoError) -> Error {
-        Error::Io(error)
+        Error {
+            kind: ErrorKind::Io,
+            source: Some(Box::new(error)),
+            context: None,
+        }
     }
 }
 
 impl From<ParseIntError> for Error {
     fn from(error: ParseIntError) -> Error {
-        Error::ParseInt(error)
+        Error {
+            kind: ErrorKind::ParseInt,
+            source: Some(Box::new(error)),
+            context: None,
+        }
     }
 }
 
 impl From<Utf8Error> for Error {
     fn from(error: Utf8Error) -> Error {
-        Error::FromUtf8(error)
+        Error {
+            kind: ErrorKind::FromUtf8,
+            source: Some(Box::new(error)),
+            context: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, ErrorKind};
+    use std::{error::Error as StdError, io, path::Path};
+
+    #[test]
+    fn context() {
+        let err = Error::from(ErrorKind::BadItemKind)
+            .at_item(1, 100)
+            .with_key("title")
+            .with_path(Path::new("a.ape"))
+            .with_path(Path::new("b.ape"));
+        assert_eq!(err.kind(), ErrorKind::BadItemKind);
+        assert_eq!(err.path(), Some(Path::new("a.ape")));
+        assert_eq!((err.item_index(), err.offset()), (Some(1), Some(100)));
+        assert_eq!(err.key(), Some("title"));
+        assert_eq!(
+            err.to_string(),
+            "a.ape: item 1 at offset 100: key \"title\": unexpected item kind"
+        );
+        assert!(err.source().is_none());
+
+        let err = Error::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(err.source().unwrap().downcast_ref::<io::Error>().is_some());
+        assert_eq!(err.offset(), None);
     }
 }
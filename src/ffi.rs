@@ -0,0 +1,220 @@
+//! UniFFI bindings for Kotlin, Swift, Python and other languages.
+//!
+//! The crate itself is built as `rlib` only, so a shared library comes from a wrapper crate
+//! with `crate-type = ["cdylib"]` which depends on `ape` with the `uniffi` feature and contains `pub use ape::*;`.
+//! Bindings are generated by `uniffi-bindgen` from that library.
+
+use crate::{
+    error::Error,
+    item::{Item, ItemValue},
+    tag::{read_from_path, remove_from_path, replace_tag_in_buffer, write_to_path, Tag},
+};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// An error returned by the bindings.
+#[derive(Debug, uniffi::Error)]
+pub enum ApeError {
+    /// An error of the library.
+    Failed {
+        /// Description of the error.
+        message: String,
+    },
+}
+
+impl fmt::Display for ApeError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApeError::Failed { ref message } => write!(out, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ApeError {}
+
+impl From<Error> for ApeError {
+    fn from(error: Error) -> ApeError {
+        ApeError::Failed {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A value of an item.
+#[derive(Clone, Debug, uniffi::Enum)]
+pub enum ApeItemValue {
+    /// Binary data.
+    Binary {
+        /// Bytes.
+        value: Vec<u8>,
+    },
+    /// A locator of external information, e.g. URL.
+    Locator {
+        /// Locator.
+        value: String,
+    },
+    /// UTF-8 text.
+    Text {
+        /// Text.
+        value: String,
+    },
+    /// Raw bytes of an item with the reserved type.
+    Reserved {
+        /// Bytes.
+        value: Vec<u8>,
+    },
+}
+
+/// An item of a tag.
+#[derive(Clone, Debug, uniffi::Record)]
+pub struct ApeItem {
+    /// Item key.
+    pub key: String,
+    /// Item value.
+    pub value: ApeItemValue,
+    /// Whether the item is marked as read only.
+    pub read_only: bool,
+}
+
+impl From<&Item> for ApeItem {
+    fn from(item: &Item) -> ApeItem {
+        ApeItem {
+            key: item.key.to_string(),
+            value: match item.value {
+                ItemValue::Binary(ref value) => ApeItemValue::Binary { value: value.to_vec() },
+                ItemValue::Locator(ref value) => ApeItemValue::Locator { value: value.clone() },
+                ItemValue::Text(ref value) => ApeItemValue::Text { value: value.clone() },
+                ItemValue::Reserved(ref value) => ApeItemValue::Reserved { value: value.clone() },
+            },
+            read_only: item.read_only,
+        }
+    }
+}
+
+impl TryFrom<ApeItem> for Item {
+    type Error = Error;
+
+    fn try_from(item: ApeItem) -> Result<Item, Error> {
+        let mut result = match item.value {
+            ApeItemValue::Binary { value } => Item::from_binary(item.key, value),
+            ApeItemValue::Locator { value } => Item::from_locator(item.key, value),
+            ApeItemValue::Text { value } => Item::from_text(item.key, value),
+            ApeItemValue::Reserved { value } => Item::new(item.key, ItemValue::Reserved(value)),
+        }?;
+        result.read_only = item.read_only;
+        Ok(result)
+    }
+}
+
+/// A tag shared with foreign code.
+#[derive(Debug, uniffi::Object)]
+pub struct ApeTag(Mutex<Tag>);
+
+impl ApeTag {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Tag> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[uniffi::export]
+impl ApeTag {
+    /// Creates a new empty tag.
+    #[uniffi::constructor]
+    pub fn new() -> Arc<ApeTag> {
+        Arc::new(ApeTag(Mutex::new(Tag::new())))
+    }
+
+    /// Returns all items.
+    pub fn items(&self) -> Vec<ApeItem> {
+        self.lock().iter().map(ApeItem::from).collect()
+    }
+
+    /// Returns the first item by key.
+    pub fn item(&self, key: String) -> Option<ApeItem> {
+        self.lock().item(key).map(ApeItem::from)
+    }
+
+    /// Sets an item replacing all items with the same key.
+    pub fn set_item(&self, item: ApeItem) -> Result<(), ApeError> {
+        self.lock().set_item(Item::try_from(item)?);
+        Ok(())
+    }
+
+    /// Removes all items by key, returns a number of deleted items.
+    pub fn remove_items(&self, key: String) -> u32 {
+        self.lock().remove_items(key) as u32
+    }
+
+    /// Serializes the tag to bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ApeError> {
+        Ok(self.lock().to_bytes()?)
+    }
+}
+
+fn shared(tag: Tag) -> Arc<ApeTag> {
+    Arc::new(ApeTag(Mutex::new(tag)))
+}
+
+/// Reads a tag from the file at the specified path.
+#[uniffi::export]
+pub fn read_tag_from_path(path: String) -> Result<Arc<ApeTag>, ApeError> {
+    Ok(shared(read_from_path(path)?))
+}
+
+/// Writes a tag to the file at the specified path.
+#[uniffi::export]
+pub fn write_tag_to_path(tag: Arc<ApeTag>, path: String) -> Result<(), ApeError> {
+    Ok(write_to_path(&tag.lock(), path)?)
+}
+
+/// Removes a tag from the file at the specified path.
+#[uniffi::export]
+pub fn remove_tag_from_path(path: String) -> Result<(), ApeError> {
+    Ok(remove_from_path(path)?)
+}
+
+/// Parses a tag from a buffer containing either a whole file or the tag only.
+#[uniffi::export]
+pub fn read_tag_from_bytes(bytes: Vec<u8>) -> Result<Arc<ApeTag>, ApeError> {
+    Ok(shared(Tag::parse(&bytes)?))
+}
+
+/// Replaces a tag of a file loaded into memory, returns the new content.
+#[uniffi::export]
+pub fn replace_tag_in_bytes(bytes: Vec<u8>, tag: Arc<ApeTag>) -> Result<Vec<u8>, ApeError> {
+    Ok(replace_tag_in_buffer(bytes, &tag.lock())?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_tag_from_bytes, replace_tag_in_bytes, ApeItem, ApeItemValue, ApeTag};
+
+    #[test]
+    fn bytes() {
+        let tag = ApeTag::new();
+        tag.set_item(ApeItem {
+            key: "title".to_string(),
+            value: ApeItemValue::Text {
+                value: "Title".to_string(),
+            },
+            read_only: true,
+        })
+        .unwrap();
+        assert!(tag
+            .set_item(ApeItem {
+                key: "x".to_string(),
+                value: ApeItemValue::Binary { value: vec![] },
+                read_only: false,
+            })
+            .is_err());
+        let bytes = replace_tag_in_bytes(vec![0; 10], tag).unwrap();
+        let tag = read_tag_from_bytes(bytes).unwrap();
+        let items = tag.items();
+        assert_eq!(items.len(), 1);
+        assert!(matches!(items[0].value, ApeItemValue::Text { ref value } if value == "Title"));
+        assert!(items[0].read_only);
+        assert_eq!(tag.remove_items("TITLE".to_string()), 1);
+    }
+}
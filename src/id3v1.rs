@@ -0,0 +1,646 @@
+//! ID3v1 tags, which are often found next to APE tags, and ID3v1 genres,
+//! which are often found in APE tags written by old tools as numbers.
+
+use crate::{
+    error::{Error, ErrorKind, Result},
+    item::Item,
+    key::ItemKey,
+    metadata::Metadata,
+    options::WriteOptions,
+    tag::{read_from_path, write_to_path, Tag},
+    util::{probe_id3v1, probe_lyrics3v2, ID3V1_OFFSET},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Size of an ID3v1 tag.
+const ID3V1_SIZE: usize = 128;
+
+/// Fields of an ID3v1 or ID3v1.1 tag.
+///
+/// Text fields are decoded as ISO-8859-1, trailing spaces and zero bytes are trimmed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Id3v1 {
+    /// Title, up to 30 characters.
+    pub title: String,
+    /// Artist, up to 30 characters.
+    pub artist: String,
+    /// Album, up to 30 characters.
+    pub album: String,
+    /// Year, up to 4 characters.
+    pub year: String,
+    /// Comment, up to 30 characters or 28 ones in ID3v1.1.
+    pub comment: String,
+    /// Track number, ID3v1.1 only.
+    pub track: Option<u8>,
+    /// Genre index, see [`GENRES`](static.GENRES.html).
+    pub genre: Option<u8>,
+}
+
+impl Id3v1 {
+    /// Reads an ID3v1 tag at the end of a reader.
+    ///
+    /// Returns `None` if there is no ID3v1 tag.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Option<Id3v1>> {
+        if !probe_id3v1(reader)? {
+            return Ok(None);
+        }
+        let mut bytes = [0; ID3V1_SIZE];
+        reader.seek(SeekFrom::End(ID3V1_OFFSET))?;
+        reader.read_exact(&mut bytes)?;
+        Ok(Some(Id3v1::parse(&bytes)))
+    }
+
+    /// Parses an ID3v1 tag, the `TAG` header is not checked.
+    pub fn parse(bytes: &[u8; ID3V1_SIZE]) -> Id3v1 {
+        // ID3v1.1 uses the last two bytes of the comment for a zero byte and a track number
+        let is_v11 = bytes[125] == 0 && bytes[126] != 0;
+        Id3v1 {
+            title: decode(&bytes[3..33]),
+            artist: decode(&bytes[33..63]),
+            album: decode(&bytes[63..93]),
+            year: decode(&bytes[93..97]),
+            comment: decode(&bytes[97..if is_v11 { 125 } else { 127 }]),
+            track: Some(bytes[126]).filter(|_| is_v11),
+            genre: Some(bytes[127]).filter(|&genre| genre != u8::MAX),
+        }
+    }
+
+    /// Derives fields from an APE tag for players which read only ID3v1.
+    ///
+    /// The first of multiple values is used, text is transliterated to ISO-8859-1 and truncated
+    /// to the length of the field. Genres which are not in [`GENRES`](static.GENRES.html)
+    /// and track numbers above 255 are dropped.
+    pub fn from_tag(tag: &Tag) -> Id3v1 {
+        let metadata = Metadata::from_tag(tag);
+        let text = |value: &Option<String>, len| {
+            let value = value
+                .as_deref()
+                .and_then(|value| value.split('\0').next())
+                .unwrap_or_default();
+            transliterate(value.trim(), len)
+        };
+        let genre = metadata.genre.map(|genre| normalize_genre(&genre));
+        let genre = genre.as_deref().and_then(|genre| genre.split('\0').next());
+        let track = metadata
+            .track
+            .and_then(|track| u8::try_from(track).ok())
+            .filter(|&track| track != 0);
+        Id3v1 {
+            title: text(&metadata.title, 30),
+            artist: text(&metadata.artist, 30),
+            album: text(&metadata.album, 30),
+            year: text(&metadata.year, 4),
+            comment: text(&metadata.comment, if track.is_some() { 28 } else { 30 }),
+            track,
+            genre: genre.and_then(genre_index),
+        }
+    }
+
+    /// Returns the ID3v1.1 tag if there is a track number or the ID3v1 one otherwise.
+    ///
+    /// Text is encoded as ISO-8859-1, other characters are replaced with `?` and too long text is truncated.
+    pub fn to_bytes(&self) -> [u8; ID3V1_SIZE] {
+        let mut bytes = [0; ID3V1_SIZE];
+        bytes[..3].copy_from_slice(b"TAG");
+        let comment_end = if self.track.is_some() { 125 } else { 127 };
+        let fields = [
+            (&self.title, 3..33),
+            (&self.artist, 33..63),
+            (&self.album, 63..93),
+            (&self.year, 93..97),
+            (&self.comment, 97..comment_end),
+        ];
+        for (value, range) in fields {
+            let encoded = value.chars().map(|ch| u8::try_from(ch).unwrap_or(b'?'));
+            for (byte, value) in bytes[range].iter_mut().zip(encoded) {
+                *byte = value;
+            }
+        }
+        if let Some(track) = self.track {
+            bytes[126] = track;
+        }
+        bytes[127] = self.genre.unwrap_or(u8::MAX);
+        bytes
+    }
+
+    /// Converts fields to APE items, empty fields and unknown genres are skipped.
+    pub fn to_tag(&self) -> Tag {
+        let track = self.track.map(|track| track.to_string()).unwrap_or_default();
+        let genre = self.genre.and_then(genre_name).unwrap_or_default();
+        let fields = [
+            (ItemKey::Title, self.title.as_str()),
+            (ItemKey::Artist, &self.artist),
+            (ItemKey::Album, &self.album),
+            (ItemKey::Year, &self.year),
+            (ItemKey::Comment, &self.comment),
+            (ItemKey::Track, &track),
+            (ItemKey::Genre, genre),
+        ];
+        // Keys are standard ones, so items can not be invalid
+        fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .filter_map(|(key, value)| Item::from_text(key.as_str(), value).ok())
+            .collect()
+    }
+}
+
+/// Converts the ID3v1 tag of a file to APE items.
+///
+/// Items are added to an existing APE tag unless it already has items with the same keys,
+/// so richer values are not overwritten. When `strip` is set, the ID3v1 tag is removed afterwards
+/// unless it is preceded by a Lyrics3 tag, which can not be found without it.
+/// Returns whether the file has an ID3v1 tag.
+///
+/// # Errors
+///
+/// Fails if the existing APE tag can not be read, see [`read_from_path`](../fn.read_from_path.html),
+/// or the file can not be written, see [`write_to_path`](../fn.write_to_path.html).
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::id3v1::upgrade_id3v1;
+///
+/// if upgrade_id3v1("path/to/file", true).unwrap() {
+///     println!("ID3v1 has been converted");
+/// }
+/// ```
+pub fn upgrade_id3v1<P: AsRef<Path>>(path: P, strip: bool) -> Result<bool> {
+    let path = path.as_ref();
+    let id3v1 = File::open(path)
+        .map_err(Error::from)
+        .and_then(|mut file| Id3v1::read_from(&mut file))
+        .map_err(|err| err.with_path(path))?;
+    let id3v1 = match id3v1 {
+        Some(id3v1) => id3v1,
+        None => return Ok(false),
+    };
+    let mut tag = match read_from_path(path) {
+        Err(err) if err.kind() == ErrorKind::TagNotFound => Tag::new(),
+        result => result?,
+    };
+    for item in id3v1.to_tag() {
+        if tag.item(&item.key).is_none() {
+            tag.add_item(item);
+        }
+    }
+    WriteOptions::new().write_to_path_if_changed(&tag, path)?;
+    if strip {
+        // The file is opened again since a backup may have replaced it
+        strip_id3v1(path).map_err(|err| err.with_path(path))?;
+    }
+    Ok(true)
+}
+
+/// Removes an ID3v1 tag unless it is preceded by a Lyrics3 tag.
+fn strip_id3v1(path: &Path) -> Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if probe_id3v1(&mut file)? && probe_lyrics3v2(&mut file)? == -1 {
+        let len = file.seek(SeekFrom::End(ID3V1_OFFSET))?;
+        file.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// Writes the APE tag to a file together with an ID3v1 tag derived from it.
+///
+/// An existing ID3v1 tag is replaced, otherwise the ID3v1 tag is appended after the APE tag,
+/// see [`Id3v1::from_tag`](struct.Id3v1.html#method.from_tag) for how fields are derived.
+///
+/// # Errors
+///
+/// Fails if the APE tag can not be written, see [`write_to_path`](../fn.write_to_path.html),
+/// or the ID3v1 tag can not be written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{id3v1::write_id3v1, read_from_path};
+///
+/// let tag = read_from_path("path/to/file").unwrap();
+/// write_id3v1(&tag, "path/to/file").unwrap();
+/// ```
+pub fn write_id3v1<P: AsRef<Path>>(tag: &Tag, path: P) -> Result<()> {
+    let path = path.as_ref();
+    write_to_path(tag, path)?;
+    let bytes = Id3v1::from_tag(tag).to_bytes();
+    write_trailer(path, &bytes).map_err(|err| err.with_path(path))
+}
+
+/// Replaces an ID3v1 tag at the end of a file or appends a new one.
+fn write_trailer(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    if probe_id3v1(&mut file)? {
+        file.seek(SeekFrom::End(ID3V1_OFFSET))?;
+    } else {
+        file.seek(SeekFrom::End(0))?;
+    }
+    file.write_all(bytes)?;
+    Ok(())
+}
+
+/// Replaces characters which can not be encoded as ISO-8859-1 with similar ones and truncates text.
+fn transliterate(value: &str, len: usize) -> String {
+    let mut result = String::new();
+    for ch in value.chars() {
+        let replacement = match ch {
+            '\u{0}'..='\u{1f}' | '\u{7f}'..='\u{9f}' => " ",
+            '\u{a0}'..='\u{ff}' | ' '..='~' => {
+                result.push(ch);
+                continue;
+            }
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{2032}' => "'",
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{2033}' => "\"",
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+            '\u{2026}' => "...",
+            '\u{152}' => "OE",
+            '\u{153}' => "oe",
+            '\u{160}' => "S",
+            '\u{161}' => "s",
+            '\u{17d}' => "Z",
+            '\u{17e}' => "z",
+            '\u{178}' => "Y",
+            '\u{20ac}' => "EUR",
+            _ => "?",
+        };
+        result.push_str(replacement);
+    }
+    result.chars().take(len).collect()
+}
+
+/// Decodes ISO-8859-1 text trimming trailing spaces and zero bytes.
+fn decode(bytes: &[u8]) -> String {
+    // The text ends at the first zero byte, the rest may contain garbage
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    let text = bytes[..end].iter().map(|&byte| char::from(byte)).collect::<String>();
+    text.trim_end().to_string()
+}
+
+/// Names of ID3v1 genres by index.
+///
+/// The first 80 genres are defined by ID3v1, the others are Winamp extensions supported by most players.
+pub static GENRES: [&str; 148] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebob",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A capella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Afro-Punk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+];
+
+/// Number of genres defined by ID3v1 itself.
+pub const STANDARD_GENRES: usize = 80;
+
+/// Returns the name of a genre by index.
+///
+/// # Examples
+///
+/// ```
+/// use ape::id3v1::genre_name;
+///
+/// assert_eq!(genre_name(17), Some("Rock"));
+/// assert_eq!(genre_name(255), None);
+/// ```
+pub fn genre_name(index: u8) -> Option<&'static str> {
+    GENRES.get(usize::from(index)).copied()
+}
+
+/// Returns the index of a genre by name, compared case-insensitively.
+pub fn genre_index(name: &str) -> Option<u8> {
+    let name = name.trim();
+    GENRES
+        .iter()
+        .position(|genre| genre.eq_ignore_ascii_case(name))
+        .map(|index| index as u8)
+}
+
+/// Replaces numeric genres with their names.
+///
+/// Both plain numbers and numbers in parentheses, as in ID3v2, are replaced, e.g. `17` and `(17)`.
+/// If a number in parentheses is followed by a name, the name is kept. Other values are not changed,
+/// multiple values separated by zero bytes are normalized separately.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{id3v1::normalize_genre, ItemValue, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_text("Genre", "(17)").unwrap();
+/// if let ItemValue::Text(ref genre) = tag["genre"].value {
+///     let genre = normalize_genre(genre);
+///     tag.set_text("Genre", genre).unwrap();
+/// }
+/// assert_eq!(tag["genre"].value, ItemValue::Text(String::from("Rock")));
+/// ```
+pub fn normalize_genre(value: &str) -> String {
+    value
+        .split('\0')
+        .map(|value| {
+            let trimmed = value.trim();
+            let (number, refinement) = match trimmed.strip_prefix('(').and_then(|rest| rest.split_once(')')) {
+                Some((number, refinement)) => (number, refinement.trim()),
+                None => (trimmed, ""),
+            };
+            let is_number = !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit());
+            match number.parse().ok().filter(|_| is_number).and_then(genre_name) {
+                Some(_) if !refinement.is_empty() => refinement,
+                Some(name) => name,
+                None => value,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\0")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{genre_index, genre_name, normalize_genre, upgrade_id3v1, write_id3v1, Id3v1, GENRES, STANDARD_GENRES};
+    use crate::{
+        item::ItemValue,
+        tag::{read_from_path, write_to_path, Tag},
+    };
+    use std::{
+        fs::{metadata, read, remove_file, write, File},
+        io::Cursor,
+    };
+
+    fn id3v1_bytes() -> Vec<u8> {
+        let mut bytes = vec![0; 128];
+        bytes[..3].copy_from_slice(b"TAG");
+        bytes[3..8].copy_from_slice(b"Title");
+        bytes[33..39].copy_from_slice(b"Artist");
+        bytes[63..68].copy_from_slice(b"Album");
+        bytes[93..97].copy_from_slice(b"2001");
+        bytes[97..104].copy_from_slice(b"Caf\xe9   ");
+        bytes[126] = 5;
+        bytes[127] = 17;
+        bytes
+    }
+
+    #[test]
+    fn genres() {
+        assert_eq!(GENRES[STANDARD_GENRES - 1], "Hard Rock");
+        assert_eq!(genre_name(0), Some("Blues"));
+        assert_eq!(genre_name(147), Some("Synthpop"));
+        assert_eq!(genre_name(148), None);
+        assert_eq!(genre_index(" hip-hop "), Some(7));
+        assert_eq!(genre_index("Unknown"), None);
+        for (index, name) in GENRES.iter().enumerate() {
+            assert_eq!(genre_index(name), Some(index as u8));
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_genre("17"), "Rock");
+        assert_eq!(normalize_genre(" (8) "), "Jazz");
+        assert_eq!(normalize_genre("(17)Hard Rock"), "Hard Rock");
+        assert_eq!(normalize_genre("13\u{0}(0)\u{0}Rock"), "Pop\u{0}Blues\u{0}Rock");
+        assert_eq!(normalize_genre("200"), "200");
+        assert_eq!(normalize_genre("+17"), "+17");
+        assert_eq!(normalize_genre("(RX)"), "(RX)");
+    }
+
+    #[test]
+    fn parse() {
+        let id3v1 = Id3v1::read_from(&mut Cursor::new(id3v1_bytes())).unwrap().unwrap();
+        assert_eq!(id3v1.title, "Title");
+        assert_eq!(id3v1.comment, "Caf\u{e9}");
+        assert_eq!(id3v1.track, Some(5));
+        assert_eq!(id3v1.genre, Some(17));
+        let tag = id3v1.to_tag();
+        assert_eq!(tag["track"].value, ItemValue::Text(String::from("5")));
+        assert_eq!(tag["genre"].value, ItemValue::Text(String::from("Rock")));
+
+        let mut bytes = id3v1_bytes();
+        bytes[125] = b'!';
+        bytes[127] = u8::MAX;
+        let id3v1 = Id3v1::read_from(&mut Cursor::new(bytes)).unwrap().unwrap();
+        assert_eq!(id3v1.track, None);
+        assert_eq!(id3v1.genre, None);
+        assert_eq!(id3v1.to_tag().iter().count(), 5);
+        assert!(Id3v1::read_from(&mut Cursor::new(vec![0; 128])).unwrap().is_none());
+    }
+
+    #[test]
+    fn upgrade() {
+        let path = "data/id3v1-upgrade.mp3";
+        write(path, [b"audio".as_ref(), &id3v1_bytes()].concat()).unwrap();
+        let mut tag = Tag::new();
+        tag.set_text("Title", "Full Title").unwrap();
+        write_to_path(&tag, path).unwrap();
+
+        assert!(upgrade_id3v1(path, false).unwrap());
+        let tag = read_from_path(path).unwrap();
+        assert_eq!(tag["title"].value, ItemValue::Text(String::from("Full Title")));
+        assert_eq!(tag["artist"].value, ItemValue::Text(String::from("Artist")));
+        assert_eq!(tag.iter().count(), 7);
+        assert!(read(path).unwrap().ends_with(&id3v1_bytes()));
+
+        assert!(upgrade_id3v1(path, true).unwrap());
+        assert!(!upgrade_id3v1(path, true).unwrap());
+        assert_eq!(read_from_path(path).unwrap().iter().count(), 7);
+        assert!(read(path).unwrap().starts_with(b"audio"));
+        remove_file(path).unwrap();
+        assert!(upgrade_id3v1(path, true).unwrap_err().path().is_some());
+    }
+
+    #[test]
+    fn from_tag() {
+        let mut tag = Tag::new();
+        tag.set_text("Title", "\u{201c}Na\u{ef}ve\u{201d} \u{2014} \u{152}uvre\u{2026}")
+            .unwrap();
+        tag.set_text("Artist", "\u{41c}\u{438}\u{440}\0Second").unwrap();
+        tag.set_text("Album", "An album with a title longer than thirty characters")
+            .unwrap();
+        tag.set_text("Year", "2001-02-03").unwrap();
+        tag.set_text("Comment", "A comment which is quite long").unwrap();
+        tag.set_text("Track", "3/12").unwrap();
+        tag.set_text("Genre", "(17)\0Jazz").unwrap();
+        let id3v1 = Id3v1::from_tag(&tag);
+        assert_eq!(id3v1.title, "\"Na\u{ef}ve\" - OEuvre...");
+        assert_eq!(id3v1.artist, "???");
+        assert_eq!(id3v1.album, "An album with a title longer t");
+        assert_eq!(id3v1.year, "2001");
+        assert_eq!(id3v1.comment, "A comment which is quite lon");
+        assert_eq!(id3v1.track, Some(3));
+        assert_eq!(id3v1.genre, Some(17));
+        assert_eq!(Id3v1::parse(&id3v1.to_bytes()), id3v1);
+
+        tag.set_text("Track", "300").unwrap();
+        tag.set_text("Genre", "Unknown").unwrap();
+        let id3v1 = Id3v1::from_tag(&tag);
+        assert_eq!((id3v1.track, id3v1.genre), (None, None));
+        assert_eq!(id3v1.comment, "A comment which is quite long");
+        assert_eq!(Id3v1::parse(&id3v1.to_bytes()), id3v1);
+        assert_eq!(Id3v1::parse(&Id3v1::default().to_bytes()), Id3v1::default());
+    }
+
+    #[test]
+    fn downgrade() {
+        let path = "data/id3v1-write.mp3";
+        write(path, b"audio").unwrap();
+        let mut tag = Tag::new();
+        tag.set_text("Title", "Title").unwrap();
+        write_id3v1(&tag, path).unwrap();
+        let len = metadata(path).unwrap().len();
+        tag.set_text("Title", "Caf\u{e9}").unwrap();
+        write_id3v1(&tag, path).unwrap();
+        assert_eq!(metadata(path).unwrap().len(), len);
+        assert_eq!(
+            read_from_path(path).unwrap()["title"].value,
+            ItemValue::Text(String::from("Caf\u{e9}"))
+        );
+        let mut file = File::open(path).unwrap();
+        assert_eq!(Id3v1::read_from(&mut file).unwrap().unwrap().title, "Caf\u{e9}");
+        remove_file(path).unwrap();
+    }
+}
@@ -1,30 +1,99 @@
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, ErrorKind, Result},
+    options::KeyValidation,
+};
 use byteorder::{LittleEndian, WriteBytesExt};
-use std::io::{Cursor, Write};
+use std::{io::Write, sync::Arc};
 
 pub const KIND_BINARY: u32 = 1;
 pub const KIND_LOCATOR: u32 = 2;
 pub const KIND_TEXT: u32 = 0;
+pub const KIND_RESERVED: u32 = 3;
 
-const DENIED_KEYS: [&str; 4] = ["ID3", "TAG", "OggS", "MP+"];
+pub(crate) const DENIED_KEYS: [&str; 4] = ["ID3", "TAG", "OggS", "MP+"];
 
 /// Represents an [APE Item Value][1]
 ///
+/// Text and Locator values are stored as `String`,
+/// so an item containing invalid UTF-8 can not be created or written.
+/// Use Binary values for raw bytes.
+///
 /// [1]: http://wiki.hydrogenaud.io/index.php?title=APE_Item_Value
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum ItemValue {
     /// Binary data. Unrecommended to use.
-    Binary(Vec<u8>),
+    ///
+    /// The bytes are shared, so cloning an item or a tag does not copy e.g. a cover image.
+    Binary(Arc<[u8]>),
     /// Locator is an UTF-8 string contains a link to external stored information.
     Locator(String),
     /// UTF-8 string contains any Text
     Text(String),
+    /// Raw bytes of an item with the type reserved by the specification.
+    ///
+    /// Such items are read only when [`ReadOptions::reserved_items`](struct.ReadOptions.html#method.reserved_items)
+    /// is enabled and are written back with the same type.
+    #[cfg_attr(feature = "arbitrary", arbitrary(skip))]
+    Reserved(Vec<u8>),
+}
+
+impl ItemValue {
+    /// Returns the type of the value.
+    pub fn item_type(&self) -> ItemType {
+        match self {
+            ItemValue::Binary(_) => ItemType::Binary,
+            ItemValue::Locator(_) => ItemType::Locator,
+            ItemValue::Text(_) => ItemType::Text,
+            ItemValue::Reserved(_) => ItemType::Reserved,
+        }
+    }
+}
+
+/// Type of an item value stored in item flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemType {
+    /// Binary data.
+    Binary,
+    /// A link to external stored information.
+    Locator,
+    /// UTF-8 text.
+    Text,
+    /// The type reserved by the specification, written by some broken tools.
+    Reserved,
+}
+
+impl ItemType {
+    /// Extracts the type from item flags.
+    pub(crate) fn from_flags(flags: u32) -> ItemType {
+        match (flags & 6) >> 1 {
+            KIND_BINARY => ItemType::Binary,
+            KIND_LOCATOR => ItemType::Locator,
+            KIND_TEXT => ItemType::Text,
+            _ => ItemType::Reserved,
+        }
+    }
+}
+
+/// Describes a change made to an item key during sanitization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyFix {
+    /// Control characters were removed.
+    RemovedControlChars,
+    /// Characters outside of the ASCII range were replaced with underscores.
+    ReplacedNonAscii,
+    /// Key was shorter than 2 characters and was padded with underscores.
+    Padded,
+    /// Key was longer than 255 characters and was truncated.
+    Truncated,
+    /// Key is denied and an underscore was appended.
+    Denied,
 }
 
 /// Represents an [APE Tag Item][1].
 ///
 /// [1]: http://wiki.hydrogenaud.io/index.php?title=APE_Tag_Item
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Item {
     /// Item key for accessing special meta-information in an audio file.
     ///
@@ -39,32 +108,83 @@ pub struct Item {
     /// Read the [specification][1] for more information.
     ///
     /// [1]: http://wiki.hydrogenaud.io/index.php?title=APE_key
-    pub key: String,
+    pub key: Arc<str>,
     /// Represents an [APE Item Value][1]
     ///
     /// [1]: http://wiki.hydrogenaud.io/index.php?title=APE_Item_Value
     pub value: ItemValue,
+    /// Whether the item is marked as read only.
+    ///
+    /// The flag is informational, such items may be changed or removed as any other ones.
+    pub read_only: bool,
 }
 
 impl Item {
-    fn new<S: Into<String>>(key: S, value: ItemValue) -> Result<Item> {
+    pub(crate) fn new<S: Into<String>>(key: S, value: ItemValue) -> Result<Item> {
         let key = key.into();
-        let len = key.len();
-        if !(2..=255).contains(&len) {
-            return Err(Error::InvalidItemKeyLen);
-        }
-        if DENIED_KEYS.contains(&key.as_str()) {
-            return Err(Error::ItemKeyDenied);
-        }
-        if !key.is_ascii() {
-            return Err(Error::InvalidItemKeyValue);
+        validate_key(&key)?;
+        Ok(Item {
+            key: key.into(),
+            value,
+            read_only: false,
+        })
+    }
+
+    /// Creates a builder of an item with the given key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::Item;
+    ///
+    /// let item = Item::builder("Artist").text("Artist Name").read_only(true).build().unwrap();
+    /// assert!(item.read_only);
+    /// ```
+    pub fn builder<K: Into<String>>(key: K) -> ItemBuilder {
+        ItemBuilder {
+            key: key.into(),
+            value: ItemValue::Text(String::new()),
+            read_only: false,
         }
-        Ok(Item { key, value })
     }
 
-    /// Creates an item with Binary value.
-    pub fn from_binary<K: Into<String>>(key: K, value: Vec<u8>) -> Result<Item> {
-        Self::new(key, ItemValue::Binary(value))
+    /// Creates an item with a sanitized key.
+    ///
+    /// Unlike other constructors it never fails,
+    /// see [`sanitize_key`](#method.sanitize_key) for details.
+    ///
+    /// Returns the item and a list of changes made to the key.
+    pub fn new_lossy<K: AsRef<str>>(key: K, value: ItemValue) -> (Item, Vec<KeyFix>) {
+        let (key, fixes) = sanitize_key(key.as_ref());
+        let item = Item {
+            key: key.into(),
+            value,
+            read_only: false,
+        };
+        (item, fixes)
+    }
+
+    /// Maps an arbitrary string to the nearest valid item key.
+    ///
+    /// Control characters are removed, non-ASCII characters are replaced with underscores,
+    /// too short keys are padded with underscores, too long keys are truncated
+    /// and an underscore is appended to denied keys.
+    pub fn sanitize_key(key: &str) -> String {
+        sanitize_key(key).0
+    }
+
+    /// Creates an item with Binary value, e.g. from `Vec<u8>` or `&[u8]`.
+    ///
+    /// Unlike Text and Locator constructors, it accepts arbitrary bytes.
+    /// Strings are not accepted, so text is not stored as Binary by mistake:
+    ///
+    /// ```compile_fail
+    /// use ape::Item;
+    ///
+    /// let item = Item::from_binary("Artist", "Artist Name");
+    /// ```
+    pub fn from_binary<K: Into<String>, V: Into<Arc<[u8]>>>(key: K, value: V) -> Result<Item> {
+        Self::new(key, ItemValue::Binary(value.into()))
     }
 
     /// Creates an item with Locator value.
@@ -77,9 +197,18 @@ impl Item {
         Self::new(key, ItemValue::Text(value.into()))
     }
 
+    /// Whether the key is one of the denied ones: `ID3`, `TAG`, `OggS` or `MP+`.
+    ///
+    /// Such items are read only when [`ReadOptions::denied_keys`](struct.ReadOptions.html#method.denied_keys)
+    /// is enabled and are not written unless [`WriteOptions::force_denied_keys`](struct.WriteOptions.html#method.force_denied_keys)
+    /// is enabled.
+    pub fn has_denied_key(&self) -> bool {
+        is_denied_key(&self.key)
+    }
+
     /// Sets a new Binary value.
-    pub fn set_binary(&mut self, value: Vec<u8>) {
-        self.value = ItemValue::Binary(value);
+    pub fn set_binary<V: Into<Arc<[u8]>>>(&mut self, value: V) {
+        self.value = ItemValue::Binary(value.into());
     }
 
     /// Sets a new Locator value.
@@ -92,48 +221,210 @@ impl Item {
         self.value = ItemValue::Text(value.into());
     }
 
+    /// Returns the size of the item representation written to a file.
+    pub(super) fn size(&self) -> u64 {
+        let value_len = match self.value {
+            ItemValue::Binary(ref val) => val.len(),
+            ItemValue::Locator(ref val) => val.len(),
+            ItemValue::Text(ref val) => val.len(),
+            ItemValue::Reserved(ref val) => val.len(),
+        };
+        // Value size, flags, key, key terminator and value
+        (4 + 4 + self.key.len() + 1 + value_len) as u64
+    }
+
     /// Creates a representation of the item suitable for writing to a file.
+    #[cfg(test)]
     pub(super) fn to_vec(&self) -> Result<Vec<u8>> {
-        let mut cursor = Cursor::new(Vec::<u8>::new());
-        let size: u32;
-        let flags: u32;
-        let value: &[u8];
-        match self.value {
-            ItemValue::Binary(ref val) => {
-                size = val.len() as u32;
-                flags = KIND_BINARY << 1;
-                value = val;
-            }
-            ItemValue::Locator(ref val) => {
-                size = val.len() as u32;
-                flags = KIND_LOCATOR << 1;
-                value = val.as_ref();
+        let mut bytes = Vec::with_capacity(self.size() as usize);
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Writes the representation of the item, the value is written as is without copying.
+    pub(super) fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let (kind, value) = match self.value {
+            ItemValue::Binary(ref val) => (KIND_BINARY, val.as_ref()),
+            ItemValue::Locator(ref val) => (KIND_LOCATOR, val.as_bytes()),
+            ItemValue::Text(ref val) => (KIND_TEXT, val.as_bytes()),
+            ItemValue::Reserved(ref val) => (KIND_RESERVED, val.as_ref()),
+        };
+        write_item(writer, &self.key, kind << 1 | u32::from(self.read_only), value)
+    }
+}
+
+/// A builder of an item returned by [`Item::builder`](struct.Item.html#method.builder).
+///
+/// The value is an empty text unless another one is set.
+#[derive(Clone, Debug)]
+pub struct ItemBuilder {
+    key: String,
+    value: ItemValue,
+    read_only: bool,
+}
+
+impl ItemBuilder {
+    /// Sets a Text value.
+    pub fn text<V: Into<String>>(&mut self, value: V) -> &mut ItemBuilder {
+        self.value = ItemValue::Text(value.into());
+        self
+    }
+
+    /// Sets multiple Text values joined with a zero byte, empty values are skipped.
+    pub fn values<I: IntoIterator<Item = V>, V: AsRef<str>>(&mut self, values: I) -> &mut ItemBuilder {
+        self.value = ItemValue::Text(join_values(values, false));
+        self
+    }
+
+    /// Sets a Locator value.
+    pub fn locator<V: Into<String>>(&mut self, value: V) -> &mut ItemBuilder {
+        self.value = ItemValue::Locator(value.into());
+        self
+    }
+
+    /// Sets a Binary value.
+    pub fn binary<V: Into<Arc<[u8]>>>(&mut self, value: V) -> &mut ItemBuilder {
+        self.value = ItemValue::Binary(value.into());
+        self
+    }
+
+    /// Sets whether the item is marked as read only, see [`Item::read_only`](struct.Item.html#structfield.read_only).
+    pub fn read_only(&mut self, read_only: bool) -> &mut ItemBuilder {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Creates the item.
+    ///
+    /// # Errors
+    ///
+    /// Keys are validated the same way as by other [`Item`](struct.Item.html) constructors.
+    pub fn build(&self) -> Result<Item> {
+        let mut item = Item::new(self.key.clone(), self.value.clone())?;
+        item.read_only = self.read_only;
+        Ok(item)
+    }
+}
+
+/// Joins non-empty values with a zero byte, repeated values are skipped if requested.
+pub(crate) fn join_values<I: IntoIterator<Item = V>, V: AsRef<str>>(values: I, unique: bool) -> String {
+    let mut joined = Vec::<V>::new();
+    for value in values {
+        let is_repeated = unique && joined.iter().any(|x| x.as_ref() == value.as_ref());
+        if !value.as_ref().is_empty() && !is_repeated {
+            joined.push(value);
+        }
+    }
+    joined.iter().map(AsRef::as_ref).collect::<Vec<_>>().join("\0")
+}
+
+/// Writes a representation of an item suitable for writing to a file.
+pub(crate) fn write_item<W: Write>(writer: &mut W, key: &str, flags: u32, value: &[u8]) -> Result<()> {
+    writer.write_u32::<LittleEndian>(value.len() as u32)?;
+    writer.write_u32::<LittleEndian>(flags)?;
+    writer.write_all(key.as_ref())?;
+    writer.write_u8(0)?;
+    writer.write_all(value)?;
+    Ok(())
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Item {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        // Keys are always valid: printable ASCII of a valid length, denied keys get an underscore appended
+        let len = u.int_in_range(2..=255)?;
+        let key = (0..len)
+            .map(|_| Ok(char::from(u.int_in_range(0x20..=0x7e)?)))
+            .collect::<arbitrary::Result<String>>()?;
+        let (mut item, _) = Item::new_lossy(key, u.arbitrary()?);
+        item.read_only = u.arbitrary()?;
+        Ok(item)
+    }
+}
+
+/// Checks the length and characters of an item key.
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+    validate_key_with(key, KeyValidation::Ascii, false)
+}
+
+/// Checks the length and characters of an item key as strictly as requested.
+pub(crate) fn validate_key_with(key: &str, validation: KeyValidation, allow_denied: bool) -> Result<()> {
+    let valid_chars = match validation {
+        KeyValidation::Permissive => true,
+        KeyValidation::Ascii => key.is_ascii(),
+        KeyValidation::Strict => key.bytes().all(|byte| (0x20..=0x7E).contains(&byte)),
+    };
+    let kind = if !(2..=255).contains(&key.len()) {
+        ErrorKind::InvalidItemKeyLen
+    } else if !allow_denied && is_denied_key(key) {
+        ErrorKind::ItemKeyDenied
+    } else if !valid_chars {
+        ErrorKind::InvalidItemKeyValue
+    } else {
+        return Ok(());
+    };
+    Err(Error::from(kind).with_key(key))
+}
+
+/// Whether a key is one of the denied ones, which are not allowed by the specification.
+pub(crate) fn is_denied_key(key: &str) -> bool {
+    DENIED_KEYS.contains(&key)
+}
+
+/// Whether a key consists of letters, digits, spaces and `()-./_` only, which all tools handle well.
+pub(crate) fn is_recommended_key(key: &str) -> bool {
+    key.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '(' | ')' | '-' | '.' | '/' | '_'))
+}
+
+fn sanitize_key(key: &str) -> (String, Vec<KeyFix>) {
+    let mut fixes = Vec::new();
+    let mut result = String::with_capacity(key.len());
+    for c in key.chars() {
+        if c.is_control() {
+            if !fixes.contains(&KeyFix::RemovedControlChars) {
+                fixes.push(KeyFix::RemovedControlChars);
             }
-            ItemValue::Text(ref val) => {
-                size = val.len() as u32;
-                flags = KIND_TEXT << 1;
-                value = val.as_ref();
+        } else if c.is_ascii() {
+            result.push(c);
+        } else {
+            if !fixes.contains(&KeyFix::ReplacedNonAscii) {
+                fixes.push(KeyFix::ReplacedNonAscii);
             }
-        };
-        cursor.write_u32::<LittleEndian>(size)?;
-        cursor.write_u32::<LittleEndian>(flags)?;
-        cursor.write_all(self.key.as_ref())?;
-        cursor.write_u8(0)?;
-        cursor.write_all(value)?;
-        Ok(cursor.into_inner())
+            result.push('_');
+        }
+    }
+    if result.len() > 255 {
+        result.truncate(255);
+        fixes.push(KeyFix::Truncated);
+    }
+    if result.len() < 2 {
+        while result.len() < 2 {
+            result.push('_');
+        }
+        fixes.push(KeyFix::Padded);
     }
+    if DENIED_KEYS.contains(&result.as_str()) {
+        result.push('_');
+        fixes.push(KeyFix::Denied);
+    }
+    (result, fixes)
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Item, ItemValue, DENIED_KEYS, KIND_BINARY, KIND_LOCATOR, KIND_TEXT};
+    use super::{Item, ItemValue, KeyFix, DENIED_KEYS, KIND_BINARY, KIND_LOCATOR, KIND_TEXT};
     use byteorder::{LittleEndian, ReadBytesExt};
     use std::io::{Cursor, Read};
 
     #[test]
     fn new_failed_with_bad_key_len() {
-        let err = Item::from_text("k", "val").unwrap_err().to_string();
-        assert_eq!(err, "item keys can have a length of 2 up to 255 characters");
+        let err = Item::from_text("k", "val").unwrap_err();
+        assert_eq!(
+            err.kind().to_string(),
+            "item keys can have a length of 2 up to 255 characters"
+        );
+        assert_eq!(err.key(), Some("k"));
     }
 
     #[test]
@@ -142,7 +433,8 @@ mod test {
         for key in DENIED_KEYS.iter() {
             match Item::from_text((*key).to_string(), "val") {
                 Err(err) => {
-                    assert_eq!(msg, format!("{err}"));
+                    assert_eq!(msg, format!("{}", err.kind()));
+                    assert_eq!(format!("key \"{key}\": {msg}"), format!("{err}"));
                 }
                 Ok(_) => {
                     panic!("Unexpected item");
@@ -153,15 +445,76 @@ mod test {
 
     #[test]
     fn new_failed_with_bad_key_val() {
-        let err = Item::from_text("Недопустимые символы", "val").unwrap_err().to_string();
-        assert_eq!(err, "item key contains non-ascii characters");
+        let err = Item::from_text("Недопустимые символы", "val").unwrap_err();
+        assert_eq!(err.kind().to_string(), "item key contains invalid characters");
+    }
+
+    #[test]
+    fn sanitize_key() {
+        assert_eq!(Item::sanitize_key("Artist"), "Artist");
+        assert_eq!(Item::sanitize_key("Artist\n"), "Artist");
+        assert_eq!(Item::sanitize_key("Исполнитель"), "___________");
+        assert_eq!(Item::sanitize_key(""), "__");
+        assert_eq!(Item::sanitize_key("k"), "k_");
+        assert_eq!(Item::sanitize_key("TAG"), "TAG_");
+        assert_eq!(Item::sanitize_key(&"k".repeat(300)).len(), 255);
+    }
+
+    #[test]
+    fn new_lossy() {
+        let (item, fixes) = Item::new_lossy("Artist", ItemValue::Text(String::from("val")));
+        assert_eq!("Artist", &*item.key);
+        assert!(fixes.is_empty());
+
+        let (item, fixes) = Item::new_lossy("é\t", ItemValue::Text(String::from("val")));
+        assert_eq!("_", &item.key[..1]);
+        assert_eq!(2, item.key.len());
+        assert_eq!(
+            fixes,
+            vec![KeyFix::ReplacedNonAscii, KeyFix::RemovedControlChars, KeyFix::Padded]
+        );
+
+        let (item, fixes) = Item::new_lossy("MP+", ItemValue::Binary(vec![].into()));
+        assert_eq!("MP+_", &*item.key);
+        assert_eq!(fixes, vec![KeyFix::Denied]);
+    }
+
+    #[test]
+    fn builder() {
+        let item = Item::builder("Genre")
+            .values(["Rock", "", "Blues"])
+            .read_only(true)
+            .build()
+            .unwrap();
+        assert_eq!(item.value, ItemValue::Text(String::from("Rock\0Blues")));
+        assert!(item.read_only);
+        let item = Item::builder("Cover").binary([1, 2]).build().unwrap();
+        assert_eq!(item, Item::from_binary("Cover", vec![1, 2]).unwrap());
+        assert_eq!(
+            Item::builder("Url").locator("http://").build().unwrap().value,
+            ItemValue::Locator("http://".into())
+        );
+        assert_eq!(
+            Item::builder("Title").build().unwrap().value,
+            ItemValue::Text(String::new())
+        );
+        assert!(Item::builder("TAG").text("value").build().is_err());
+
+        let mut data = Vec::new();
+        Item::builder("Artist")
+            .read_only(true)
+            .build()
+            .unwrap()
+            .write_to(&mut data)
+            .unwrap();
+        assert_eq!(&data[4..8], &[1, 0, 0, 0]);
     }
 
     #[test]
     fn binary() {
         let vec: Vec<u8> = vec![1];
         let mut item = Item::from_binary("key", vec).unwrap();
-        assert_eq!("key", item.key);
+        assert_eq!("key", &*item.key);
         assert_eq!(
             1,
             match item.value {
@@ -178,13 +531,16 @@ mod test {
                 _ => panic!("Invalid value"),
             }[0]
         );
+        let bytes: &[u8] = &[2, 3];
+        item.set_binary(bytes);
+        assert_eq!(item, Item::from_binary("key", [2, 3]).unwrap());
     }
 
     #[test]
     fn locator() {
         let locator = "http://hostname.com";
         let mut item = Item::from_locator("key", locator).unwrap();
-        assert_eq!("key", item.key);
+        assert_eq!("key", &*item.key);
         assert_eq!(
             locator,
             match item.value {
@@ -207,7 +563,7 @@ mod test {
     fn text() {
         let text = "text";
         let mut item = Item::from_text("key", text).unwrap();
-        assert_eq!("key", item.key);
+        assert_eq!("key", &*item.key);
         assert_eq!(
             text,
             match item.value {
@@ -228,7 +584,9 @@ mod test {
 
     #[test]
     fn to_vec() {
-        let mut data = Cursor::new(Item::from_binary("cover", vec![1, 2, 3]).unwrap().to_vec().unwrap());
+        let item = Item::from_binary("cover", vec![1, 2, 3]).unwrap();
+        assert_eq!(item.size(), item.to_vec().unwrap().len() as u64);
+        let mut data = Cursor::new(item.to_vec().unwrap());
         let item_size = data.read_u32::<LittleEndian>().unwrap();
         assert_eq!(3, item_size);
         let item_flags = data.read_u32::<LittleEndian>().unwrap();
@@ -0,0 +1,471 @@
+use crate::{
+    error::Result,
+    options::WriteOptions,
+    tag::{append_tag, move_following, shift_left},
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+static MAGIC: &[u8] = b"APEJRNL1";
+
+/// Bytes following a tag are being moved towards the beginning of a file.
+const OP_SHIFT: u8 = 1;
+/// A tag is being appended to a file.
+const OP_APPEND: u8 = 2;
+/// Bytes following a tag are being moved to fit another tag, which is written in its place.
+const OP_REPLACE: u8 = 3;
+
+/// Sequence number, progress and chunk length.
+const SLOT_HEADER_SIZE: u64 = 8 + 8 + 4;
+const CHECKSUM_SIZE: u64 = 8;
+
+/// A sidecar file describing a modification in progress.
+///
+/// The journal is written and synced before the target file is modified,
+/// so an interrupted modification can be completed by [`recover_journal`].
+///
+/// A shift of bytes is recorded chunk by chunk: every chunk is stored in the journal
+/// before it overwrites its destination, alternating between two slots.
+pub(crate) struct Journal {
+    path: PathBuf,
+    file: File,
+    // Size of the header, slots follow it.
+    header_len: u64,
+    // Capacity of a slot.
+    chunk_capacity: u64,
+    // Sequence number of the next chunk.
+    seq: u64,
+}
+
+impl Journal {
+    /// Creates a journal for the file at the specified path.
+    pub(crate) fn create(path: &Path) -> Result<Journal> {
+        let path = journal_path(path);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        sync_parent_dir(&path)?;
+        Ok(Journal {
+            path,
+            file,
+            header_len: 0,
+            chunk_capacity: 0,
+            seq: 0,
+        })
+    }
+
+    /// Records an intent to remove `size` bytes at `offset`
+    /// and to append the `pending` tag afterwards.
+    pub(crate) fn begin_shift(
+        &mut self,
+        offset: u64,
+        size: u64,
+        filesize: u64,
+        chunk_capacity: u64,
+        pending: &[u8],
+    ) -> Result<()> {
+        self.chunk_capacity = chunk_capacity;
+        self.write_header(OP_SHIFT, [offset, size, filesize, chunk_capacity], pending)
+    }
+
+    /// Records an intent to replace `size` bytes at `offset` with the `tag`
+    /// moving the following bytes to the left or to the right.
+    pub(crate) fn begin_replace(
+        &mut self,
+        offset: u64,
+        size: u64,
+        filesize: u64,
+        chunk_capacity: u64,
+        tag: &[u8],
+    ) -> Result<()> {
+        self.chunk_capacity = chunk_capacity;
+        self.write_header(OP_REPLACE, [offset, size, filesize, chunk_capacity], tag)
+    }
+
+    /// Records a chunk which is about to be written at `offset + progress`.
+    pub(crate) fn record_chunk(&mut self, progress: u64, chunk: &[u8]) -> Result<()> {
+        let mut slot = Vec::with_capacity(SLOT_HEADER_SIZE as usize + chunk.len() + CHECKSUM_SIZE as usize);
+        slot.write_u64::<LittleEndian>(self.seq)?;
+        slot.write_u64::<LittleEndian>(progress)?;
+        slot.write_u32::<LittleEndian>(chunk.len() as u32)?;
+        slot.write_all(chunk)?;
+        slot.write_u64::<LittleEndian>(checksum(&slot))?;
+        let slot_size = SLOT_HEADER_SIZE + self.chunk_capacity + CHECKSUM_SIZE;
+        self.file
+            .seek(SeekFrom::Start(self.header_len + (self.seq % 2) * slot_size))?;
+        self.file.write_all(&slot)?;
+        self.file.sync_data()?;
+        self.seq += 1;
+        Ok(())
+    }
+
+    /// Records an intent to truncate a file to `base` bytes and to write `data` after it.
+    pub(crate) fn begin_append(&mut self, base: u64, data: &[u8]) -> Result<()> {
+        self.write_header(OP_APPEND, [base, 0, 0, 0], data)
+    }
+
+    /// Removes the journal after the modification is complete.
+    pub(crate) fn finish(self) -> Result<()> {
+        drop(self.file);
+        fs::remove_file(&self.path)?;
+        sync_parent_dir(&self.path)?;
+        Ok(())
+    }
+
+    /// Forgets a modification which has been reverted.
+    pub(crate) fn discard(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        self.header_len = 0;
+        Ok(())
+    }
+
+    /// Removes the journal unless a modification has been started.
+    ///
+    /// A started modification has to be completed by [`recover_journal`].
+    pub(crate) fn abandon(self) -> Result<()> {
+        if self.header_len == 0 {
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    fn write_header(&mut self, op: u8, args: [u64; 4], data: &[u8]) -> Result<()> {
+        let mut header = Vec::with_capacity(MAGIC.len() + 1 + 5 * 8 + data.len() + CHECKSUM_SIZE as usize);
+        header.write_all(MAGIC)?;
+        header.write_u8(op)?;
+        for arg in args {
+            header.write_u64::<LittleEndian>(arg)?;
+        }
+        header.write_u64::<LittleEndian>(data.len() as u64)?;
+        header.write_all(data)?;
+        header.write_u64::<LittleEndian>(checksum(&header))?;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.sync_data()?;
+        self.header_len = header.len() as u64;
+        self.seq = 0;
+        Ok(())
+    }
+}
+
+/// Completes a modification of the file at the specified path interrupted in the journaled mode.
+///
+/// Returns `false` when there is no journal, i.e. nothing has been interrupted.
+///
+/// See [`WriteOptions::journal`](struct.WriteOptions.html#method.journal)
+pub fn recover_journal<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let path = path.as_ref();
+    recover(path).map_err(|err| err.with_path(path))
+}
+
+fn recover(path: &Path) -> Result<bool> {
+    let data = match fs::read(journal_path(path)) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    // A broken header means that it was not synced, so the file has not been modified yet
+    if let Some((op, args, pending, header_len)) = parse_header(&data) {
+        let mut journal = Journal {
+            path: journal_path(path),
+            file: OpenOptions::new().read(true).write(true).open(journal_path(path))?,
+            header_len,
+            chunk_capacity: args[3],
+            seq: 0,
+        };
+        let [offset, size, filesize, chunk_capacity] = args;
+        let slot_size = SLOT_HEADER_SIZE + chunk_capacity + CHECKSUM_SIZE;
+        let last = (0..2)
+            .filter_map(|idx| parse_slot(data.get((header_len + idx * slot_size) as usize..)?))
+            .max_by_key(|slot| slot.0);
+        match op {
+            OP_SHIFT => {
+                let mut progress = 0;
+                if let Some((seq, chunk_progress, chunk)) = last {
+                    // The chunk may have been written partially
+                    file.seek(SeekFrom::Start(offset + chunk_progress))?;
+                    file.write_all(chunk)?;
+                    file.sync_data()?;
+                    progress = chunk_progress + chunk.len() as u64;
+                    journal.seq = seq + 1;
+                }
+                shift_left(
+                    &mut file,
+                    (offset, size),
+                    filesize,
+                    progress,
+                    chunk_capacity,
+                    &WriteOptions::new(),
+                    Some(&mut journal),
+                )?;
+                if !pending.is_empty() {
+                    append_tag(&mut file, pending, Some(&mut journal))?;
+                }
+            }
+            OP_REPLACE => {
+                let mut last_chunk = None;
+                if let Some((seq, chunk_progress, chunk)) = last {
+                    // Chunks are written after the new tag in both directions
+                    file.seek(SeekFrom::Start(offset + pending.len() as u64 + chunk_progress))?;
+                    file.write_all(chunk)?;
+                    file.sync_data()?;
+                    last_chunk = Some((chunk_progress, chunk.len() as u64));
+                    journal.seq = seq + 1;
+                }
+                move_following(
+                    &mut file,
+                    (offset, size, pending.len() as u64),
+                    filesize,
+                    last_chunk,
+                    chunk_capacity,
+                    &WriteOptions::new(),
+                    Some(&mut journal),
+                )?;
+                file.seek(SeekFrom::Start(offset))?;
+                file.write_all(pending)?;
+            }
+            OP_APPEND => {
+                let base = args[0];
+                file.set_len(base)?;
+                file.seek(SeekFrom::Start(base))?;
+                file.write_all(pending)?;
+            }
+            _ => {}
+        }
+        file.sync_all()?;
+        drop(file);
+        return journal.finish().map(|_| true);
+    }
+    let path = journal_path(path);
+    fs::remove_file(&path)?;
+    sync_parent_dir(&path)?;
+    Ok(true)
+}
+
+/// Syncs the directory containing the journal, so a created or removed journal stays so after a power loss.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn journal_path(path: &Path) -> PathBuf {
+    let mut journal_path = OsString::from(path);
+    journal_path.push(".ape-journal");
+    PathBuf::from(journal_path)
+}
+
+/// Returns operation, its arguments, data and the header size.
+fn parse_header(data: &[u8]) -> Option<(u8, [u64; 4], &[u8], u64)> {
+    let mut cursor = Cursor::new(data);
+    let mut magic = [0; 8];
+    cursor.read_exact(&mut magic).ok()?;
+    if magic != MAGIC {
+        return None;
+    }
+    let op = cursor.read_u8().ok()?;
+    let mut args = [0; 4];
+    for arg in args.iter_mut() {
+        *arg = cursor.read_u64::<LittleEndian>().ok()?;
+    }
+    let len = cursor.read_u64::<LittleEndian>().ok()? as usize;
+    let start = cursor.position() as usize;
+    let pending = data.get(start..start.checked_add(len)?)?;
+    let end = start + len;
+    let sum = u64::from_le_bytes(data.get(end..end + CHECKSUM_SIZE as usize)?.try_into().ok()?);
+    if sum != checksum(&data[..end]) {
+        return None;
+    }
+    Some((op, args, pending, (end + CHECKSUM_SIZE as usize) as u64))
+}
+
+/// Returns sequence number, progress and chunk of a valid slot.
+fn parse_slot(data: &[u8]) -> Option<(u64, u64, &[u8])> {
+    let mut cursor = Cursor::new(data);
+    let seq = cursor.read_u64::<LittleEndian>().ok()?;
+    let progress = cursor.read_u64::<LittleEndian>().ok()?;
+    let len = cursor.read_u32::<LittleEndian>().ok()? as usize;
+    let start = SLOT_HEADER_SIZE as usize;
+    let chunk = data.get(start..start + len)?;
+    let end = start + len;
+    let sum = u64::from_le_bytes(data.get(end..end + CHECKSUM_SIZE as usize)?.try_into().ok()?);
+    if sum != checksum(&data[..end]) {
+        return None;
+    }
+    Some((seq, progress, chunk))
+}
+
+/// FNV-1a hash used to detect torn writes.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{journal_path, recover_journal, Journal};
+    use crate::{
+        item::Item,
+        options::WriteOptions,
+        tag::{read_from_path, Tag},
+    };
+    use std::{
+        fs::{self, OpenOptions},
+        io::{Seek, SeekFrom, Write},
+        path::Path,
+    };
+
+    /// Returns a header-only tag with a single item followed by the audio data.
+    fn header_tag(value: &[u8], audio: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(b"APETAGEX");
+        data.extend(2000u32.to_le_bytes());
+        // Size of the items, there is no footer
+        data.extend((12 + value.len() as u32).to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend(0xe000_0000u32.to_le_bytes());
+        data.extend([0; 8]);
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend(b"key\0");
+        data.extend(value);
+        data.extend(audio);
+        data
+    }
+
+    #[test]
+    fn recover_interrupted_shift() {
+        let path = "data/journal-shift.apev2";
+        let audio = (0..300).map(|x| x as u8).collect::<Vec<_>>();
+        let data = header_tag(b"value", &audio);
+        fs::write(path, &data).unwrap();
+        assert!(read_from_path(path).is_ok());
+
+        // Emulate a crash after the second chunk has been journaled but written partially
+        let (offset, size, capacity) = (0, 49, 100);
+        let mut journal = Journal::create(Path::new(path)).unwrap();
+        let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+        journal
+            .begin_shift(offset, size, data.len() as u64, capacity, b"")
+            .unwrap();
+        journal.record_chunk(0, &audio[..100]).unwrap();
+        file.write_all(&audio[..100]).unwrap();
+        journal.record_chunk(100, &audio[100..200]).unwrap();
+        file.seek(SeekFrom::Start(100)).unwrap();
+        file.write_all(&audio[100..150]).unwrap();
+        drop(file);
+        drop(journal);
+
+        assert!(recover_journal(path).unwrap());
+        assert_eq!(fs::read(path).unwrap(), audio);
+        assert!(!journal_path(Path::new(path)).exists());
+        assert!(!recover_journal(path).unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn recover_interrupted_replace() {
+        let path = "data/journal-replace.apev2";
+        let audio = (0..300).map(|x| x as u8).collect::<Vec<_>>();
+        let data = header_tag(b"value", &audio);
+        let tag = header_tag(&[b'x'; 30], b"");
+        fs::write(path, &data).unwrap();
+
+        // Emulate a crash while the audio data is moved to the right to fit a larger tag
+        let (size, capacity) = (49, 100);
+        let mut journal = Journal::create(Path::new(path)).unwrap();
+        let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+        journal
+            .begin_replace(0, size, data.len() as u64, capacity, &tag)
+            .unwrap();
+        journal.record_chunk(200, &audio[200..]).unwrap();
+        file.seek(SeekFrom::Start(tag.len() as u64 + 200)).unwrap();
+        file.write_all(&audio[200..]).unwrap();
+        journal.record_chunk(100, &audio[100..200]).unwrap();
+        file.seek(SeekFrom::Start(tag.len() as u64 + 100)).unwrap();
+        file.write_all(&audio[100..150]).unwrap();
+        drop(file);
+        drop(journal);
+
+        assert!(recover_journal(path).unwrap());
+        assert_eq!(fs::read(path).unwrap(), header_tag(&[b'x'; 30], &audio));
+        assert!(!journal_path(Path::new(path)).exists());
+
+        // Emulate a crash while the audio data is moved to the left to fit a smaller tag
+        let data = fs::read(path).unwrap();
+        let tag = header_tag(b"value", b"");
+        let mut journal = Journal::create(Path::new(path)).unwrap();
+        let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+        journal.begin_replace(0, 74, data.len() as u64, capacity, &tag).unwrap();
+        journal.record_chunk(0, &audio[..100]).unwrap();
+        file.seek(SeekFrom::Start(tag.len() as u64)).unwrap();
+        file.write_all(&audio[..60]).unwrap();
+        drop(file);
+        drop(journal);
+
+        assert!(recover_journal(path).unwrap());
+        assert_eq!(fs::read(path).unwrap(), [tag, audio].concat());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn recover_broken_journal() {
+        let path = "data/journal-broken.apev2";
+        fs::write(path, [1; 10]).unwrap();
+        fs::write(journal_path(Path::new(path)), b"APEJRNL1\x01").unwrap();
+        assert!(recover_journal(path).unwrap());
+        assert_eq!(fs::read(path).unwrap(), vec![1; 10]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn journaled_write() {
+        let path = "data/journal-write.apev2";
+        let audio = vec![1; 100_000];
+        fs::write(path, header_tag(b"value", &audio)).unwrap();
+
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "new value").unwrap());
+        WriteOptions::new().journal(true).write_to_path(&tag, path).unwrap();
+        assert!(!journal_path(Path::new(path)).exists());
+        assert_eq!(&fs::read(path).unwrap()[..audio.len()], &audio[..]);
+        assert_eq!(1, read_from_path(path).unwrap().items("key").len());
+
+        WriteOptions::new().journal(true).remove_from_path(path).unwrap();
+        assert_eq!(fs::read(path).unwrap(), audio);
+
+        // A tag at the start is replaced in place moving the audio data once
+        for value in ["x".repeat(1000), String::from("v")] {
+            fs::write(path, header_tag(b"value", &audio)).unwrap();
+            let mut tag = Tag::new();
+            tag.set_item(Item::from_text("key", value.as_str()).unwrap());
+            let mut options = WriteOptions::new();
+            options.journal(true).preserve_layout(true);
+            options.write_to_path(&tag, path).unwrap();
+            assert!(!journal_path(Path::new(path)).exists());
+            assert!(fs::read(path).unwrap() == header_tag(value.as_bytes(), &audio));
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
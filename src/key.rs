@@ -0,0 +1,346 @@
+use crate::metrics;
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+macro_rules! item_keys {
+    ($($(#[$attr:meta])* $variant:ident => $name:literal,)*) => {
+        /// A well-known [APE item key][1].
+        ///
+        /// Can be used anywhere a string key is accepted.
+        /// Standard keys are written with their canonical capitalization
+        /// and matched case-insensitively on read.
+        ///
+        /// [1]: http://wiki.hydrogenaud.io/index.php?title=APE_key
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum ItemKey {
+            $($(#[$attr])* $variant,)*
+            /// Any other key.
+            Custom(String),
+        }
+
+        impl ItemKey {
+            /// Returns the key as it is written to a tag.
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $(ItemKey::$variant => $name,)*
+                    ItemKey::Custom(ref key) => key,
+                }
+            }
+
+            fn standard(key: &str) -> Option<ItemKey> {
+                $(
+                    if key.eq_ignore_ascii_case($name) {
+                        return Some(ItemKey::$variant);
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+item_keys! {
+    /// Music piece title, multiple values are possible.
+    Title => "Title",
+    /// Title when TITLE contains the work or additional sub title.
+    Subtitle => "Subtitle",
+    /// Performing artist.
+    Artist => "Artist",
+    /// Artist of the whole album.
+    AlbumArtist => "Album Artist",
+    /// Album name.
+    Album => "Album",
+    /// Debut album name.
+    DebutAlbum => "Debut album",
+    /// Record label or publisher.
+    Publisher => "Publisher",
+    /// Conductor.
+    Conductor => "Conductor",
+    /// Track number or track number/total tracks number.
+    Track => "Track",
+    /// Disc number or disc number/total discs number.
+    Disc => "Disc",
+    /// Name of the original composer.
+    Composer => "Composer",
+    /// User comment(s).
+    Comment => "Comment",
+    /// Copyright holder.
+    Copyright => "Copyright",
+    /// Publication right holder.
+    PublicationRight => "Publicationright",
+    /// File location.
+    File => "File",
+    /// UPC/EAN code.
+    EanUpc => "EAN/UPC",
+    /// ISBN number with check digit.
+    Isbn => "ISBN",
+    /// Catalog number.
+    Catalog => "Catalog",
+    /// Label code.
+    LabelCode => "LC",
+    /// Release date.
+    Year => "Year",
+    /// Record date.
+    RecordDate => "Record Date",
+    /// Record location.
+    RecordLocation => "Record Location",
+    /// Genre.
+    Genre => "Genre",
+    /// Source media.
+    Media => "Media",
+    /// Indexes for quick access.
+    Index => "Index",
+    /// Location of related information.
+    Related => "Related",
+    /// International Standard Recording Code.
+    Isrc => "ISRC",
+    /// Abstract.
+    Abstract => "Abstract",
+    /// Used language for music/spoken words.
+    Language => "Language",
+    /// Bibliography/discography.
+    Bibliography => "Bibliography",
+    /// Characteric part of piece for intro playing.
+    Introplay => "Introplay",
+    /// Lyrics.
+    Lyrics => "Lyrics",
+    /// Front cover image.
+    CoverArtFront => "Cover Art (Front)",
+    /// Rating, the scale depends on a player.
+    Rating => "Rating",
+    /// Embedded cuesheet.
+    Cuesheet => "Cuesheet",
+}
+
+/// Names of lofty `ItemKey` variants paired with keys lofty stores them as in APE tags.
+///
+/// Totals of tracks and discs are left out, they are only a part of a "Track" or "Disc" value like "3/12",
+/// so mapping them to the whole item would overwrite the number.
+static LOFTY_KEYS: &[(&str, &str)] = &[
+    ("AlbumTitle", "Album"),
+    ("SetSubtitle", "DiscSubtitle"),
+    ("ContentGroup", "Grouping"),
+    ("TrackTitle", "Title"),
+    ("TrackSubtitle", "Subtitle"),
+    ("Work", "Work"),
+    ("AlbumTitleSortOrder", "ALBUMSORT"),
+    ("AlbumArtistSortOrder", "ALBUMARTISTSORT"),
+    ("TrackTitleSortOrder", "TITLESORT"),
+    ("TrackArtistSortOrder", "ARTISTSORT"),
+    ("AlbumArtist", "Album Artist"),
+    ("AlbumArtist", "ALBUMARTIST"),
+    ("TrackArtist", "Artist"),
+    ("Arranger", "Arranger"),
+    ("Writer", "Writer"),
+    ("Composer", "Composer"),
+    ("Conductor", "Conductor"),
+    ("Engineer", "Engineer"),
+    ("Lyricist", "Lyricist"),
+    ("MixDj", "DjMixer"),
+    ("MixEngineer", "Mixer"),
+    ("Performer", "Performer"),
+    ("Producer", "Producer"),
+    ("Label", "Label"),
+    ("Remixer", "MixArtist"),
+    ("DiscNumber", "Disc"),
+    ("TrackNumber", "Track"),
+    ("Year", "Year"),
+    ("Isrc", "ISRC"),
+    ("Barcode", "Barcode"),
+    ("CatalogNumber", "CatalogNumber"),
+    ("FlagCompilation", "Compilation"),
+    ("OriginalMediaType", "Media"),
+    ("EncodedBy", "EncodedBy"),
+    ("Genre", "Genre"),
+    ("Color", "Color"),
+    ("Mood", "Mood"),
+    ("CopyrightMessage", "Copyright"),
+    ("Comment", "Comment"),
+    ("Language", "language"),
+    ("Script", "Script"),
+    ("Lyrics", "Lyrics"),
+    ("ReplayGainAlbumGain", "REPLAYGAIN_ALBUM_GAIN"),
+    ("ReplayGainAlbumPeak", "REPLAYGAIN_ALBUM_PEAK"),
+    ("ReplayGainTrackGain", "REPLAYGAIN_TRACK_GAIN"),
+    ("ReplayGainTrackPeak", "REPLAYGAIN_TRACK_PEAK"),
+    ("MusicBrainzRecordingId", "MUSICBRAINZ_TRACKID"),
+    ("MusicBrainzReleaseId", "MUSICBRAINZ_ALBUMID"),
+    ("MusicBrainzArtistId", "MUSICBRAINZ_ARTISTID"),
+    ("MusicBrainzReleaseArtistId", "MUSICBRAINZ_ALBUMARTISTID"),
+];
+
+impl ItemKey {
+    /// Returns the key a lofty `ItemKey` variant is stored as, e.g. "Artist" for `TrackArtist`.
+    ///
+    /// The variant is given by its name, so the mapping does not depend on lofty.
+    pub fn from_lofty(name: &str) -> Option<ItemKey> {
+        LOFTY_KEYS
+            .iter()
+            .find(|(lofty, _)| *lofty == name)
+            .map(|(_, key)| ItemKey::from(*key))
+    }
+
+    /// Returns names of lofty `ItemKey` variants stored as the key, e.g. `AlbumArtist` for "Album Artist".
+    pub fn lofty_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        LOFTY_KEYS
+            .iter()
+            .filter(move |(_, key)| key.eq_ignore_ascii_case(self.as_str()))
+            .map(|(lofty, _)| *lofty)
+    }
+}
+
+impl AsRef<str> for ItemKey {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for ItemKey {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for ItemKey {
+    fn from(key: &str) -> ItemKey {
+        ItemKey::standard(key).unwrap_or_else(|| ItemKey::Custom(key.to_string()))
+    }
+}
+
+impl From<String> for ItemKey {
+    fn from(key: String) -> ItemKey {
+        ItemKey::standard(&key).unwrap_or(ItemKey::Custom(key))
+    }
+}
+
+impl From<ItemKey> for Arc<str> {
+    fn from(key: ItemKey) -> Arc<str> {
+        match key {
+            ItemKey::Custom(key) => key.into(),
+            key => key.as_str().into(),
+        }
+    }
+}
+
+impl From<ItemKey> for String {
+    fn from(key: ItemKey) -> String {
+        match key {
+            ItemKey::Custom(key) => key,
+            key => key.as_str().to_string(),
+        }
+    }
+}
+
+/// A pool of item keys shared between tags read with the same options.
+///
+/// Reading thousands of files allocates the same keys ("Artist", "Title", ...) over and over,
+/// with a pool each distinct key is allocated once and items hold references to it.
+/// Clones of a pool share the same keys, so it can be used from several threads.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{KeyPool, ReadOptions};
+///
+/// let pool = KeyPool::new();
+/// let mut options = ReadOptions::new();
+/// options.key_pool(pool.clone());
+/// let first = options.read_from_path("path/to/first").unwrap();
+/// let second = options.read_from_path("path/to/second").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct KeyPool(Arc<Mutex<HashSet<Arc<str>>>>);
+
+impl KeyPool {
+    /// Creates an empty pool.
+    pub fn new() -> KeyPool {
+        Self::default()
+    }
+
+    /// Returns a shared copy of the given key, the key is added to the pool if it is not there yet.
+    ///
+    /// Keys are compared case-sensitively, so "Artist" and "ARTIST" are different keys.
+    pub fn intern(&self, key: &str) -> Arc<str> {
+        let mut keys = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(key) = keys.get(key) {
+            metrics::record(|m| m.cache_hit());
+            return key.clone();
+        }
+        let key = Arc::<str>::from(key);
+        keys.insert(key.clone());
+        key
+    }
+
+    /// Returns a number of distinct keys in the pool.
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Whether the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ItemKey, KeyPool};
+    use crate::{item::Item, tag::Tag};
+    use std::sync::Arc;
+
+    #[test]
+    fn from_str() {
+        assert_eq!(ItemKey::from("artist"), ItemKey::Artist);
+        assert_eq!(ItemKey::from("ALBUM ARTIST"), ItemKey::AlbumArtist);
+        assert_eq!(ItemKey::from("ean/upc"), ItemKey::EanUpc);
+        assert_eq!(ItemKey::from("mood"), ItemKey::Custom(String::from("mood")));
+        assert_eq!(ItemKey::from(String::from("Title")), ItemKey::Title);
+    }
+
+    #[test]
+    fn canonical_capitalization() {
+        let item = Item::from_text(ItemKey::AlbumArtist, "Artist Name").unwrap();
+        assert_eq!("Album Artist", &*item.key);
+        let item = Item::from_text(ItemKey::Custom(String::from("mood")), "calm").unwrap();
+        assert_eq!("mood", &*item.key);
+        assert_eq!("Cover Art (Front)", ItemKey::CoverArtFront.to_string());
+    }
+
+    #[test]
+    fn tag_lookup() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("ARTIST", "Artist Name").unwrap());
+        assert!(tag.item(ItemKey::Artist).is_some());
+        assert_eq!(tag.items(&ItemKey::Artist).len(), 1);
+        assert_eq!(tag.remove_items(ItemKey::Artist), 1);
+        assert!(tag.item(ItemKey::Artist).is_none());
+    }
+
+    #[test]
+    fn lofty_mapping() {
+        assert_eq!(ItemKey::from_lofty("TrackArtist"), Some(ItemKey::Artist));
+        assert_eq!(ItemKey::from_lofty("AlbumArtist"), Some(ItemKey::AlbumArtist));
+        assert_eq!(ItemKey::from_lofty("Mood"), Some(ItemKey::Custom(String::from("Mood"))));
+        assert_eq!(ItemKey::from_lofty("Unknown"), None);
+        let names = ItemKey::Track.lofty_names().collect::<Vec<_>>();
+        assert_eq!(names, vec!["TrackNumber"]);
+        assert_eq!(ItemKey::from_lofty("TrackTotal"), None);
+        let names = ItemKey::from("albumartist").lofty_names().collect::<Vec<_>>();
+        assert_eq!(names, vec!["AlbumArtist"]);
+        assert_eq!(ItemKey::Cuesheet.lofty_names().count(), 0);
+    }
+
+    #[test]
+    fn key_pool() {
+        let pool = KeyPool::new();
+        assert!(pool.is_empty());
+        let first = pool.intern("Artist");
+        let second = pool.clone().intern("Artist");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &pool.intern("ARTIST")));
+        assert_eq!(pool.len(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    error::{ErrorKind, Result},
+    meta::Meta,
+    util::{probe_id3v1, probe_lyrics3v2, ID3V1_OFFSET},
+};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Type of a metadata block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockKind {
+    /// APE tag including header and footer.
+    Ape,
+    /// Lyrics3 v2.00 tag.
+    Lyrics3v2,
+    /// ID3v1 tag.
+    Id3v1,
+}
+
+/// A metadata block found by [`layout`](fn.layout.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetadataBlock {
+    /// Type of the block.
+    pub kind: BlockKind,
+    /// Offset of the first byte of the block.
+    pub offset: u64,
+    /// Length of the block in bytes.
+    pub len: u64,
+}
+
+/// Metadata blocks of a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileLayout {
+    /// Length of the file in bytes.
+    pub len: u64,
+    /// Blocks in the order of their offsets.
+    pub blocks: Vec<MetadataBlock>,
+}
+
+impl FileLayout {
+    /// Returns the span of the file between the leading and the trailing metadata blocks.
+    pub fn audio_range(&self) -> Range<u64> {
+        let mut start = 0;
+        for block in &self.blocks {
+            if block.offset == start {
+                start += block.len;
+            }
+        }
+        let mut end = self.len;
+        for block in self.blocks.iter().rev() {
+            if block.offset + block.len == end && block.offset >= start {
+                end = block.offset;
+            }
+        }
+        start..end.max(start)
+    }
+}
+
+/// Returns every metadata block stacked at the end of a file.
+///
+/// An APE tag located at the start of a file is included as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::layout;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// for block in layout(&mut file).unwrap().blocks {
+///     println!("{:?} at {}, {} bytes", block.kind, block.offset, block.len);
+/// }
+/// ```
+pub fn layout<R: Read + Seek>(reader: &mut R) -> Result<FileLayout> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut blocks = Vec::new();
+    if probe_id3v1(reader)? {
+        let id3v1_offset = len - (-ID3V1_OFFSET) as u64;
+        blocks.push(MetadataBlock {
+            kind: BlockKind::Id3v1,
+            offset: id3v1_offset,
+            len: (-ID3V1_OFFSET) as u64,
+        });
+        let lyrics3v2_size = probe_lyrics3v2(reader)?;
+        if lyrics3v2_size != -1 {
+            blocks.push(MetadataBlock {
+                kind: BlockKind::Lyrics3v2,
+                offset: id3v1_offset - lyrics3v2_size as u64,
+                len: lyrics3v2_size as u64,
+            });
+        }
+    }
+    match Meta::read_all(reader, 0) {
+        Ok(all) => {
+            for meta in all {
+                let (offset, len) = meta.region()?;
+                blocks.push(MetadataBlock {
+                    kind: BlockKind::Ape,
+                    offset,
+                    len,
+                });
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::TagNotFound => {}
+        Err(err) => return Err(err),
+    }
+    blocks.sort_by_key(|block| block.offset);
+    Ok(FileLayout { len, blocks })
+}
+
+/// Returns the span of a file excluding all known metadata blocks.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::audio_range;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// let range = audio_range(&mut file).unwrap();
+/// println!("{} bytes of audio", range.end - range.start);
+/// ```
+pub fn audio_range<R: Read + Seek>(reader: &mut R) -> Result<Range<u64>> {
+    Ok(layout(reader)?.audio_range())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audio_range, layout, BlockKind, MetadataBlock};
+    use crate::{
+        audio::{hash_audio_data, AudioDataReader},
+        error::ErrorKind,
+        tag::write_to,
+        Item, Tag,
+    };
+    use std::io::{sink, Cursor};
+
+    fn tag() -> Tag {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        tag
+    }
+
+    #[test]
+    fn no_blocks() {
+        let mut data = Cursor::new(vec![0; 300]);
+        let result = layout(&mut data).unwrap();
+        assert_eq!(result.len, 300);
+        assert!(result.blocks.is_empty());
+        assert_eq!(result.audio_range(), 0..300);
+    }
+
+    #[test]
+    fn corrupt_header_flag() {
+        // A footer claiming a header which would start before the file
+        let mut footer = Tag::new().to_bytes().unwrap();
+        footer[12..16].copy_from_slice(&42u32.to_le_bytes());
+        footer[20..24].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        let mut data = Cursor::new([&[0; 10][..], &footer].concat());
+        assert_eq!(layout(&mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+        assert_eq!(audio_range(&mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+        assert!(AudioDataReader::new(&mut data).is_err());
+        assert!(hash_audio_data(&mut data, &mut sink()).is_err());
+    }
+
+    #[test]
+    fn all_trailers() {
+        let mut data = Cursor::new(vec![0; 100]);
+        write_to(&tag(), &mut data).unwrap();
+        let tag_len = data.get_ref().len() as u64 - 100;
+        let lyrics = b"LYRICSBEGININD0000210EAL00005Title000034LYRICS200";
+        data.get_mut().extend_from_slice(lyrics);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        data.get_mut().extend_from_slice(&id3v1);
+
+        let result = layout(&mut data).unwrap();
+        assert_eq!(result.len, 100 + tag_len + lyrics.len() as u64 + 128);
+        assert_eq!(
+            result.blocks,
+            vec![
+                MetadataBlock {
+                    kind: BlockKind::Ape,
+                    offset: 100,
+                    len: tag_len,
+                },
+                MetadataBlock {
+                    kind: BlockKind::Lyrics3v2,
+                    offset: 100 + tag_len,
+                    len: lyrics.len() as u64,
+                },
+                MetadataBlock {
+                    kind: BlockKind::Id3v1,
+                    offset: 100 + tag_len + lyrics.len() as u64,
+                    len: 128,
+                },
+            ]
+        );
+        assert_eq!(result.audio_range(), 0..100);
+        assert_eq!(audio_range(&mut data).unwrap(), 0..100);
+    }
+}
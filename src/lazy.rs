@@ -0,0 +1,168 @@
+use crate::{
+    error::Result,
+    item::{Item, ItemValue},
+    options::ReadOptions,
+    tag::{read_tag_deferred, Tag},
+};
+use std::io::{Read, Seek, SeekFrom, Take};
+
+/// Location of a Binary value in a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValueHandle {
+    /// Offset of the first byte of the value.
+    pub offset: u64,
+    /// Length of the value.
+    pub len: u64,
+    /// Whether the item is marked as read only, see [`Item::read_only`](struct.Item.html#structfield.read_only).
+    pub read_only: bool,
+}
+
+impl ValueHandle {
+    /// Returns a reader of the value.
+    ///
+    /// The reader must be the one the tag has been read from.
+    pub fn reader<'a, R: Read + Seek>(&self, reader: &'a mut R) -> Result<Take<&'a mut R>> {
+        reader.seek(SeekFrom::Start(self.offset))?;
+        Ok(reader.take(self.len))
+    }
+
+    /// Reads the value into memory.
+    pub fn read<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>> {
+        let mut value = Vec::with_capacity(self.len as usize);
+        self.reader(reader)?.read_to_end(&mut value)?;
+        Ok(value)
+    }
+}
+
+/// A tag which Binary values are not read until they are requested.
+///
+/// Returned by [`read_lazy_from`](fn.read_lazy_from.html).
+#[derive(Clone, Debug, Default)]
+pub struct LazyTag {
+    tag: Tag,
+    deferred: Vec<(String, ValueHandle)>,
+}
+
+impl LazyTag {
+    /// Returns Text and Locator items.
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// Returns keys and locations of Binary values.
+    pub fn deferred(&self) -> impl Iterator<Item = (&str, ValueHandle)> {
+        self.deferred.iter().map(|(key, handle)| (key.as_str(), *handle))
+    }
+
+    /// Returns the location of a first found Binary value by key.
+    pub fn handle<K: AsRef<str>>(&self, key: K) -> Option<ValueHandle> {
+        let key = key.as_ref();
+        self.deferred
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(key))
+            .map(|(_, handle)| *handle)
+    }
+
+    /// Reads all Binary values and returns the complete tag.
+    ///
+    /// Binary items follow the other ones.
+    pub fn load<R: Read + Seek>(self, reader: &mut R) -> Result<Tag> {
+        let mut tag = self.tag;
+        for (key, handle) in self.deferred {
+            // Keys have been validated when the tag was read
+            let value = ItemValue::Binary(handle.read(reader)?.into());
+            tag.add_item(Item {
+                key: key.into(),
+                value,
+                read_only: handle.read_only,
+            });
+        }
+        Ok(tag)
+    }
+}
+
+/// Attempts to read an APE tag without Binary values, e.g. covers.
+///
+/// Text and Locator items are available at once,
+/// Binary values are read on demand using [`ValueHandle`](struct.ValueHandle.html).
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_lazy_from;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// let tag = read_lazy_from(&mut file).unwrap();
+/// println!("{:?}", tag.tag().item("title"));
+/// if let Some(cover) = tag.handle("cover art (front)") {
+///     let cover = cover.read(&mut file).unwrap();
+/// }
+/// ```
+pub fn read_lazy_from<R: Read + Seek>(reader: &mut R) -> Result<LazyTag> {
+    ReadOptions::new().read_lazy_from(reader)
+}
+
+pub(crate) fn read_lazy<R: Read + Seek>(reader: &mut R, options: &ReadOptions) -> Result<LazyTag> {
+    let mut deferred = Vec::new();
+    let tag = read_tag_deferred(reader, options, Some(&mut deferred))?;
+    Ok(LazyTag { tag, deferred })
+}
+
+#[cfg(test)]
+mod test {
+    use super::read_lazy_from;
+    use crate::{
+        item::Item,
+        tag::{read_from, write_to, Tag},
+    };
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn lazy() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        tag.set_item(Item::from_binary("cover", vec![3; 1000]).unwrap());
+        write_to(&tag, &mut data).unwrap();
+
+        let lazy = read_lazy_from(&mut data).unwrap();
+        assert_eq!(lazy.tag().iter().collect::<Vec<_>>(), vec![tag.item("title").unwrap()]);
+        assert_eq!(lazy.deferred().count(), 1);
+        let handle = lazy.handle("COVER").unwrap();
+        assert_eq!(handle.len, 1000);
+        let mut prefix = [0; 4];
+        handle.reader(&mut data).unwrap().read_exact(&mut prefix).unwrap();
+        assert_eq!(prefix, [3; 4]);
+        assert_eq!(handle.read(&mut data).unwrap(), vec![3; 1000]);
+
+        let loaded = lazy.load(&mut data).unwrap();
+        assert!(loaded.has_same_items(&read_from(&mut data).unwrap()));
+    }
+
+    #[test]
+    fn read_only() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.add_item(Item::builder("title").text("Title").read_only(true).build().unwrap());
+        tag.add_item(
+            Item::builder("cover")
+                .binary(vec![3; 10])
+                .read_only(true)
+                .build()
+                .unwrap(),
+        );
+        tag.add_item(Item::from_binary("back", vec![4; 10]).unwrap());
+        write_to(&tag, &mut data).unwrap();
+
+        let lazy = read_lazy_from(&mut data).unwrap();
+        assert!(lazy.tag().item("title").unwrap().read_only);
+        assert!(lazy.handle("cover").unwrap().read_only);
+        assert!(!lazy.handle("back").unwrap().read_only);
+        assert!(lazy.load(&mut data).unwrap().has_same_items(&tag));
+    }
+}
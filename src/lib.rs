@@ -53,14 +53,88 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+#[cfg(feature = "encoding_rs")]
+pub use encoding_rs;
+
+#[cfg(feature = "futures-io")]
+pub use self::async_io::{read_from_async, remove_from_async, write_to_async, AsyncApeRead, AsyncApeWrite};
+#[cfg(feature = "edit")]
+pub use self::edit::EditSession;
+#[cfg(feature = "memmap")]
+pub use self::mmap::{read_from_mmap, ItemRef, ItemValueRef, MappedTag};
 pub use self::{
-    error::{Error, Result},
-    item::{Item, ItemValue},
-    tag::{read_from, read_from_path, remove_from, remove_from_path, write_to, write_to_path, Tag},
+    audio::{hash_audio_data, AudioDataReader},
+    cow::{CowItem, CowTag, CowValue},
+    cuesheet::{CueIndex, CueTrack, Cuesheet},
+    error::{Error, ErrorKind, Result},
+    item::{Item, ItemBuilder, ItemType, ItemValue, KeyFix},
+    journal::recover_journal,
+    key::{ItemKey, KeyPool},
+    layout::{audio_range, layout, BlockKind, FileLayout, MetadataBlock},
+    lazy::{read_lazy_from, LazyTag, ValueHandle},
+    lyrics::LyricLine,
+    meta::MetaPosition,
+    metadata::Metadata,
+    metrics::{set_metrics, Metrics},
+    options::{
+        ApeVersion, CopyOptions, DuplicateKeys, ItemComparator, ItemOrder, KeyValidation, ReadOptions, WriteOptions,
+    },
+    pattern::{FormatOptions, Pattern, RenderOptions},
+    plan::{plan_remove, plan_write, RemovePlan, WritePlan},
+    rating::{Rating, RatingScale},
+    repair::{repair, TagFix},
+    scan::{scan_for_tags, TagLocation},
+    semantics::{Finding, FindingKind},
+    storage::{ApeStorage, StorageAdapter, Truncate},
+    tag::{
+        copy_tag, copy_with_tag, read_from, read_from_at, read_from_path, read_from_stream, read_keys, relocate_tag,
+        remove_from, remove_from_path, replace_tag_in_buffer, strip_all_metadata, write_at, write_to,
+        write_to_if_changed, write_to_path, write_to_path_if_changed, SetPolicy, Tag,
+    },
+    warning::{read_with_warnings, Warning},
 };
 
+#[cfg(feature = "futures-io")]
+mod async_io;
+mod audio;
+pub mod audit;
+pub mod batch;
+pub mod container;
+mod cow;
+mod cuesheet;
+#[cfg(feature = "edit")]
+mod edit;
 mod error;
+#[cfg(feature = "uniffi")]
+pub mod ffi;
+pub mod id3v1;
 mod item;
+mod journal;
+mod key;
+mod layout;
+mod lazy;
+mod lyrics;
+mod macros;
 mod meta;
+mod metadata;
+mod metrics;
+#[cfg(feature = "memmap")]
+mod mmap;
+mod options;
+mod pattern;
+mod plan;
+pub mod query;
+mod rating;
+mod repair;
+mod scan;
+mod semantics;
+mod storage;
 mod tag;
+#[cfg(feature = "proptest")]
+pub mod testing;
+mod trace;
 mod util;
+mod warning;
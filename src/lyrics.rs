@@ -0,0 +1,190 @@
+use crate::{
+    item::{Item, ItemValue},
+    key::ItemKey,
+    tag::Tag,
+};
+use std::time::Duration;
+
+/// A line of LRC-style lyrics.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LyricLine {
+    /// Time when the line starts, `None` if the line has no timestamp.
+    pub time: Option<Duration>,
+    /// Text of the line.
+    pub text: String,
+}
+
+impl LyricLine {
+    /// Parses lyrics with simple LRC-style timestamps.
+    ///
+    /// Each `[mm:ss]` or `[mm:ss.xx]` timestamp at the start of a line produces a separate line,
+    /// lines without timestamps are kept with empty time,
+    /// ID tags such as `[ar:Artist]` are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::LyricLine;
+    /// use std::time::Duration;
+    ///
+    /// let lines = LyricLine::parse("[ar:Artist]\n[00:12.50]First line");
+    /// assert_eq!(lines.len(), 1);
+    /// assert_eq!(lines[0].time, Some(Duration::from_millis(12500)));
+    /// assert_eq!(lines[0].text, "First line");
+    /// ```
+    pub fn parse(lyrics: &str) -> Vec<LyricLine> {
+        let mut result = Vec::new();
+        for line in normalize_line_endings(lyrics).lines() {
+            let mut rest = line.trim_start();
+            let mut times = Vec::new();
+            let mut is_id_tag = false;
+            while let Some((tag, tail)) = rest.strip_prefix('[').and_then(|s| s.split_once(']')) {
+                match parse_timestamp(tag) {
+                    Some(time) => times.push(time),
+                    None if times.is_empty() && tag.contains(':') => {
+                        is_id_tag = true;
+                        break;
+                    }
+                    None => break,
+                }
+                rest = tail;
+            }
+            if is_id_tag {
+                continue;
+            }
+            if times.is_empty() {
+                result.push(LyricLine {
+                    time: None,
+                    text: line.to_string(),
+                });
+            } else {
+                result.extend(times.into_iter().map(|time| LyricLine {
+                    time: Some(time),
+                    text: rest.to_string(),
+                }));
+            }
+        }
+        result
+    }
+}
+
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let (minutes, seconds) = value.split_once(':')?;
+    let minutes = minutes.parse::<u64>().ok()?;
+    let (seconds, fraction) = match seconds.split_once('.') {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (seconds, ""),
+    };
+    if seconds.len() != 2 || fraction.len() > 3 {
+        return None;
+    }
+    let seconds = seconds.parse::<u64>().ok()?;
+    let millis = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32)
+    };
+    let millis = minutes
+        .checked_mul(60)?
+        .checked_add(seconds)?
+        .checked_mul(1000)?
+        .checked_add(millis)?;
+    Some(Duration::from_millis(millis))
+}
+
+fn normalize_line_endings(value: &str) -> String {
+    value.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+impl Tag {
+    /// Returns lyrics stored in the Lyrics item.
+    ///
+    /// Multiple values (e.g. lyrics in different languages) are separated by a null byte
+    /// and returned separately.
+    /// Line endings are normalized to `\n`.
+    pub fn lyrics(&self) -> Vec<String> {
+        match self.item(ItemKey::Lyrics).map(|item| &item.value) {
+            Some(ItemValue::Text(value)) => value.split('\0').map(normalize_line_endings).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets lyrics to the Lyrics item.
+    ///
+    /// Multiple values are joined using a null byte.
+    /// Line endings are normalized to `\n`.
+    pub fn set_lyrics<I, S>(&mut self, lyrics: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let value = lyrics
+            .into_iter()
+            .map(|value| normalize_line_endings(value.as_ref()))
+            .collect::<Vec<_>>()
+            .join("\0");
+        self.set_item(Item {
+            key: ItemKey::Lyrics.into(),
+            value: ItemValue::Text(value),
+            read_only: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LyricLine;
+    use crate::{item::Item, tag::Tag};
+    use std::time::Duration;
+
+    #[test]
+    fn tag_lyrics() {
+        let mut tag = Tag::new();
+        assert!(tag.lyrics().is_empty());
+        tag.set_item(Item::from_text("LYRICS", "Line 1\r\nLine 2\rLine 3\0Строка 1").unwrap());
+        assert_eq!(tag.lyrics(), vec!["Line 1\nLine 2\nLine 3", "Строка 1"]);
+        tag.set_lyrics(["One\r\nTwo"]);
+        assert_eq!(&*tag.item("lyrics").unwrap().key, "Lyrics");
+        assert_eq!(tag.lyrics(), vec!["One\nTwo"]);
+        tag.set_lyrics(vec![String::from("One"), String::from("Один")]);
+        assert_eq!(tag.lyrics(), vec!["One", "Один"]);
+    }
+
+    #[test]
+    fn parse_oversized_timestamp() {
+        // Malformed timestamps are skipped the same way as ID tags
+        let lines = LyricLine::parse("[99999999999999999999:00]One\n[307445734561826:00.000]Two\n[00:01]Three");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].text, "Three");
+    }
+
+    #[test]
+    fn parse_lrc() {
+        let lines = LyricLine::parse("[ti:Title]\r\n[00:01]One\n[01:02.5][02:00.123]Two\nPlain [text]\n[00:03.00]");
+        assert_eq!(
+            lines,
+            vec![
+                LyricLine {
+                    time: Some(Duration::from_secs(1)),
+                    text: String::from("One"),
+                },
+                LyricLine {
+                    time: Some(Duration::from_millis(62500)),
+                    text: String::from("Two"),
+                },
+                LyricLine {
+                    time: Some(Duration::from_millis(120123)),
+                    text: String::from("Two"),
+                },
+                LyricLine {
+                    time: None,
+                    text: String::from("Plain [text]"),
+                },
+                LyricLine {
+                    time: Some(Duration::from_secs(3)),
+                    text: String::new(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+/// Creates a tag from a list of items.
+///
+/// Values are Text by default, lists of strings are joined with a zero byte
+/// and `binary(...)` or `locator(...)` create items of other kinds.
+/// Items are added in the given order, so repeated keys are kept.
+///
+/// Evaluates to `Result<Tag>`, keys are validated the same way as by [`Item`](struct.Item.html) constructors.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{tag, ItemValue};
+///
+/// let cover = vec![0; 16];
+/// let tag = tag! {
+///     "Artist" => "Artist Name",
+///     "Genre" => ["Rock", "Blues"],
+///     "Cover Art (Front)" => binary(cover),
+///     "Related" => locator("http://example.com"),
+/// }
+/// .unwrap();
+/// assert_eq!(tag["genre"].value, ItemValue::Text("Rock\0Blues".into()));
+/// ```
+#[macro_export]
+macro_rules! tag {
+    (@items [$($items:expr),*]) => {
+        ::std::iter::empty::<$crate::Result<$crate::Item>>()
+            $(.chain(::std::iter::once($items)))*
+            .collect::<$crate::Result<$crate::Tag>>()
+    };
+    (@items [$($items:expr),*] $key:expr => binary($value:expr) $(, $($rest:tt)*)?) => {
+        $crate::tag!(@items [
+            $($items,)*
+            $crate::Item::from_binary($key, $value)
+        ] $($($rest)*)?)
+    };
+    (@items [$($items:expr),*] $key:expr => locator($value:expr) $(, $($rest:tt)*)?) => {
+        $crate::tag!(@items [$($items,)* $crate::Item::from_locator($key, $value)] $($($rest)*)?)
+    };
+    (@items [$($items:expr),*] $key:expr => [$($value:expr),* $(,)?] $(, $($rest:tt)*)?) => {
+        $crate::tag!(@items [
+            $($items,)*
+            $crate::Item::from_text(
+                $key,
+                <[&str]>::join(&[$(::std::convert::AsRef::<str>::as_ref(&$value)),*], "\0"),
+            )
+        ] $($($rest)*)?)
+    };
+    (@items [$($items:expr),*] $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $crate::tag!(@items [$($items,)* $crate::Item::from_text($key, $value)] $($($rest)*)?)
+    };
+    ($($items:tt)*) => {
+        $crate::tag!(@items [] $($items)*)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{error::ErrorKind, item::Item, tag::Tag};
+
+    #[test]
+    fn tag() {
+        let genres = [String::from("Rock"), String::from("Blues")];
+        let tag = tag! {
+            "Artist" => "Artist",
+            "Artist" => String::from("Another Artist"),
+            "Genre" => [genres[0], genres[1]],
+            "Mood" => [],
+            "Cover" => binary([1, 2]),
+            "Url" => locator("http://example.com")
+        }
+        .unwrap();
+        let mut expected = Tag::new();
+        expected.add_item(Item::from_text("Artist", "Artist").unwrap());
+        expected.add_item(Item::from_text("Artist", "Another Artist").unwrap());
+        expected.add_item(Item::from_text("Genre", "Rock\0Blues").unwrap());
+        expected.add_item(Item::from_text("Mood", "").unwrap());
+        expected.add_item(Item::from_binary("Cover", vec![1, 2]).unwrap());
+        expected.add_item(Item::from_locator("Url", "http://example.com").unwrap());
+        assert_eq!(tag.iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+
+        assert_eq!(tag! {}.unwrap().iter().count(), 0);
+        assert_eq!(tag! { "TAG" => "value" }.unwrap_err().kind(), ErrorKind::ItemKeyDenied);
+    }
+}
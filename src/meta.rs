@@ -1,20 +1,30 @@
 use crate::{
-    error::{Error, Result},
-    util::{probe_ape, probe_id3v1, probe_lyrics3v2, ID3V1_OFFSET},
+    error::{ErrorKind, Result},
+    metrics,
+    scan::scan_for_tags,
+    trace::{debug, trace},
+    util::{probe_ape, probe_id3v1, probe_lyrics3v2, APE_PREAMBLE, ID3V1_OFFSET},
 };
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::{Read, Seek, SeekFrom};
 
 pub(super) const APE_VERSION: u32 = 2000;
+pub(super) const APE_VERSION_1: u32 = 1000;
+/// Size of a tag header or footer.
+pub(crate) const APE_HEADER_SIZE: u64 = 32;
 
 #[derive(Debug)]
 pub(super) struct Meta {
+    // Version of the tag, either 1000 or 2000.
+    pub(super) version: u32,
     // Tag size in bytes including footer and all tag items excluding the header.
     pub(super) size: u32,
     // Position of the metadata.
     pub(super) position: MetaPosition,
     // Tag contains a header.
     pub(super) has_header: bool,
+    // Tag contains a footer.
+    pub(super) has_footer: bool,
     // Number of items in the Tag.
     pub(super) item_count: u32,
     // Initial position of the Tag items.
@@ -25,26 +35,89 @@ pub(super) struct Meta {
 
 impl Meta {
     pub(super) fn read<R: Read + Seek>(reader: &mut R) -> Result<Meta> {
-        const APE_HEADER_SIZE: i64 = 32;
+        // The reader is left after the preamble
+        Meta::locate(reader)?;
+        Meta::parse(reader)
+    }
 
-        let mut found = probe_ape(reader, SeekFrom::End(-APE_HEADER_SIZE))? || probe_ape(reader, SeekFrom::Start(0))?;
+    /// Returns the offset of a header or footer found at the expected positions without parsing it.
+    pub(super) fn locate<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+        let mut found =
+            probe_ape(reader, SeekFrom::End(-(APE_HEADER_SIZE as i64)))? || probe_ape(reader, SeekFrom::Start(0))?;
+        trace!(found, "probed the end and the start of a file");
         // When located at the end of an MP3 file, an APE tag should be placed after
         // the last frame, just before the ID3v1 tag (if any).
         if !found && probe_id3v1(reader)? {
-            found = probe_ape(reader, SeekFrom::End(ID3V1_OFFSET - APE_HEADER_SIZE))?;
+            found = probe_ape(reader, SeekFrom::End(ID3V1_OFFSET - APE_HEADER_SIZE as i64))?;
+            trace!(found, "probed before ID3v1 tag");
             if !found {
                 // ID3v1 tag maybe preceded by Lyrics3v2: http://id3.org/Lyrics3v2
                 let size = probe_lyrics3v2(reader)?;
                 if size != -1 {
-                    found = probe_ape(reader, SeekFrom::End(ID3V1_OFFSET - size - APE_HEADER_SIZE))?;
+                    found = probe_ape(reader, SeekFrom::End(ID3V1_OFFSET - size - APE_HEADER_SIZE as i64))?;
+                    trace!(found, lyrics3v2_size = size, "probed before Lyrics3v2 tag");
                 }
             }
         }
         if !found {
-            return Err(Error::TagNotFound);
+            debug!("APE tag not found at the expected positions");
+            return Err(ErrorKind::TagNotFound.into());
+        }
+        Ok(reader.stream_position()? - APE_PREAMBLE.len() as u64)
+    }
+
+    /// Reads a header or footer located at the given offset.
+    pub(super) fn read_at<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<Meta> {
+        if !probe_ape(reader, SeekFrom::Start(offset))? {
+            return Err(ErrorKind::TagNotFound.into());
         }
-        if reader.read_u32::<LittleEndian>()? != APE_VERSION {
-            return Err(Error::InvalidApeVersion);
+        Meta::parse(reader)
+    }
+
+    /// Same as [`read`](#method.read), but also searches the last `window` bytes
+    /// for a footer followed by garbage when there is no tag at the expected positions.
+    pub(super) fn read_tolerant<R: Read + Seek>(reader: &mut R, window: u64) -> Result<Meta> {
+        match Meta::read(reader) {
+            Err(err) if err.kind() == ErrorKind::TagNotFound && window > 0 => {
+                let location = scan_for_tags(reader, Some(window + APE_HEADER_SIZE))?
+                    .into_iter()
+                    .rev()
+                    .find(|location| location.position == MetaPosition::Footer)
+                    .ok_or(ErrorKind::TagNotFound)?;
+                debug!(offset = location.offset, "found a footer followed by garbage");
+                metrics::record(|m| m.lenient_recovery());
+                reader.seek(SeekFrom::Start(location.offset + APE_PREAMBLE.len() as u64))?;
+                Meta::parse(reader)
+            }
+            result => result,
+        }
+    }
+
+    /// Reads the tag found by [`read_tolerant`](#method.read_tolerant)
+    /// and another one located at the start of a file (if any).
+    ///
+    /// Some broken tools write a tag at the start of a file and another one at the end.
+    /// The tag at the end goes first.
+    pub(super) fn read_all<R: Read + Seek>(reader: &mut R, window: u64) -> Result<Vec<Meta>> {
+        let meta = Meta::read_tolerant(reader, window)?;
+        let mut all = Vec::with_capacity(2);
+        if meta.position == MetaPosition::Footer && probe_ape(reader, SeekFrom::Start(0))? {
+            let start = Meta::parse(reader)?;
+            // A tag with a header occupying the whole file is found at both positions
+            if start.position == MetaPosition::Header && start.region()? != meta.region()? {
+                debug!("found another tag at the start of a file");
+                all.push(start);
+            }
+        }
+        all.insert(0, meta);
+        Ok(all)
+    }
+
+    /// Reads a header or footer following the preamble.
+    fn parse<R: Read + Seek>(reader: &mut R) -> Result<Meta> {
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != APE_VERSION && version != APE_VERSION_1 {
+            return Err(ErrorKind::InvalidApeVersion.into());
         }
         let size = reader.read_u32::<LittleEndian>()?;
         let item_count = reader.read_u32::<LittleEndian>()?;
@@ -52,10 +125,27 @@ impl Meta {
         // The following 8 bytes are reserved
         const RESERVED_BYTES_NUM: i64 = 8;
         let end_pos = reader.seek(SeekFrom::Current(RESERVED_BYTES_NUM))?;
+        debug!(
+            version,
+            size,
+            item_count,
+            position = ?flags.position,
+            has_header = flags.has_header,
+            has_footer = flags.has_footer,
+            end_pos,
+            "parsed APE tag metadata"
+        );
+        let header_size = if flags.has_header { APE_HEADER_SIZE } else { 0 };
+        if flags.position == MetaPosition::Footer && end_pos < size as u64 + header_size {
+            // The tag can't start before the beginning of a file (or a buffered part of a stream)
+            return Err(ErrorKind::BadTagSize.into());
+        }
         Ok(Meta {
+            version,
             size,
             position: flags.position,
             has_header: flags.has_header,
+            has_footer: flags.has_footer,
             item_count,
             start_pos: match flags.position {
                 MetaPosition::Header => end_pos,
@@ -65,36 +155,105 @@ impl Meta {
                 MetaPosition::Header => {
                     let mut pos = end_pos + size as u64;
                     if flags.has_footer {
-                        pos -= APE_HEADER_SIZE as u64;
+                        pos -= APE_HEADER_SIZE;
                     }
                     pos
                 }
-                MetaPosition::Footer => end_pos - APE_HEADER_SIZE as u64,
+                MetaPosition::Footer => end_pos - APE_HEADER_SIZE,
             },
         })
     }
 }
 
+impl Meta {
+    /// Returns a capacity reserved for items of the tag.
+    ///
+    /// The declared number of items is not trusted, it is limited by the number of the smallest items fitting into the tag.
+    pub(super) fn items_capacity(&self, item_count: u32) -> usize {
+        // Size of a value, flags, a two-character key and its terminator
+        const MIN_ITEM_SIZE: u64 = 4 + 4 + 2 + 1;
+        u64::from(item_count).min(self.end_pos.saturating_sub(self.start_pos) / MIN_ITEM_SIZE) as usize
+    }
+
+    /// Returns offset and size of the whole tag including header and footer.
+    pub(super) fn region(&self) -> Result<(u64, u64)> {
+        let mut offset = self.start_pos;
+        let mut size = self.size as u64;
+        if self.has_header || self.position == MetaPosition::Header {
+            offset = offset.checked_sub(APE_HEADER_SIZE).ok_or(ErrorKind::BadTagSize)?;
+            size += APE_HEADER_SIZE;
+        }
+        Ok((offset, size))
+    }
+}
+
+impl Meta {
+    /// Returns the layout of the tag.
+    pub(super) fn layout(&self) -> Layout {
+        Layout {
+            header: self.has_header || self.position == MetaPosition::Header,
+            footer: self.has_footer || self.position == MetaPosition::Footer,
+            at_start: self.position == MetaPosition::Header,
+        }
+    }
+}
+
+/// Describes how a tag is placed in a file.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub(super) enum MetaPosition {
-    // It's header of the tag.
+pub(super) struct Layout {
+    // Tag contains a header.
+    pub(super) header: bool,
+    // Tag contains a footer.
+    pub(super) footer: bool,
+    // Tag is located at the start of a file.
+    pub(super) at_start: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            header: false,
+            footer: true,
+            at_start: false,
+        }
+    }
+}
+
+impl Layout {
+    /// Returns flags of the tag footer, flags of the header also contain `IS_HEADER`.
+    pub(super) fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.header {
+            flags |= HAS_HEADER;
+        }
+        if !self.footer {
+            flags |= HAS_NO_FOOTER;
+        }
+        flags
+    }
+}
+
+/// Position of a tag in a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetaPosition {
+    /// The tag is located at the start of a file and is described by its header.
     Header,
-    // It's footer of the tag.
+    /// The tag is located at the end of a file and is described by its footer.
     Footer,
 }
 
-const HAS_HEADER: u32 = 1 << 31;
-const HAS_NO_FOOTER: u32 = 1 << 30;
-const IS_HEADER: u32 = 1 << 29;
+pub(super) const HAS_HEADER: u32 = 1 << 31;
+pub(super) const HAS_NO_FOOTER: u32 = 1 << 30;
+pub(super) const IS_HEADER: u32 = 1 << 29;
 
-struct MetaFlags {
-    position: MetaPosition,
-    has_header: bool,
-    has_footer: bool,
+pub(super) struct MetaFlags {
+    pub(super) position: MetaPosition,
+    pub(super) has_header: bool,
+    pub(super) has_footer: bool,
 }
 
 impl MetaFlags {
-    fn from_raw(raw: u32) -> Self {
+    pub(super) fn from_raw(raw: u32) -> Self {
         Self {
             position: if raw & IS_HEADER != 0 {
                 MetaPosition::Header
@@ -218,9 +377,24 @@ mod test {
     fn invalid_ape_version() {
         let mut data = Cursor::new(Vec::<u8>::new());
         data.write_all(b"APETAGEX").unwrap();
-        data.write_u32::<LittleEndian>(1000).unwrap();
+        data.write_u32::<LittleEndian>(3000).unwrap();
         data.write_all(&[0; 20]).unwrap();
         let err = Meta::read(&mut data).unwrap_err().to_string();
         assert_eq!(err, "invalid APE version");
     }
+
+    #[test]
+    fn missing_header_space() {
+        // A footer claiming a header, which would start before the file
+        let mut data = Cursor::new(vec![0; 10]);
+        data.seek(SeekFrom::End(0)).unwrap();
+        data.write_all(b"APETAGEX").unwrap();
+        data.write_u32::<LittleEndian>(2000).unwrap();
+        data.write_u32::<LittleEndian>(42).unwrap();
+        data.write_u32::<LittleEndian>(0).unwrap();
+        data.write_u32::<LittleEndian>(HAS_HEADER).unwrap();
+        data.write_all(&[0; 8]).unwrap();
+        let err = Meta::read(&mut data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadTagSize);
+    }
 }
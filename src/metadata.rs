@@ -0,0 +1,221 @@
+use crate::{
+    item::{Item, ItemValue},
+    key::ItemKey,
+    tag::Tag,
+};
+
+/// Common fields of a tag for applications which do not need to deal with items.
+///
+/// Text values are kept as they are, so multiple values are separated by zero bytes.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{Metadata, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_text("Track", "3/12").unwrap();
+/// let mut metadata = Metadata::from_tag(&tag);
+/// assert_eq!((metadata.track, metadata.track_total), (Some(3), Some(12)));
+///
+/// metadata.title = Some(String::from("Title"));
+/// metadata.apply_to(&mut tag);
+/// assert_eq!(&*tag.item("title").unwrap().key, "Title");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Music piece title.
+    pub title: Option<String>,
+    /// Performing artist.
+    pub artist: Option<String>,
+    /// Album name.
+    pub album: Option<String>,
+    /// Artist of the whole album.
+    pub album_artist: Option<String>,
+    /// Release date, usually a year.
+    pub year: Option<String>,
+    /// Track number.
+    pub track: Option<u32>,
+    /// Total number of tracks.
+    pub track_total: Option<u32>,
+    /// Disc number.
+    pub disc: Option<u32>,
+    /// Total number of discs.
+    pub disc_total: Option<u32>,
+    /// Genre.
+    pub genre: Option<String>,
+    /// User comment.
+    pub comment: Option<String>,
+    /// Front cover image without the file name preceding it in the item.
+    pub cover: Option<Vec<u8>>,
+}
+
+impl Metadata {
+    /// Extracts common fields from a tag.
+    ///
+    /// Fields are `None` when there is no item, its value has another type
+    /// or, for numbers, does not match `n` or `n/total`.
+    pub fn from_tag(tag: &Tag) -> Metadata {
+        let text = |key: ItemKey| match tag.item(key)?.value {
+            ItemValue::Text(ref value) => Some(value.clone()),
+            _ => None,
+        };
+        let (track, track_total) = text(ItemKey::Track).map_or((None, None), |value| parse_number(&value));
+        let (disc, disc_total) = text(ItemKey::Disc).map_or((None, None), |value| parse_number(&value));
+        let cover = match tag.item(ItemKey::CoverArtFront).map(|item| &item.value) {
+            Some(ItemValue::Binary(value)) => Some(strip_file_name(value).to_vec()),
+            _ => None,
+        };
+        Metadata {
+            title: text(ItemKey::Title),
+            artist: text(ItemKey::Artist),
+            album: text(ItemKey::Album),
+            album_artist: text(ItemKey::AlbumArtist),
+            year: text(ItemKey::Year),
+            track,
+            track_total,
+            disc,
+            disc_total,
+            genre: text(ItemKey::Genre),
+            comment: text(ItemKey::Comment),
+            cover,
+        }
+    }
+
+    /// Writes common fields to a tag.
+    ///
+    /// Items of fields which are set are replaced, items of fields which are `None` are removed
+    /// and other items are kept. A total without a number is not written.
+    /// The cover is written with a file name which extension depends on the image format.
+    pub fn apply_to(&self, tag: &mut Tag) {
+        let texts = [
+            (ItemKey::Title, &self.title),
+            (ItemKey::Artist, &self.artist),
+            (ItemKey::Album, &self.album),
+            (ItemKey::AlbumArtist, &self.album_artist),
+            (ItemKey::Year, &self.year),
+            (ItemKey::Genre, &self.genre),
+            (ItemKey::Comment, &self.comment),
+        ];
+        for (key, value) in texts {
+            set_value(tag, key, value.as_ref().map(|value| ItemValue::Text(value.clone())));
+        }
+        let numbers = [
+            (ItemKey::Track, self.track, self.track_total),
+            (ItemKey::Disc, self.disc, self.disc_total),
+        ];
+        for (key, number, total) in numbers {
+            let value = number.map(|number| match total {
+                Some(total) => format!("{}/{}", number, total),
+                None => number.to_string(),
+            });
+            set_value(tag, key, value.map(ItemValue::Text));
+        }
+        let cover = self.cover.as_ref().map(|image| {
+            let name: &[u8] = if image.starts_with(b"\x89PNG") {
+                b"cover.png\0"
+            } else {
+                b"cover.jpg\0"
+            };
+            ItemValue::Binary([name, image].concat().into())
+        });
+        set_value(tag, ItemKey::CoverArtFront, cover);
+    }
+}
+
+/// Replaces items with the given key or removes them if there is no value.
+fn set_value(tag: &mut Tag, key: ItemKey, value: Option<ItemValue>) {
+    match value {
+        Some(value) => tag.set_item(Item {
+            key: key.into(),
+            value,
+            read_only: false,
+        }),
+        None => {
+            tag.remove_items(key);
+        }
+    }
+}
+
+/// Parses `n` or `n/total`.
+fn parse_number(value: &str) -> (Option<u32>, Option<u32>) {
+    let (number, total) = match value.split_once('/') {
+        Some((number, total)) => (number, Some(total)),
+        None => (value, None),
+    };
+    match (number.trim().parse().ok(), total.map(|total| total.trim().parse().ok())) {
+        (Some(number), None) => (Some(number), None),
+        (Some(number), Some(Some(total))) => (Some(number), Some(total)),
+        _ => (None, None),
+    }
+}
+
+/// Strips a file name separated by a zero byte from an image.
+///
+/// Images without a file name are returned as they are, since common formats never start with a valid UTF-8 string.
+fn strip_file_name(value: &[u8]) -> &[u8] {
+    match value.iter().position(|&byte| byte == 0) {
+        Some(idx) if std::str::from_utf8(&value[..idx]).is_ok() => &value[idx + 1..],
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_number, strip_file_name, Metadata};
+    use crate::{item::Item, tag::Tag};
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("3"), (Some(3), None));
+        assert_eq!(parse_number(" 3 / 12 "), (Some(3), Some(12)));
+        assert_eq!(parse_number("A1"), (None, None));
+        assert_eq!(parse_number("3/"), (None, None));
+    }
+
+    #[test]
+    fn file_name() {
+        assert_eq!(strip_file_name(b"cover.jpg\0\xff\xd8"), b"\xff\xd8");
+        assert_eq!(strip_file_name(b"\x89PNG\r\n\x1a\n\0\0"), b"\x89PNG\r\n\x1a\n\0\0");
+    }
+
+    #[test]
+    fn from_tag_and_apply_to() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_text("Artist", "First\0Second").unwrap());
+        tag.add_item(Item::from_locator("Album", "http://example.com").unwrap());
+        tag.add_item(Item::from_text("Disc", "1/2").unwrap());
+        tag.add_item(Item::from_text("Track", "A1").unwrap());
+        tag.add_item(Item::from_text("Mood", "Calm").unwrap());
+        tag.add_item(Item::from_binary("Cover Art (Front)", b"front.png\0\x89PNG".as_ref()).unwrap());
+        let mut metadata = Metadata::from_tag(&tag);
+        assert_eq!(
+            metadata,
+            Metadata {
+                title: Some(String::from("Title")),
+                artist: Some(String::from("First\0Second")),
+                disc: Some(1),
+                disc_total: Some(2),
+                cover: Some(b"\x89PNG".to_vec()),
+                ..Metadata::default()
+            }
+        );
+
+        metadata.title = None;
+        metadata.track = Some(3);
+        metadata.disc_total = None;
+        metadata.apply_to(&mut tag);
+        assert!(tag.item("title").is_none());
+        assert_eq!(tag["artist"], Item::from_text("Artist", "First\0Second").unwrap());
+        assert!(tag.item("album").is_none());
+        assert_eq!(tag["track"], Item::from_text("Track", "3").unwrap());
+        assert_eq!(tag["disc"], Item::from_text("Disc", "1").unwrap());
+        assert_eq!(tag["mood"], Item::from_text("Mood", "Calm").unwrap());
+        assert_eq!(
+            tag["cover art (front)"],
+            Item::from_binary("Cover Art (Front)", b"cover.png\0\x89PNG".as_ref()).unwrap()
+        );
+        assert_eq!(Metadata::from_tag(&tag), metadata);
+    }
+}
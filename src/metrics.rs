@@ -0,0 +1,160 @@
+use std::sync::OnceLock;
+
+static METRICS: OnceLock<Box<dyn Metrics>> = OnceLock::new();
+
+/// Receives counters about the library operations.
+///
+/// All methods have empty default implementations,
+/// so an implementation may handle only the counters it is interested in.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{set_metrics, Metrics};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     tags_read: AtomicU64,
+/// }
+///
+/// impl Metrics for Counters {
+///     fn tag_read(&self) {
+///         self.tags_read.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// set_metrics(Box::new(Counters::default()));
+/// ```
+pub trait Metrics: Send + Sync {
+    /// A tag has been successfully read.
+    fn tag_read(&self) {}
+
+    /// A tag has been successfully written.
+    fn tag_written(&self) {}
+
+    /// A tag has been removed, a tag replaced by a written one is not counted.
+    fn tag_removed(&self) {}
+
+    /// Bytes following a removed tag have been moved within a file.
+    fn bytes_shifted(&self, _bytes: u64) {}
+
+    /// A malformed tag has been read thanks to a lenient option,
+    /// e.g. a wrong item count or a footer followed by garbage.
+    fn lenient_recovery(&self) {}
+
+    /// A key has been taken from a [`KeyPool`](struct.KeyPool.html) instead of being allocated.
+    fn cache_hit(&self) {}
+}
+
+/// Sets the global metrics receiver.
+///
+/// Returns `false` when a receiver has already been set,
+/// in this case the given receiver is dropped.
+pub fn set_metrics(metrics: Box<dyn Metrics>) -> bool {
+    METRICS.set(metrics).is_ok()
+}
+
+/// Calls the given function with the global metrics receiver (if any).
+pub(crate) fn record<F: FnOnce(&dyn Metrics)>(f: F) {
+    if let Some(metrics) = METRICS.get() {
+        f(metrics.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{set_metrics, Metrics, METRICS};
+    use crate::{
+        item::Item,
+        key::KeyPool,
+        options::{ReadOptions, WriteOptions},
+        tag::{read_from_path, write_to_path, Tag},
+    };
+    use std::{
+        cell::Cell,
+        fs::{remove_file, File, OpenOptions},
+        io::Write,
+    };
+
+    // Counters are recorded by the calling thread, so other tests running concurrently do not affect them
+    thread_local! {
+        static READ: Cell<u64> = const { Cell::new(0) };
+        static WRITTEN: Cell<u64> = const { Cell::new(0) };
+        static REMOVED: Cell<u64> = const { Cell::new(0) };
+        static RECOVERED: Cell<u64> = const { Cell::new(0) };
+        static CACHE_HITS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    fn increment(counter: &'static std::thread::LocalKey<Cell<u64>>) {
+        counter.with(|counter| counter.set(counter.get() + 1));
+    }
+
+    fn counters() -> [u64; 5] {
+        [&READ, &WRITTEN, &REMOVED, &RECOVERED, &CACHE_HITS].map(|counter| counter.with(Cell::get))
+    }
+
+    struct Counters;
+
+    impl Metrics for Counters {
+        fn tag_read(&self) {
+            increment(&READ);
+        }
+
+        fn tag_written(&self) {
+            increment(&WRITTEN);
+        }
+
+        fn tag_removed(&self) {
+            increment(&REMOVED);
+        }
+
+        fn lenient_recovery(&self) {
+            increment(&RECOVERED);
+        }
+
+        fn cache_hit(&self) {
+            increment(&CACHE_HITS);
+        }
+    }
+
+    #[test]
+    fn counters_recorded() {
+        set_metrics(Box::new(Counters));
+        assert!(METRICS.get().is_some());
+        assert!(!set_metrics(Box::new(Counters)));
+
+        let path = "data/metrics.apev2";
+        File::create(path).unwrap().write_all(&[0; 200]).unwrap();
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to_path(&tag, path).unwrap();
+        // The replaced tag is not counted as removed
+        write_to_path(&tag, path).unwrap();
+        read_from_path(path).unwrap();
+        assert_eq!(counters(), [1, 2, 0, 0, 0]);
+
+        let mut options = ReadOptions::new();
+        options.key_pool(KeyPool::new()).trailing_garbage_window(16);
+        options.read_from_path(path).unwrap();
+        options.read_from_path(path).unwrap();
+        assert_eq!(counters(), [3, 2, 0, 0, 1]);
+
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(b"garbage")
+            .unwrap();
+        options.read_from_path(path).unwrap();
+        assert_eq!(counters(), [4, 2, 0, 1, 2]);
+
+        WriteOptions::new()
+            .trailing_garbage_window(16)
+            .remove_from_path(path)
+            .unwrap();
+        remove_file(path).unwrap();
+        // The tag followed by garbage is found leniently again
+        assert_eq!(counters(), [4, 2, 1, 2, 2]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Zero-copy reading of memory-mapped files.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use ape::read_from_mmap;
+//!
+//! let tag = read_from_mmap("path/to/file").unwrap();
+//! for item in tag.iter() {
+//!     println!("{}: {:?}", item.key, item.value);
+//! }
+//! ```
+
+use crate::{
+    cow::{locate_items, CowTag, RawItem},
+    error::Result,
+    item::{Item, ItemValue, KIND_BINARY, KIND_LOCATOR},
+    metrics,
+    options::ReadOptions,
+    tag::Tag,
+    trace::span,
+};
+use memmap2::Mmap;
+use std::{fs::File, ops::Range, path::Path, str};
+
+/// A value borrowed from a mapped file, see [`ItemValue`](enum.ItemValue.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemValueRef<'a> {
+    /// Binary data.
+    Binary(&'a [u8]),
+    /// A link to external stored information.
+    Locator(&'a str),
+    /// UTF-8 text.
+    Text(&'a str),
+}
+
+/// An item borrowed from a mapped file, see [`Item`](struct.Item.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemRef<'a> {
+    /// Item key.
+    pub key: &'a str,
+    /// Item value.
+    pub value: ItemValueRef<'a>,
+    /// Whether the item is marked as read only, see [`Item::read_only`](struct.Item.html#structfield.read_only).
+    pub read_only: bool,
+}
+
+impl ItemRef<'_> {
+    /// Copies the item.
+    pub fn to_item(&self) -> Item {
+        let value = match self.value {
+            ItemValueRef::Binary(value) => ItemValue::Binary(value.into()),
+            ItemValueRef::Locator(value) => ItemValue::Locator(value.to_string()),
+            ItemValueRef::Text(value) => ItemValue::Text(value.to_string()),
+        };
+        Item {
+            key: self.key.into(),
+            value,
+            read_only: self.read_only,
+        }
+    }
+}
+
+/// A tag of a memory-mapped file returned by [`read_from_mmap`](fn.read_from_mmap.html).
+///
+/// The file stays mapped while the tag is alive, items are borrowed from the mapping.
+#[derive(Debug)]
+pub struct MappedTag {
+    map: Mmap,
+    items: Vec<RawItem>,
+}
+
+impl MappedTag {
+    /// Returns an iterator over items.
+    pub fn iter(&self) -> impl Iterator<Item = ItemRef<'_>> {
+        self.items.iter().map(|raw| self.get(raw))
+    }
+
+    /// Returns the first item by key, case-insensitive.
+    pub fn item<K: AsRef<str>>(&self, key: K) -> Option<ItemRef<'_>> {
+        let key = key.as_ref();
+        self.iter().find(|item| item.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Copies all items into a tag.
+    pub fn to_tag(&self) -> Tag {
+        Tag(self.iter().map(|item| item.to_item()).collect(), false)
+    }
+
+    /// Parses the mapped tag again as a [`CowTag`](struct.CowTag.html) which items may be modified.
+    pub fn to_cow(&self) -> Result<CowTag<'_>> {
+        CowTag::parse(&self.map)
+    }
+
+    fn get<'a>(&'a self, raw: &'a RawItem) -> ItemRef<'a> {
+        // Keys and text values have been validated when the tag was read,
+        // they can be invalid only when the file has been modified since then.
+        let text = |range: &Range<usize>| str::from_utf8(&self.map[range.clone()]).unwrap_or_default();
+        let value = match (raw.kind, raw.decoded.as_deref()) {
+            (KIND_BINARY, _) => ItemValueRef::Binary(&self.map[raw.value.clone()]),
+            (KIND_LOCATOR, decoded) => ItemValueRef::Locator(decoded.unwrap_or_else(|| text(&raw.value))),
+            (_, decoded) => ItemValueRef::Text(decoded.unwrap_or_else(|| text(&raw.value))),
+        };
+        ItemRef {
+            key: text(&raw.key),
+            value,
+            read_only: raw.read_only,
+        }
+    }
+}
+
+/// Maps the file at the specified path into memory and reads an APE tag without copying items.
+///
+/// Scanning large libraries this way avoids reading items into intermediate buffers.
+/// Text and Locator values must be valid UTF-8 (ISO-8859-1 in APEv1 tags), `ReadOptions` are not supported.
+///
+/// The file must not be modified while it is mapped: it is undefined which contents are observed then,
+/// and the items of a modified tag may be read as empty strings.
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+pub fn read_from_mmap<P: AsRef<Path>>(path: P) -> Result<MappedTag> {
+    span!("read_from_mmap");
+    let path = path.as_ref();
+    map(path).map_err(|err| err.with_path(path))
+}
+
+fn map(path: &Path) -> Result<MappedTag> {
+    let file = File::open(path)?;
+    // SAFETY: The mapping is read-only, concurrent modifications of the file are documented above.
+    let map = unsafe { Mmap::map(&file)? };
+    let items = locate_items(&map, &ReadOptions::new())?;
+    metrics::record(|m| m.tag_read());
+    Ok(MappedTag { map, items })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_from_mmap, ItemValueRef};
+    use crate::{
+        error::ErrorKind,
+        tag::{read_from_path, write_to_path, Tag},
+        Item,
+    };
+    use std::{
+        fs::{remove_file, write},
+        path::Path,
+    };
+
+    #[test]
+    fn read() {
+        let path = "data/mmap.apev2";
+        write(path, [1; 200]).unwrap();
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_locator("url", "http://example.com").unwrap());
+        tag.add_item(Item::from_binary("cover", vec![0, 1, 2]).unwrap());
+        write_to_path(&tag, path).unwrap();
+
+        let mapped = read_from_mmap(path).unwrap();
+        assert_eq!(mapped.item("TITLE").unwrap().value, ItemValueRef::Text("Title"));
+        assert_eq!(
+            mapped.item("url").unwrap().value,
+            ItemValueRef::Locator("http://example.com")
+        );
+        assert_eq!(mapped.item("cover").unwrap().value, ItemValueRef::Binary(&[0, 1, 2]));
+        assert!(mapped.to_tag().has_same_items(&read_from_path(path).unwrap()));
+        assert_eq!(mapped.to_cow().unwrap().iter().count(), 3);
+        drop(mapped);
+
+        write(path, [1; 200]).unwrap();
+        let err = read_from_mmap(path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TagNotFound);
+        assert_eq!(err.path(), Some(Path::new(path)));
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn huge_item_count() {
+        let path = "data/mmap-huge-item-count.apev2";
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        let mut bytes = tag.to_bytes().unwrap();
+        let count = bytes.len() - 16;
+        bytes[count..count + 4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        write(path, bytes).unwrap();
+        assert!(read_from_mmap(path).is_err());
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_only() {
+        let path = "data/mmap-read-only.apev2";
+        write(path, [1; 200]).unwrap();
+        let mut tag = Tag::new();
+        tag.add_item(Item::builder("title").text("Title").read_only(true).build().unwrap());
+        tag.add_item(
+            Item::builder("cover")
+                .binary(vec![0, 1, 2])
+                .read_only(true)
+                .build()
+                .unwrap(),
+        );
+        tag.add_item(Item::from_text("artist", "Artist").unwrap());
+        write_to_path(&tag, path).unwrap();
+
+        let mapped = read_from_mmap(path).unwrap();
+        assert_eq!(
+            mapped.iter().map(|item| item.read_only).collect::<Vec<_>>(),
+            vec![true, true, false]
+        );
+        assert!(mapped.to_tag().has_same_items(&tag));
+        drop(mapped);
+        remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,1384 @@
+#[cfg(feature = "futures-io")]
+use crate::async_io::{modify_async, AsyncApeWrite};
+use crate::{
+    cow::{parse_cow, CowTag},
+    error::{Error, Result},
+    item::{validate_key_with, Item, ItemType},
+    journal::{recover_journal, Journal},
+    key::KeyPool,
+    lazy::{read_lazy, LazyTag},
+    plan::{plan_remove_with, plan_write_with, RemovePlan, WritePlan},
+    storage::Truncate,
+    tag::{
+        is_changed, read_tag, read_tag_at, read_tag_from_stream, read_tag_keys, remove_tags, write_tag, write_tag_at,
+        Tag,
+    },
+    warning::{read_tag_with_warnings, Warning},
+};
+#[cfg(feature = "encoding_rs")]
+use encoding_rs::Encoding;
+use std::{
+    cmp::Ordering,
+    ffi::OsString,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Size of the buffer used to move data, see [`WriteOptions::buffer_size`](struct.WriteOptions.html#method.buffer_size).
+const DEFAULT_BUFFER_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A function receiving a key and a reader of a Binary value,
+/// see [`ReadOptions::spill_binary_values`](struct.ReadOptions.html#method.spill_binary_values).
+type SpillFn = dyn Fn(&str, &mut dyn Read) -> io::Result<()> + Send + Sync;
+
+/// A function receiving a key and returning whether the item is copied,
+/// see [`CopyOptions::filter`](struct.CopyOptions.html#method.filter).
+type FilterFn = dyn Fn(&str) -> bool + Send + Sync;
+
+/// Options and flags which can be used to configure how a tag is read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::ReadOptions;
+///
+/// let tag = ReadOptions::new().read_from_path("path/to/file").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReadOptions {
+    #[cfg(feature = "encoding_rs")]
+    fallback_encoding: Option<&'static Encoding>,
+    garbage_window: u64,
+    spill_threshold: u64,
+    spill: Option<Callback<SpillFn>>,
+    duplicate_keys: DuplicateKeys,
+    reserved_items: bool,
+    tolerate_item_count: bool,
+    key_validation: KeyValidation,
+    denied_keys: bool,
+    key_pool: Option<KeyPool>,
+}
+
+impl ReadOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// Reading with these options is the same as calling [`read_from`](fn.read_from.html).
+    pub fn new() -> ReadOptions {
+        Self::default()
+    }
+
+    /// Sets an encoding used to decode Text and Locator values which are not valid UTF-8.
+    ///
+    /// Many old tags contain text in a legacy encoding such as Windows-1251 or Latin-1.
+    /// By default such values cause an error.
+    #[cfg(feature = "encoding_rs")]
+    pub fn fallback_encoding(&mut self, encoding: &'static Encoding) -> &mut ReadOptions {
+        self.fallback_encoding = Some(encoding);
+        self
+    }
+
+    /// Sets a number of bytes at the end of a file searched for a tag footer followed by garbage.
+    ///
+    /// Some rippers leave a few stray bytes after the tag, so it can not be found at the expected position.
+    /// The search is performed only when there is no tag at the expected positions, it is disabled by default.
+    pub fn trailing_garbage_window(&mut self, window: u64) -> &mut ReadOptions {
+        self.garbage_window = window;
+        self
+    }
+
+    /// Sets how items with the same key are handled, all of them are kept by default.
+    ///
+    /// Keys are compared case-insensitively, the first item keeps its position.
+    /// Binary values deferred by [`read_lazy_from`](#method.read_lazy_from) are not affected.
+    pub fn duplicate_keys(&mut self, duplicate_keys: DuplicateKeys) -> &mut ReadOptions {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Sets whether items with the reserved type are read as [`ItemValue::Reserved`](enum.ItemValue.html#variant.Reserved).
+    ///
+    /// Some broken tools set the reserved type bits, such items cause an error by default.
+    /// When enabled, the rest of the tag remains accessible and the type is preserved on write.
+    pub fn reserved_items(&mut self, reserved_items: bool) -> &mut ReadOptions {
+        self.reserved_items = reserved_items;
+        self
+    }
+
+    /// Sets whether an item count of a tag is ignored, items are read as long as they fit into the tag.
+    ///
+    /// Some buggy tools write off-by-one counts, such tags cause an error by default.
+    /// The discrepancy is reported by [`read_with_warnings`](#method.read_with_warnings).
+    pub fn tolerate_item_count(&mut self, tolerate: bool) -> &mut ReadOptions {
+        self.tolerate_item_count = tolerate;
+        self
+    }
+
+    /// Sets how keys of read items are validated, non-ASCII keys cause an error by default.
+    ///
+    /// [`KeyValidation::Permissive`](enum.KeyValidation.html#variant.Permissive) allows to read keys
+    /// which other tools wrote outside of the range, such items are written back only with the same validation.
+    /// [`read_with_warnings`](#method.read_with_warnings) reports keys outside of the recommended subset
+    /// when keys are validated strictly.
+    pub fn key_validation(&mut self, validation: KeyValidation) -> &mut ReadOptions {
+        self.key_validation = validation;
+        self
+    }
+
+    /// Sets whether items with denied keys written by broken software are read, e.g. `TAG`.
+    ///
+    /// Such items cause an error by default. When enabled, they can be inspected and removed,
+    /// see [`Item::has_denied_key`](struct.Item.html#method.has_denied_key), and are reported by
+    /// [`read_with_warnings`](#method.read_with_warnings).
+    pub fn denied_keys(&mut self, allow: bool) -> &mut ReadOptions {
+        self.denied_keys = allow;
+        self
+    }
+
+    /// Sets a pool used to share item keys between tags, see [`KeyPool`](struct.KeyPool.html).
+    ///
+    /// By default every item owns its key.
+    pub fn key_pool(&mut self, pool: KeyPool) -> &mut ReadOptions {
+        self.key_pool = Some(pool);
+        self
+    }
+
+    /// Sets a function receiving Binary values larger than `threshold` bytes instead of reading them into memory.
+    ///
+    /// The function gets an item key and a reader of the value, e.g. to copy it into a temporary file.
+    /// Such items are left out of the tag and the tag can not be written,
+    /// since writing it back would remove them, see [`Tag::is_partial`](struct.Tag.html#method.is_partial).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ape::ReadOptions;
+    /// use std::{fs::File, io};
+    ///
+    /// let tag = ReadOptions::new()
+    ///     .spill_binary_values(1024 * 1024, |key, value| {
+    ///         io::copy(value, &mut File::create(format!("/tmp/{}", key))?).map(|_| ())
+    ///     })
+    ///     .read_from_path("path/to/file")
+    ///     .unwrap();
+    /// ```
+    pub fn spill_binary_values<F>(&mut self, threshold: u64, spill: F) -> &mut ReadOptions
+    where
+        F: Fn(&str, &mut dyn Read) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.spill_threshold = threshold;
+        self.spill = Some(Callback(Arc::new(spill)));
+        self
+    }
+
+    /// Attempts to read an APE tag from the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_from_path`](fn.read_from_path.html)
+    pub fn read_from_path<P: AsRef<Path>>(&self, path: P) -> Result<Tag> {
+        let path = path.as_ref();
+        OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(Error::from)
+            // Items are parsed in small pieces, so reads are buffered
+            .and_then(|file| self.read_from(&mut BufReader::new(file)))
+            .map_err(|err| err.with_path(path))
+    }
+
+    /// Attempts to read an APE tag from a reader.
+    ///
+    /// # Errors
+    ///
+    /// See [`read_from_path`](fn.read_from_path.html)
+    pub fn read_from<R: Read + Seek>(&self, reader: &mut R) -> Result<Tag> {
+        read_tag(reader, self)
+    }
+
+    /// Attempts to read an APE tag from the end of a forward-only stream.
+    ///
+    /// See [`read_from_stream`](fn.read_from_stream.html)
+    pub fn read_from_stream<R: Read>(&self, reader: R, tail_hint: usize) -> Result<Tag> {
+        read_tag_from_stream(reader, tail_hint, self)
+    }
+
+    /// Attempts to read an APE tag, recoverable oddities are returned alongside the tag.
+    ///
+    /// See [`read_with_warnings`](fn.read_with_warnings.html)
+    pub fn read_with_warnings<R: Read + Seek>(&self, reader: &mut R) -> Result<(Tag, Vec<Warning>)> {
+        read_tag_with_warnings(reader, self)
+    }
+
+    /// Attempts to read keys, types and value sizes of items.
+    ///
+    /// See [`read_keys`](fn.read_keys.html)
+    pub fn read_keys<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<(String, ItemType, u32)>> {
+        read_tag_keys(reader, self)
+    }
+
+    /// Parses a tag from a buffer without copying items, see [`CowTag::parse`](struct.CowTag.html#method.parse).
+    ///
+    /// Values decoded from a legacy encoding are owned. Duplicate keys are always kept,
+    /// Binary values are not spilled and keys are not interned.
+    pub fn parse_cow<'a>(&self, bytes: &'a [u8]) -> Result<CowTag<'a>> {
+        parse_cow(bytes, self)
+    }
+
+    /// Attempts to read an APE tag without Binary values.
+    ///
+    /// See [`read_lazy_from`](fn.read_lazy_from.html)
+    pub fn read_lazy_from<R: Read + Seek>(&self, reader: &mut R) -> Result<LazyTag> {
+        read_lazy(reader, self)
+    }
+
+    /// Attempts to read an APE tag which header or footer is located at the given offset.
+    ///
+    /// See [`read_from_at`](fn.read_from_at.html)
+    pub fn read_from_at<R: Read + Seek>(&self, reader: &mut R, offset: u64) -> Result<Tag> {
+        read_tag_at(reader, offset, self)
+    }
+
+    pub(crate) fn garbage_window(&self) -> u64 {
+        self.garbage_window
+    }
+
+    pub(crate) fn duplicate_key_policy(&self) -> DuplicateKeys {
+        self.duplicate_keys
+    }
+
+    pub(crate) fn allows_reserved(&self) -> bool {
+        self.reserved_items
+    }
+
+    pub(crate) fn tolerates_item_count(&self) -> bool {
+        self.tolerate_item_count
+    }
+
+    pub(crate) fn key_validation_mode(&self) -> KeyValidation {
+        self.key_validation
+    }
+
+    pub(crate) fn validate_key(&self, key: &str) -> Result<()> {
+        validate_key_with(key, self.key_validation, self.denied_keys)
+    }
+
+    pub(crate) fn intern_key(&self, key: &str) -> Arc<str> {
+        match self.key_pool {
+            Some(ref pool) => pool.intern(key),
+            None => key.into(),
+        }
+    }
+
+    pub(crate) fn spills(&self, len: u64) -> bool {
+        self.spill.is_some() && len > self.spill_threshold
+    }
+
+    pub(crate) fn spill(&self, key: &str, value: &mut dyn Read) -> Result<()> {
+        if let Some(Callback(ref spill)) = self.spill {
+            spill(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Converts a Text or Locator value to a string.
+    pub(crate) fn decode_text(&self, value: Vec<u8>) -> Result<String> {
+        let err = match String::from_utf8(value) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        #[cfg(feature = "encoding_rs")]
+        if let Some(encoding) = self.fallback_encoding {
+            return Ok(encoding.decode_without_bom_handling(err.as_bytes()).0.into_owned());
+        }
+        Err(err.utf8_error().into())
+    }
+}
+
+/// Handling of items with the same key, see [`ReadOptions::duplicate_keys`](struct.ReadOptions.html#method.duplicate_keys).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Reading fails with [`ErrorKind::DuplicateKey`](enum.ErrorKind.html#variant.DuplicateKey).
+    Error,
+    /// All items are kept.
+    #[default]
+    KeepAll,
+    /// Only the first item with a key is kept.
+    KeepFirst,
+    /// Text and Locator values are joined with a zero byte into the first item with the same key and kind,
+    /// which is how the specification stores lists. Other duplicates are dropped.
+    MergeValues,
+}
+
+/// Validation of item keys, see [`ReadOptions::key_validation`](struct.ReadOptions.html#method.key_validation)
+/// and [`WriteOptions::key_validation`](struct.WriteOptions.html#method.key_validation).
+///
+/// Keys are always checked for length and denied values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyValidation {
+    /// Any characters are allowed.
+    Permissive,
+    /// ASCII characters are allowed, including control ones, the same as item constructors do.
+    #[default]
+    Ascii,
+    /// Only characters from 0x20 (space) up to 0x7E (tilde) are allowed, as required by the specification.
+    Strict,
+}
+
+/// A function comparing items, see [`ItemOrder::Custom`](enum.ItemOrder.html#variant.Custom).
+pub type ItemComparator = Arc<dyn Fn(&Item, &Item) -> Ordering + Send + Sync>;
+
+/// Order of items in a written tag.
+#[derive(Clone, Default)]
+pub enum ItemOrder {
+    /// Ascending by size, as suggested by the specification.
+    #[default]
+    Size,
+    /// Alphabetical by key, case-insensitive.
+    Key,
+    /// The order of items in a tag.
+    Insertion,
+    /// Sorted using the given comparator.
+    Custom(ItemComparator),
+}
+
+impl fmt::Debug for ItemOrder {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemOrder::Size => out.write_str("Size"),
+            ItemOrder::Key => out.write_str("Key"),
+            ItemOrder::Insertion => out.write_str("Insertion"),
+            ItemOrder::Custom(_) => out.write_str("Custom"),
+        }
+    }
+}
+
+/// Version of a written tag.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApeVersion {
+    /// APEv1, supports text items only, has no header.
+    V1,
+    /// APEv2.
+    #[default]
+    V2,
+}
+
+/// Options and flags which can be used to configure how a tag is written or removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{Tag, WriteOptions};
+///
+/// let tag = Tag::new();
+/// WriteOptions::new().backup(true).write_to_path(&tag, "path/to/file").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    backup: bool,
+    backup_path: Option<PathBuf>,
+    journal: bool,
+    padding: u32,
+    garbage_window: u64,
+    preserve_layout: bool,
+    verify_mpeg_frames: bool,
+    buffer_size: Option<u64>,
+    sync: bool,
+    order: ItemOrder,
+    version: ApeVersion,
+    key_validation: KeyValidation,
+    force_denied_keys: bool,
+    progress: Option<Callback<dyn Fn(u64, u64) + Send + Sync>>,
+    should_cancel: Option<Callback<dyn Fn() -> bool + Send + Sync>>,
+}
+
+/// A user-provided function stored in options.
+struct Callback<F: ?Sized>(Arc<F>);
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Callback(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str("Callback")
+    }
+}
+
+impl WriteOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// Writing with these options is the same as calling [`write_to`](fn.write_to.html).
+    pub fn new() -> WriteOptions {
+        Self::default()
+    }
+
+    /// Sets the option to copy the original file before it is modified.
+    ///
+    /// By default the copy is placed next to the original file with the `.orig` suffix appended.
+    /// An existing backup is kept, so it holds the file as it was before the first modification.
+    /// Only path-based functions are able to create a backup.
+    pub fn backup(&mut self, backup: bool) -> &mut WriteOptions {
+        self.backup = backup;
+        self
+    }
+
+    /// Sets a path of the backup copy and enables the backup option.
+    pub fn backup_path<P: Into<PathBuf>>(&mut self, path: P) -> &mut WriteOptions {
+        self.backup = true;
+        self.backup_path = Some(path.into());
+        self
+    }
+
+    /// Sets the option to journal modifications, so they survive a crash or power loss.
+    ///
+    /// Every change is recorded and synced to the `<path>.ape-journal` sidecar file
+    /// before the original file is touched, on Unix the directory is synced after the sidecar is created or removed.
+    /// An interrupted modification is completed by [`recover_journal`](fn.recover_journal.html),
+    /// which is also called before any journaled modification of the same file.
+    /// Only path-based functions are able to keep a journal.
+    pub fn journal(&mut self, journal: bool) -> &mut WriteOptions {
+        self.journal = journal;
+        self
+    }
+
+    /// Sets a number of zero bytes reserved after tag items.
+    ///
+    /// When a file already contains a tag at the end and the new tag fits into it,
+    /// the tag is overwritten in place and the rest of the file is not touched.
+    /// Otherwise the tag is written with the given padding, so subsequent small edits fit into it.
+    pub fn padding(&mut self, padding: u32) -> &mut WriteOptions {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets a number of bytes at the end of a file searched for an existing tag followed by garbage,
+    /// see [`ReadOptions::trailing_garbage_window`](struct.ReadOptions.html#method.trailing_garbage_window).
+    ///
+    /// The tag is removed while the garbage is kept.
+    pub fn trailing_garbage_window(&mut self, window: u64) -> &mut WriteOptions {
+        self.garbage_window = window;
+        self
+    }
+
+    /// Sets the order of items in a written tag, by default items are sorted by size.
+    ///
+    /// Some players display items in the order they are stored.
+    pub fn item_order(&mut self, order: ItemOrder) -> &mut WriteOptions {
+        self.order = order;
+        self
+    }
+
+    /// Sets the version of a written tag, APEv2 by default.
+    ///
+    /// APEv1 is understood by old hardware players.
+    /// Such a tag contains text items only, which are encoded as ISO-8859-1,
+    /// so other characters cause [`ErrorKind::UnencodableText`](enum.ErrorKind.html#variant.UnencodableText).
+    pub fn version(&mut self, version: ApeVersion) -> &mut WriteOptions {
+        self.version = version;
+        self
+    }
+
+    /// Sets how keys are validated before writing, non-ASCII keys cause an error by default.
+    ///
+    /// Keys are checked when a tag is written, so items read with
+    /// [`ReadOptions::key_validation`](struct.ReadOptions.html#method.key_validation) can be written back.
+    pub fn key_validation(&mut self, validation: KeyValidation) -> &mut WriteOptions {
+        self.key_validation = validation;
+        self
+    }
+
+    /// Sets whether items with denied keys are written, it is disabled by default.
+    ///
+    /// Such keys are not allowed by the specification and may confuse readers of other tag formats,
+    /// so writing a tag containing them causes [`ErrorKind::ItemKeyDenied`](enum.ErrorKind.html#variant.ItemKeyDenied).
+    pub fn force_denied_keys(&mut self, force: bool) -> &mut WriteOptions {
+        self.force_denied_keys = force;
+        self
+    }
+
+    /// Sets the option to keep the order of items and the layout of an existing tag.
+    ///
+    /// Items are written in the order of the tag regardless of [`item_order`](#method.item_order).
+    /// The new tag has a header if the existing one has, and a tag located at the start of a file stays there.
+    /// It allows to round-trip tags written by other tools without byte-level changes.
+    /// A tag at the start of a stream whose format forbids it is refused with `ErrorKind::UnsafeTagPlacement`.
+    pub fn preserve_layout(&mut self, preserve_layout: bool) -> &mut WriteOptions {
+        self.preserve_layout = preserve_layout;
+        self
+    }
+
+    /// Sets the option to check MP3 files before a tag is appended.
+    ///
+    /// A tag must follow the last valid MPEG frame, see [`check_frames`](container/mpeg/fn.check_frames.html).
+    /// Writing fails with `ErrorKind::TruncatedFrame` when the last frame is truncated
+    /// and with `ErrorKind::JunkBeforeTag` when junk follows it. Other formats are not checked.
+    pub fn verify_mpeg_frames(&mut self, verify_mpeg_frames: bool) -> &mut WriteOptions {
+        self.verify_mpeg_frames = verify_mpeg_frames;
+        self
+    }
+
+    /// Sets a size of the buffer used to move data following a tag, 4 MiB by default.
+    ///
+    /// Data is moved when a tag located at the start of a file is written or removed
+    /// and when a tag is removed from the end of a file followed by ID3v1 or LYRICS3v2.
+    /// Larger buffers need fewer reads and writes, the buffer is allocated once per operation.
+    /// Journaled modifications move data in chunks of at most 64 KiB, since each one is copied into the journal.
+    pub fn buffer_size(&mut self, buffer_size: u64) -> &mut WriteOptions {
+        self.buffer_size = Some(buffer_size.max(1));
+        self
+    }
+
+    /// Sets the option to flush a modified file to a durable storage before returning.
+    ///
+    /// Path-based functions call `File::sync_all`, other ones call [`Truncate::sync_data`](trait.Truncate.html#method.sync_data).
+    /// Without this option a successful call only means that the operating system has received the data.
+    pub fn sync(&mut self, sync: bool) -> &mut WriteOptions {
+        self.sync = sync;
+        self
+    }
+
+    /// Sets a function called with the number of bytes processed and the total number of bytes
+    /// while data following a tag is moved, e.g. when a tag located at the start of a large file is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ape::WriteOptions;
+    ///
+    /// WriteOptions::new()
+    ///     .progress(|done, total| println!("{}%", done * 100 / total))
+    ///     .remove_from_path("path/to/file")
+    ///     .unwrap();
+    /// ```
+    pub fn progress<F>(&mut self, callback: F) -> &mut WriteOptions
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.progress = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a function checked before every chunk of data is moved.
+    ///
+    /// When it returns `true`, the chunks moved so far are moved back,
+    /// the original tag is restored and [`ErrorKind::Cancelled`](enum.ErrorKind.html#variant.Cancelled) is returned.
+    /// The file is not modified if an operation is cancelled.
+    pub fn should_cancel<F>(&mut self, callback: F) -> &mut WriteOptions
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.should_cancel = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Attempts to write the APE tag to the file at the specified path.
+    pub fn write_to_path<P: AsRef<Path>>(&self, tag: &Tag, path: P) -> Result<()> {
+        self.modify(path.as_ref(), |file, journal| write_tag(tag, file, self, journal))
+    }
+
+    /// Attempts to write the APE tag to a File or any other truncatable storage.
+    pub fn write_to<F: Read + Write + Seek + Truncate>(&self, tag: &Tag, file: &mut F) -> Result<()> {
+        write_tag(tag, file, self, None)?;
+        self.sync_storage(file)
+    }
+
+    /// Attempts to write the APE tag to the file at the specified path
+    /// unless the file already contains the same items.
+    ///
+    /// The file is not opened for writing (and no backup is created) when nothing has changed.
+    /// Returns whether the file has been written.
+    pub fn write_to_path_if_changed<P: AsRef<Path>>(&self, tag: &Tag, path: P) -> Result<bool> {
+        let path = path.as_ref();
+        let changed = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map_err(Error::from)
+            .and_then(|file| is_changed(tag, &mut BufReader::new(file)))
+            .map_err(|err| err.with_path(path))?;
+        if !changed {
+            return Ok(false);
+        }
+        self.write_to_path(tag, path)?;
+        Ok(true)
+    }
+
+    /// Writes the APE tag into a region of exactly `max_len` bytes at the given offset.
+    ///
+    /// See [`write_at`](fn.write_at.html)
+    pub fn write_at<W: Write + Seek>(&self, tag: &Tag, writer: &mut W, offset: u64, max_len: u64) -> Result<()> {
+        write_tag_at(tag, writer, offset, max_len, self)
+    }
+
+    /// Attempts to write the APE tag to a File unless it already contains the same items.
+    ///
+    /// Returns whether the file has been written.
+    pub fn write_to_if_changed<F: Read + Write + Seek + Truncate>(&self, tag: &Tag, file: &mut F) -> Result<bool> {
+        if !is_changed(tag, file)? {
+            return Ok(false);
+        }
+        self.write_to(tag, file)?;
+        Ok(true)
+    }
+
+    /// Attempts to remove APE tag from the file at the specified path.
+    ///
+    /// # Errors
+    ///
+    /// See [`remove_from_path`](fn.remove_from_path.html)
+    pub fn remove_from_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.modify(path.as_ref(), |file, journal| remove_tags(file, self, journal))
+    }
+
+    /// Attempts to remove an APE tag from a File or any other truncatable storage.
+    ///
+    /// # Errors
+    ///
+    /// See [`remove_from_path`](fn.remove_from_path.html)
+    pub fn remove_from<F: Read + Write + Seek + Truncate>(&self, file: &mut F) -> Result<()> {
+        remove_tags(file, self, None)?;
+        self.sync_storage(file)
+    }
+
+    /// Attempts to write the APE tag to an asynchronous storage.
+    ///
+    /// See [`write_to_async`](fn.write_to_async.html)
+    #[cfg(feature = "futures-io")]
+    pub async fn write_to_async<F: AsyncApeWrite>(&self, tag: &Tag, file: &mut F) -> Result<()> {
+        modify_async(file, self, |window| write_tag(tag, window, self, None)).await?;
+        self.sync_storage_async(file).await
+    }
+
+    /// Attempts to remove an APE tag from an asynchronous storage.
+    ///
+    /// See [`remove_from_async`](fn.remove_from_async.html)
+    #[cfg(feature = "futures-io")]
+    pub async fn remove_from_async<F: AsyncApeWrite>(&self, file: &mut F) -> Result<()> {
+        modify_async(file, self, |window| remove_tags(window, self, None)).await?;
+        self.sync_storage_async(file).await
+    }
+
+    /// Describes how the tag would be written to a reader with these options without modifying it.
+    ///
+    /// See [`plan_write`](fn.plan_write.html)
+    pub fn plan_write<R: Read + Seek>(&self, tag: &Tag, reader: &mut R) -> Result<WritePlan> {
+        plan_write_with(tag, reader, self)
+    }
+
+    /// Describes how a tag would be removed from a reader with these options without modifying it.
+    ///
+    /// See [`plan_remove`](fn.plan_remove.html)
+    pub fn plan_remove<R: Read + Seek>(&self, reader: &mut R) -> Result<RemovePlan> {
+        plan_remove_with(reader, self)
+    }
+
+    pub(crate) fn padding_size(&self) -> u32 {
+        self.padding
+    }
+
+    pub(crate) fn garbage_window(&self) -> u64 {
+        self.garbage_window
+    }
+
+    pub(crate) fn preserves_layout(&self) -> bool {
+        self.preserve_layout
+    }
+
+    pub(crate) fn verifies_mpeg_frames(&self) -> bool {
+        self.verify_mpeg_frames
+    }
+
+    pub(crate) fn shift_buffer_size(&self) -> u64 {
+        self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)
+    }
+
+    pub(crate) fn validate_key(&self, key: &str) -> Result<()> {
+        validate_key_with(key, self.key_validation, self.force_denied_keys)
+    }
+
+    pub(crate) fn ape_version(&self) -> ApeVersion {
+        self.version
+    }
+
+    pub(crate) fn order(&self) -> &ItemOrder {
+        &self.order
+    }
+
+    pub(crate) fn report_progress(&self, done: u64, total: u64) {
+        if let Some(Callback(ref progress)) = self.progress {
+            progress(done, total);
+        }
+    }
+
+    pub(crate) fn is_cancellable(&self) -> bool {
+        self.should_cancel.is_some()
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        match self.should_cancel {
+            Some(Callback(ref should_cancel)) => should_cancel(),
+            None => false,
+        }
+    }
+
+    /// Modifies the file at the specified path keeping a journal if needed.
+    ///
+    /// Errors are returned with the path attached.
+    fn modify<F>(&self, path: &Path, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut File, Option<&mut Journal>) -> Result<()>,
+    {
+        self.modify_file(path, f).map_err(|err| err.with_path(path))
+    }
+
+    fn modify_file<F>(&self, path: &Path, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut File, Option<&mut Journal>) -> Result<()>,
+    {
+        if !self.journal {
+            let mut file = self.open(path)?;
+            f(&mut file, None)?;
+            if self.sync {
+                file.sync_all()?;
+            }
+            return Ok(());
+        }
+        recover_journal(path)?;
+        let mut file = self.open(path)?;
+        let mut journal = Journal::create(path)?;
+        match f(&mut file, Some(&mut journal)) {
+            Ok(()) => {
+                file.sync_all()?;
+                journal.finish()?;
+                Ok(())
+            }
+            Err(err) => {
+                journal.abandon()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Flushes a storage modified by a function which is not path-based.
+    fn sync_storage<F: Truncate>(&self, file: &mut F) -> Result<()> {
+        if self.sync {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "futures-io")]
+    async fn sync_storage_async<F: AsyncApeWrite>(&self, file: &mut F) -> Result<()> {
+        if self.sync {
+            file.ape_sync_data().await?;
+        }
+        Ok(())
+    }
+
+    /// Opens a file for modification creating a backup copy if needed.
+    fn open(&self, path: &Path) -> Result<File> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        if self.backup {
+            let backup_path = match self.backup_path {
+                Some(ref backup_path) => backup_path.clone(),
+                None => {
+                    let mut backup_path = OsString::from(path);
+                    backup_path.push(".orig");
+                    PathBuf::from(backup_path)
+                }
+            };
+            // An existing backup is the original one, so it must not be overwritten
+            match OpenOptions::new().write(true).create_new(true).open(backup_path) {
+                Ok(mut backup) => {
+                    io::copy(&mut file, &mut backup)?;
+                    file.rewind()?;
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// Options and flags which can be used to configure how a tag is copied between files.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::CopyOptions;
+///
+/// CopyOptions::new()
+///     .drop_binary_items(true)
+///     .filter(|key| !key.eq_ignore_ascii_case("Comment"))
+///     .copy_tag("path/to/source", "path/to/destination")
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CopyOptions {
+    read_options: ReadOptions,
+    write_options: WriteOptions,
+    filter: Option<Callback<FilterFn>>,
+    drop_binary_items: bool,
+}
+
+impl CopyOptions {
+    /// Creates a blank new set of options.
+    ///
+    /// Copying with these options is the same as calling [`copy_tag`](fn.copy_tag.html).
+    pub fn new() -> CopyOptions {
+        Self::default()
+    }
+
+    /// Sets options used to read the source tag.
+    pub fn read_options(&mut self, options: ReadOptions) -> &mut CopyOptions {
+        self.read_options = options;
+        self
+    }
+
+    /// Sets options used to write the destination tag.
+    pub fn write_options(&mut self, options: WriteOptions) -> &mut CopyOptions {
+        self.write_options = options;
+        self
+    }
+
+    /// Sets a function receiving a key and returning whether the item is copied.
+    pub fn filter<F>(&mut self, filter: F) -> &mut CopyOptions
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Callback(Arc::new(filter)));
+        self
+    }
+
+    /// Sets whether Binary items, e.g. covers, are dropped.
+    pub fn drop_binary_items(&mut self, drop: bool) -> &mut CopyOptions {
+        self.drop_binary_items = drop;
+        self
+    }
+
+    /// Copies the APE tag of a file to another file replacing its tag.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::TagNotFound`](enum.ErrorKind.html#variant.TagNotFound) if the source file has no tag,
+    /// the destination file is not changed then. See also [`read_from_path`](fn.read_from_path.html)
+    /// and [`write_to_path`](fn.write_to_path.html).
+    pub fn copy_tag<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> Result<()> {
+        let tag = self
+            .read_options
+            .read_from_path(src)?
+            .into_iter()
+            .filter(|item| !self.drop_binary_items || item.value.item_type() != ItemType::Binary)
+            .filter(|item| match self.filter {
+                Some(Callback(ref filter)) => filter(&item.key),
+                None => true,
+            })
+            .collect::<Tag>();
+        self.write_options.write_to_path(&tag, dst)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CopyOptions, DuplicateKeys, KeyValidation, ReadOptions, WriteOptions};
+    use crate::{
+        error::ErrorKind,
+        item::{Item, ItemType, ItemValue},
+        key::KeyPool,
+        meta::MetaPosition,
+        storage::Truncate,
+        tag::{read_from, read_from_path, relocate_tag, write_to, Tag},
+        warning::Warning,
+    };
+    use std::{
+        fs::{read, remove_file, write, File},
+        io::{self, Cursor, Read, Seek, SeekFrom, Write},
+        sync::{Arc, Mutex},
+    };
+
+    #[test]
+    fn backup() {
+        let path = "data/backup.apev2";
+        write(path, [1; 200]).unwrap();
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().backup(true).write_to_path(&tag, path).unwrap();
+        assert_eq!(read("data/backup.apev2.orig").unwrap(), vec![1; 200]);
+        assert!(read_from_path(path).is_ok());
+        tag.set_item(Item::from_text("key", "new value").unwrap());
+        WriteOptions::new().backup(true).write_to_path(&tag, path).unwrap();
+        assert_eq!(read("data/backup.apev2.orig").unwrap(), vec![1; 200]);
+
+        let backup_path = "data/backup-custom.apev2";
+        WriteOptions::new()
+            .backup_path(backup_path)
+            .remove_from_path(path)
+            .unwrap();
+        assert!(read_from_path(backup_path).is_ok());
+        assert_eq!(read(path).unwrap(), vec![1; 200]);
+
+        remove_file(path).unwrap();
+        remove_file("data/backup.apev2.orig").unwrap();
+        remove_file(backup_path).unwrap();
+    }
+
+    #[test]
+    fn copy_tag() {
+        let (src, dst) = ("data/copy-src.apev2", "data/copy-dst.apev2");
+        write(src, [1; 100]).unwrap();
+        write(dst, [2; 100]).unwrap();
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        tag.add_item(Item::from_text("Comment", "Comment").unwrap());
+        tag.add_item(Item::from_binary("Cover Art (Front)", vec![0; 16]).unwrap());
+        WriteOptions::new().write_to_path(&tag, src).unwrap();
+        crate::tag::copy_tag(src, dst).unwrap();
+        let copied = read_from_path(dst).unwrap();
+        assert_eq!(copied.iter().collect::<Vec<_>>(), tag.iter().collect::<Vec<_>>());
+        CopyOptions::new()
+            .drop_binary_items(true)
+            .filter(|key| key != "Comment")
+            .copy_tag(src, dst)
+            .unwrap();
+        let keys = read_from_path(dst)
+            .unwrap()
+            .keys()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["Title"]);
+        assert_eq!(read(dst).unwrap()[..100], [2; 100]);
+
+        write(src, [1; 100]).unwrap();
+        let err = CopyOptions::new().copy_tag(src, dst).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TagNotFound);
+        assert!(read_from_path(dst).is_ok());
+        remove_file(src).unwrap();
+        remove_file(dst).unwrap();
+    }
+
+    #[test]
+    fn progress() {
+        let mut data = vec![1; 200];
+        data.extend(b"TAG");
+        data.extend([2; 125]);
+        let mut data = Cursor::new(data);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let progress = calls.clone();
+        WriteOptions::new()
+            .progress(move |done, total| progress.lock().unwrap().push((done, total)))
+            .remove_from(&mut data)
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![(128, 128)]);
+    }
+
+    #[test]
+    fn buffer_size() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+        relocate_tag(&mut data, MetaPosition::Header).unwrap();
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let progress = calls.clone();
+        WriteOptions::new()
+            .buffer_size(64)
+            .progress(move |done, _| progress.lock().unwrap().push(done))
+            .remove_from(&mut data)
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![64, 128, 192, 200]);
+        assert_eq!(data.into_inner(), vec![1; 200]);
+    }
+
+    #[test]
+    fn sync() {
+        struct Synced(Cursor<Vec<u8>>, usize);
+
+        impl Read for Synced {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl Write for Synced {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl Seek for Synced {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        impl Truncate for Synced {
+            fn truncate(&mut self, len: u64) -> io::Result<()> {
+                self.0.truncate(len)
+            }
+
+            fn sync_data(&mut self) -> io::Result<()> {
+                self.1 += 1;
+                Ok(())
+            }
+        }
+
+        let mut data = Synced(Cursor::new(vec![1; 200]), 0);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+        assert_eq!(data.1, 0);
+        WriteOptions::new().sync(true).write_to(&tag, &mut data).unwrap();
+        assert_eq!(data.1, 1);
+        WriteOptions::new().sync(true).remove_from(&mut data).unwrap();
+        assert_eq!(data.1, 2);
+        assert_eq!(data.0.into_inner(), vec![1; 200]);
+
+        let path = "data/sync.apev2";
+        write(path, [1; 200]).unwrap();
+        WriteOptions::new().sync(true).write_to_path(&tag, path).unwrap();
+        WriteOptions::new()
+            .sync(true)
+            .journal(true)
+            .remove_from_path(path)
+            .unwrap();
+        assert_eq!(read(path).unwrap(), vec![1; 200]);
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cancel() {
+        let mut data = vec![1; 200];
+        data.extend(b"TAG");
+        data.extend([2; 125]);
+        let mut data = Cursor::new(data);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+        let original = data.get_ref().clone();
+
+        let err = WriteOptions::new()
+            .should_cancel(|| true)
+            .remove_from(&mut data)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "operation cancelled");
+        assert_eq!(data.get_ref(), &original);
+    }
+
+    #[test]
+    fn padding() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut options = WriteOptions::new();
+        options.padding(64);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        options.write_to(&tag, &mut data).unwrap();
+        let size = data.get_ref().len();
+        assert_eq!(size, 200 + 17 + 64 + 32);
+        assert_eq!(read_from(&mut data).unwrap().item("key"), tag.item("key"));
+
+        tag.set_item(Item::from_text("key", "longer value").unwrap());
+        options.write_to(&tag, &mut data).unwrap();
+        assert_eq!(data.get_ref().len(), size);
+        assert_eq!(read_from(&mut data).unwrap().item("key"), tag.item("key"));
+
+        tag.set_item(Item::from_text("key", vec!["value"; 20].join(" ")).unwrap());
+        options.write_to(&tag, &mut data).unwrap();
+        assert_eq!(data.get_ref().len(), 200 + 131 + 64 + 32);
+        assert_eq!(read_from(&mut data).unwrap().item("key"), tag.item("key"));
+    }
+
+    #[test]
+    fn verify_mpeg_frames() {
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+        let frames = [frame.clone(), frame.clone(), frame].concat();
+        let mut options = WriteOptions::new();
+        options.verify_mpeg_frames(true);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+
+        let mut data = Cursor::new(frames.clone());
+        options.write_to(&tag, &mut data).unwrap();
+        // The tag is replaced at the same position
+        options.write_to(&tag, &mut data).unwrap();
+
+        let mut data = Cursor::new(frames[..frames.len() - 10].to_vec());
+        let err = options.write_to(&tag, &mut data).unwrap_err();
+        assert_eq!(err.to_string(), "APE tag would be placed inside a truncated MPEG frame");
+        assert_eq!(data.get_ref().len(), frames.len() - 10);
+
+        let mut data = Cursor::new([frames, vec![1; 20]].concat());
+        let err = options.write_to(&tag, &mut data).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "junk would be left between the last MPEG frame and APE tag"
+        );
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+        data.get_mut().extend([0, 1, 2]);
+        assert!(read_from(&mut data).is_err());
+        assert!(ReadOptions::new()
+            .trailing_garbage_window(2)
+            .read_from(&mut data)
+            .is_err());
+        let read = ReadOptions::new()
+            .trailing_garbage_window(1024)
+            .read_from(&mut data)
+            .unwrap();
+        assert_eq!(read.item("key"), tag.item("key"));
+
+        WriteOptions::new()
+            .trailing_garbage_window(1024)
+            .remove_from(&mut data)
+            .unwrap();
+        assert_eq!(data.into_inner(), [vec![1; 200], vec![0, 1, 2]].concat());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("artist", "First").unwrap());
+        tag.add_item(Item::from_text("ARTIST", "Second").unwrap());
+        tag.add_item(Item::from_binary("artist", vec![1; 100]).unwrap());
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        let bytes = tag.to_bytes().unwrap();
+        let read = |policy| {
+            ReadOptions::new()
+                .duplicate_keys(policy)
+                .read_from(&mut Cursor::new(&bytes))
+        };
+
+        assert_eq!(read(DuplicateKeys::KeepAll).unwrap().items("artist").len(), 3);
+        let err = read(DuplicateKeys::Error).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+        assert!(err.key().unwrap().eq_ignore_ascii_case("artist"));
+        let first = read(DuplicateKeys::KeepFirst).unwrap();
+        assert_eq!(first.items("artist").len(), 1);
+        assert_eq!(first.iter().count(), 2);
+        let merged = read(DuplicateKeys::MergeValues).unwrap();
+        assert_eq!(merged.iter().count(), 2);
+        assert_eq!(
+            merged.item("artist").unwrap().value,
+            ItemValue::Text(String::from("First\0Second"))
+        );
+    }
+
+    #[test]
+    fn reserved_items() {
+        let path = "data/bad-item-kind.apev2";
+        let err = ReadOptions::new()
+            .read_keys(&mut File::open(path).unwrap())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadItemKind);
+
+        let mut options = ReadOptions::new();
+        options.reserved_items(true);
+        let tag = options.read_from(&mut File::open(path).unwrap()).unwrap();
+        let item = tag.iter().next().unwrap();
+        assert_eq!(item.value.item_type(), ItemType::Reserved);
+        let keys = options.read_keys(&mut File::open(path).unwrap()).unwrap();
+        assert_eq!(keys[0].1, ItemType::Reserved);
+        let bytes = read(path).unwrap();
+        assert_eq!(
+            ReadOptions::new().parse_cow(&bytes).unwrap_err().kind(),
+            ErrorKind::BadItemKind
+        );
+        assert!(options.parse_cow(&bytes).unwrap().into_owned().has_same_items(&tag));
+
+        // The type bits are preserved on write
+        let result = options.read_from(&mut Cursor::new(tag.to_bytes().unwrap())).unwrap();
+        assert!(result.has_same_items(&tag));
+    }
+
+    #[test]
+    fn tolerate_item_count() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_text("artist", "Artist").unwrap());
+        let bytes = tag.to_bytes().unwrap();
+        // Item count of the footer
+        let count = bytes.len() - 16;
+
+        for declared in [1, 3] {
+            let mut bytes = bytes.clone();
+            bytes[count] = declared;
+            assert!(read_from(&mut Cursor::new(&bytes)).is_err());
+
+            let mut options = ReadOptions::new();
+            options.tolerate_item_count(true);
+            let (read, warnings) = options.read_with_warnings(&mut Cursor::new(&bytes)).unwrap();
+            assert!(read.has_same_items(&tag));
+            assert!(warnings.contains(&Warning::ItemCount {
+                declared: u32::from(declared),
+                found: 2
+            }));
+            assert_eq!(options.read_keys(&mut Cursor::new(&bytes)).unwrap().len(), 2);
+            assert!(options.parse_cow(&bytes).unwrap().into_owned().has_same_items(&tag));
+        }
+    }
+
+    #[test]
+    fn key_validation() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Tab\tKey", "value").unwrap());
+        tag.add_item(Item {
+            key: "Название".into(),
+            value: ItemValue::Text(String::from("value")),
+            read_only: false,
+        });
+        let mut data = Cursor::new(Vec::new());
+        let err = WriteOptions::new().write_to(&tag, &mut data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidItemKeyValue);
+        assert_eq!(err.key(), Some("Название"));
+        WriteOptions::new()
+            .key_validation(KeyValidation::Permissive)
+            .write_to(&tag, &mut data)
+            .unwrap();
+        let bytes = data.into_inner();
+
+        let read = |validation| {
+            ReadOptions::new()
+                .key_validation(validation)
+                .read_from(&mut Cursor::new(&bytes))
+        };
+        assert_eq!(
+            read(KeyValidation::Ascii).unwrap_err().kind(),
+            ErrorKind::InvalidItemKeyValue
+        );
+        assert!(read(KeyValidation::Permissive).unwrap().has_same_items(&tag));
+        let err = read(KeyValidation::Strict).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidItemKeyValue);
+        assert_eq!(err.key(), Some("Tab\tKey"));
+
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Key: Value", "value").unwrap());
+        tag.add_item(Item::from_text("Cover Art (Front)", "value").unwrap());
+        let (_, warnings) = ReadOptions::new()
+            .key_validation(KeyValidation::Strict)
+            .read_with_warnings(&mut Cursor::new(tag.to_bytes().unwrap()))
+            .unwrap();
+        assert_eq!(warnings, vec![Warning::KeyCharacters(String::from("Key: Value"))]);
+    }
+
+    #[test]
+    fn denied_keys() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        tag.add_item(Item {
+            key: "TAG".into(),
+            read_only: false,
+            value: ItemValue::Text(String::from("value")),
+        });
+        let mut data = Cursor::new(Vec::new());
+        let err = WriteOptions::new().write_to(&tag, &mut data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ItemKeyDenied);
+        WriteOptions::new()
+            .force_denied_keys(true)
+            .write_to(&tag, &mut data)
+            .unwrap();
+
+        assert_eq!(read_from(&mut data).unwrap_err().kind(), ErrorKind::ItemKeyDenied);
+        let (read, warnings) = ReadOptions::new()
+            .denied_keys(true)
+            .read_with_warnings(&mut data)
+            .unwrap();
+        assert!(read.has_same_items(&tag));
+        assert!(read.item("TAG").unwrap().has_denied_key());
+        assert_eq!(warnings, vec![Warning::DeniedKey(String::from("TAG"))]);
+    }
+
+    #[test]
+    fn spill_binary_values() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        tag.set_item(Item::from_binary("small", vec![2; 10]).unwrap());
+        tag.set_item(Item::from_binary("cover", vec![3; 1000]).unwrap());
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+
+        let spilled = Arc::new(Mutex::new(Vec::new()));
+        let sink = spilled.clone();
+        let result = ReadOptions::new()
+            .spill_binary_values(100, move |key, value| {
+                let mut bytes = Vec::new();
+                value.read_to_end(&mut bytes)?;
+                sink.lock().unwrap().push((key.to_string(), bytes));
+                Ok(())
+            })
+            .read_from(&mut data)
+            .unwrap();
+        assert_eq!(*spilled.lock().unwrap(), vec![("cover".to_string(), vec![3; 1000])]);
+        assert_eq!(result.iter().count(), 2);
+        assert!(result.item("cover").is_none());
+        assert_eq!(result.item("small"), tag.item("small"));
+        assert!(result.is_partial());
+        let err = WriteOptions::new().write_to(&result, &mut data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PartialTag);
+        assert!(read_from(&mut data).unwrap().has_same_items(&tag));
+
+        let result = result.into_iter().collect::<Tag>();
+        assert!(!result.is_partial());
+        WriteOptions::new().write_to(&result, &mut data).unwrap();
+        assert!(read_from(&mut data).unwrap().item("cover").is_none());
+    }
+
+    #[test]
+    fn key_pool() {
+        let mut data = Cursor::new(Vec::new());
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Artist", "First").unwrap());
+        tag.add_item(Item::from_text("Artist", "Second").unwrap());
+        write_to(&tag, &mut data).unwrap();
+        let pool = KeyPool::new();
+        let mut options = ReadOptions::new();
+        options.key_pool(pool.clone());
+        let first = options.read_from(&mut data).unwrap();
+        let second = options.read_from(&mut data).unwrap();
+        let keys = first
+            .iter()
+            .chain(second.iter())
+            .map(|item| &item.key)
+            .collect::<Vec<_>>();
+        assert!(keys.iter().all(|key| Arc::ptr_eq(key, keys[0])));
+        assert_eq!(pool.len(), 1);
+        let tag = ReadOptions::new().read_from(&mut data).unwrap();
+        assert!(!Arc::ptr_eq(&tag.0[0].key, &tag.0[1].key));
+    }
+
+    #[test]
+    fn decode_text() {
+        let options = ReadOptions::new();
+        assert_eq!(options.decode_text(b"text".to_vec()).unwrap(), "text");
+        assert!(options.decode_text(vec![0xcf, 0xf0, 0xe8]).is_err());
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn decode_text_with_fallback_encoding() {
+        let mut options = ReadOptions::new();
+        options.fallback_encoding(encoding_rs::WINDOWS_1251);
+        assert_eq!(options.decode_text(vec![0xcf, 0xf0, 0xe8]).unwrap(), "При");
+        assert_eq!(options.decode_text("При".as_bytes().to_vec()).unwrap(), "При");
+    }
+}
@@ -0,0 +1,564 @@
+use crate::{
+    error::{ErrorKind, Result},
+    item::{validate_key, Item, ItemValue},
+    key::ItemKey,
+    tag::Tag,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+/// Characters not allowed in file names on common file systems, besides control characters.
+const ILLEGAL_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// A part of a pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    /// Text matched as it is.
+    Literal(String),
+    /// An item key.
+    Field(String),
+    /// Parts omitted if a field is missing.
+    Section(Vec<Part>),
+}
+
+/// A pattern of file paths or display strings such as `%artist% - %album%/%track% %title%`.
+///
+/// Fields are names of items enclosed in percent signs, standard keys are matched case-insensitively
+/// and may use underscores instead of spaces, e.g. `%album_artist%`. Other text is matched literally,
+/// slashes separate directories. Parts enclosed in square brackets are omitted if any field inside them is missing,
+/// e.g. `%title%[ (%year%)]`. A backslash escapes the following character, e.g. `\%` or `\[`.
+///
+/// Paths are rendered from tags with [`RenderOptions`](struct.RenderOptions.html),
+/// display strings are rendered with [`FormatOptions`](struct.FormatOptions.html).
+///
+/// # Examples
+///
+/// ```
+/// use ape::{Metadata, Pattern};
+/// use std::path::Path;
+///
+/// let pattern = Pattern::new("%artist% - %album%/%track% %title%").unwrap();
+/// let tag = pattern.match_path(Path::new("/music/Artist - Album/01 Title.ape")).unwrap();
+/// assert_eq!(&*tag.item("album").unwrap().key, "Album");
+///
+/// let metadata = Metadata::from_tag(&tag);
+/// assert_eq!(metadata.track, Some(1));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    parts: Vec<Part>,
+}
+
+impl Pattern {
+    /// Parses a pattern.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::InvalidPattern`](enum.ErrorKind.html#variant.InvalidPattern)
+    /// if a field is empty or not closed, a bracket is unbalanced, a section contains a slash
+    /// or the pattern ends with a backslash. Field names are validated the same way as item keys.
+    pub fn new(pattern: &str) -> Result<Pattern> {
+        let parts = parse_parts(&mut pattern.chars(), false)?;
+        Ok(Pattern { parts })
+    }
+
+    /// Extracts fields from a path.
+    ///
+    /// The pattern is matched against the last components of the path without the extension,
+    /// as many as there are directories in the pattern. Fields never contain slashes
+    /// and are as short as possible, so `%artist% - %title%` splits `A - B - C` at the first hyphen.
+    /// Values are trimmed and empty ones are skipped.
+    ///
+    /// Returns `None` when the path does not match the pattern or is not valid UTF-8.
+    pub fn match_path<P: AsRef<Path>>(&self, path: P) -> Option<Tag> {
+        let path = path.as_ref();
+        // Sections can not contain slashes
+        let depth = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => literal.matches('/').count(),
+                Part::Field(_) | Part::Section(_) => 0,
+            })
+            .sum::<usize>();
+        let mut components = vec![path.file_stem()?.to_str()?];
+        for component in path.parent()?.iter().rev().take(depth) {
+            components.push(component.to_str()?);
+        }
+        if components.len() <= depth {
+            return None;
+        }
+        components.reverse();
+        let text = components.join("/");
+        let mut values = Vec::new();
+        if !match_parts(&self.parts, &[], &text, &mut values) {
+            return None;
+        }
+        let mut tag = Tag::new();
+        for (key, value) in values {
+            let value = value.trim();
+            if !value.is_empty() {
+                tag.add_item(Item::from_text(key, value).ok()?);
+            }
+        }
+        Some(tag)
+    }
+}
+
+/// Options of rendering a [`Pattern`](struct.Pattern.html) to a file path, e.g. to rename files from tags.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{Pattern, RenderOptions, Tag};
+/// use std::path::Path;
+///
+/// let mut tag = Tag::new();
+/// tag.set_text("Artist", "AC/DC").unwrap();
+/// let pattern = Pattern::new("%artist%/%album%").unwrap();
+/// let path = RenderOptions::new().fallback("Unknown Album").render(&pattern, &tag);
+/// assert_eq!(path, Path::new("AC_DC/Unknown Album"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    fallback: String,
+    replacement: char,
+    max_len: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            fallback: String::from("Unknown"),
+            replacement: '_',
+            max_len: 240,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Creates a new set of options.
+    pub fn new() -> RenderOptions {
+        Self::default()
+    }
+
+    /// Sets a value used instead of missing, empty or Binary fields outside of sections, `Unknown` by default.
+    ///
+    /// It is also used instead of components which become empty, e.g. `..`.
+    pub fn fallback<S: Into<String>>(&mut self, fallback: S) -> &mut RenderOptions {
+        self.fallback = fallback.into();
+        self
+    }
+
+    /// Sets a character used instead of illegal ones, `_` by default.
+    ///
+    /// Slashes, backslashes, control characters and `:*?"<>|` are illegal,
+    /// so values work on all common file systems.
+    pub fn replacement(&mut self, replacement: char) -> &mut RenderOptions {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Sets the maximum length of a path component in bytes, 240 by default.
+    ///
+    /// Longer components are truncated, the default limit leaves room for an extension.
+    pub fn max_len(&mut self, max_len: usize) -> &mut RenderOptions {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Renders a relative path from the values of a tag.
+    ///
+    /// Multiple values are joined with a comma. Components are trimmed, trailing dots are removed.
+    pub fn render(&self, pattern: &Pattern, tag: &Tag) -> PathBuf {
+        let fallback = self
+            .fallback
+            .chars()
+            .map(|ch| self.sanitize_char(ch))
+            .collect::<String>();
+        let value = |key: &str| {
+            let values = text_values(tag, key)?;
+            Some(values.join(", ").chars().map(|ch| self.sanitize_char(ch)).collect())
+        };
+        let mut text = String::new();
+        render_parts(&pattern.parts, &value, Some(&fallback), &mut text);
+        text.split('/')
+            .map(|component| self.sanitize_component(component))
+            .collect()
+    }
+
+    fn sanitize_char(&self, ch: char) -> char {
+        if ch.is_control() || ILLEGAL_CHARS.contains(&ch) {
+            self.replacement
+        } else {
+            ch
+        }
+    }
+
+    fn sanitize_component(&self, component: &str) -> String {
+        let component = component.chars().map(|ch| self.sanitize_char(ch)).collect::<String>();
+        let mut component = component.trim();
+        if component.len() > self.max_len {
+            let end = (0..=self.max_len)
+                .rev()
+                .find(|&idx| component.is_char_boundary(idx))
+                .unwrap_or(0);
+            component = &component[..end];
+        }
+        // Windows does not allow trailing dots and spaces, "." and ".." are not names
+        let component = component.trim_end_matches(|ch: char| ch == '.' || ch.is_whitespace());
+        if component.is_empty() {
+            self.fallback.chars().map(|ch| self.sanitize_char(ch)).collect()
+        } else {
+            component.to_string()
+        }
+    }
+}
+
+/// Options of rendering a [`Pattern`](struct.Pattern.html) to a display string, e.g. in players.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{FormatOptions, Pattern, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_multi("Artist", ["First", "Second"]).unwrap();
+/// tag.set_text("Title", "Title").unwrap();
+/// let pattern = Pattern::new("%artist% - %title%[ (%year%)]").unwrap();
+/// assert_eq!(FormatOptions::new().separator(" & ").format(&pattern, &tag), "First & Second - Title");
+/// assert_eq!(FormatOptions::new().first_value(true).format(&pattern, &tag), "First - Title");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    separator: String,
+    first_value: bool,
+    fallback: String,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            separator: String::from(", "),
+            first_value: false,
+            fallback: String::new(),
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Creates a new set of options.
+    pub fn new() -> FormatOptions {
+        Self::default()
+    }
+
+    /// Sets a separator of multiple values, `, ` by default.
+    pub fn separator<S: Into<String>>(&mut self, separator: S) -> &mut FormatOptions {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Sets whether only the first one of multiple values is used.
+    pub fn first_value(&mut self, first_value: bool) -> &mut FormatOptions {
+        self.first_value = first_value;
+        self
+    }
+
+    /// Sets a value used instead of missing, empty or Binary fields outside of sections, empty by default.
+    pub fn fallback<S: Into<String>>(&mut self, fallback: S) -> &mut FormatOptions {
+        self.fallback = fallback.into();
+        self
+    }
+
+    /// Renders a string from the values of a tag.
+    pub fn format(&self, pattern: &Pattern, tag: &Tag) -> String {
+        let value = |key: &str| {
+            let values = text_values(tag, key)?;
+            if self.first_value {
+                Some(values[0].to_string())
+            } else {
+                Some(values.join(&self.separator))
+            }
+        };
+        let mut text = String::new();
+        render_parts(&pattern.parts, &value, Some(&self.fallback), &mut text);
+        text
+    }
+}
+
+impl Tag {
+    /// Renders a display string such as `%artist% - %title%[ (%year%)]`, see [`Pattern`](struct.Pattern.html).
+    ///
+    /// Multiple values are joined with a comma, use [`FormatOptions`](struct.FormatOptions.html) for other options.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the pattern is invalid, see [`Pattern::new`](struct.Pattern.html#method.new).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_text("Artist", "Artist").unwrap();
+    /// tag.set_text("Title", "Title").unwrap();
+    /// tag.set_text("Year", "2001").unwrap();
+    /// assert_eq!(tag.format("%artist% - %title%[ (%year%)]").unwrap(), "Artist - Title (2001)");
+    /// ```
+    pub fn format(&self, pattern: &str) -> Result<String> {
+        Ok(FormatOptions::new().format(&Pattern::new(pattern)?, self))
+    }
+}
+
+/// Parses parts until the end of a pattern or a section.
+fn parse_parts(chars: &mut Chars, in_section: bool) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    loop {
+        let ch = match chars.next() {
+            Some(']') if in_section => break,
+            Some('/') if in_section => return Err(ErrorKind::InvalidPattern.into()),
+            None if in_section => return Err(ErrorKind::InvalidPattern.into()),
+            Some(ch) => ch,
+            None => break,
+        };
+        match ch {
+            '\\' => literal.push(chars.next().ok_or(ErrorKind::InvalidPattern)?),
+            '%' => {
+                let (name, tail) = chars.as_str().split_once('%').ok_or(ErrorKind::InvalidPattern)?;
+                if name.is_empty() {
+                    return Err(ErrorKind::InvalidPattern.into());
+                }
+                let key = field_key(name);
+                validate_key(&key)?;
+                *chars = tail.chars();
+                flush_literal(&mut parts, &mut literal);
+                parts.push(Part::Field(key));
+            }
+            '[' => {
+                flush_literal(&mut parts, &mut literal);
+                parts.push(Part::Section(parse_parts(chars, true)?));
+            }
+            ']' => return Err(ErrorKind::InvalidPattern.into()),
+            ch => literal.push(ch),
+        }
+    }
+    flush_literal(&mut parts, &mut literal);
+    Ok(parts)
+}
+
+fn flush_literal(parts: &mut Vec<Part>, literal: &mut String) {
+    if !literal.is_empty() {
+        parts.push(Part::Literal(std::mem::take(literal)));
+    }
+}
+
+/// Renders parts, returns `false` if a field is missing and there is no fallback.
+fn render_parts(
+    parts: &[Part],
+    value: &dyn Fn(&str) -> Option<String>,
+    fallback: Option<&str>,
+    text: &mut String,
+) -> bool {
+    for part in parts {
+        match part {
+            Part::Literal(literal) => text.push_str(literal),
+            Part::Field(key) => match (value(key), fallback) {
+                (Some(value), _) => text.push_str(&value),
+                (None, Some(fallback)) => text.push_str(fallback),
+                (None, None) => return false,
+            },
+            Part::Section(parts) => {
+                let mut section = String::new();
+                if render_parts(parts, value, None, &mut section) {
+                    text.push_str(&section);
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Returns trimmed non-empty values of a Text or Locator item, `None` if there are no such values.
+fn text_values<'a>(tag: &'a Tag, key: &str) -> Option<Vec<&'a str>> {
+    let values = match tag.item(key)?.value {
+        ItemValue::Text(ref value) | ItemValue::Locator(ref value) => value
+            .split('\0')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>(),
+        ItemValue::Binary(_) | ItemValue::Reserved(_) => return None,
+    };
+    Some(values).filter(|values| !values.is_empty())
+}
+
+/// Returns a key of an item used by a field.
+fn field_key(name: &str) -> String {
+    match ItemKey::from(name.replace('_', " ")) {
+        ItemKey::Custom(_) => name.to_string(),
+        key => key.into(),
+    }
+}
+
+/// Matches parts followed by the next ones against a text collecting field values,
+/// backtracks if a field or a section is followed by a mismatch.
+fn match_parts<'a>(
+    parts: &'a [Part],
+    next: &[&'a [Part]],
+    text: &'a str,
+    values: &mut Vec<(&'a str, &'a str)>,
+) -> bool {
+    match parts.split_first() {
+        None => match next.split_first() {
+            Some((parts, next)) => match_parts(parts, next, text, values),
+            None => text.is_empty(),
+        },
+        Some((Part::Literal(literal), rest)) => match text.strip_prefix(literal.as_str()) {
+            Some(text) => match_parts(rest, next, text, values),
+            None => false,
+        },
+        Some((Part::Section(section), rest)) => {
+            let mut section_next = vec![rest];
+            section_next.extend_from_slice(next);
+            match_parts(section, &section_next, text, values) || match_parts(rest, next, text, values)
+        }
+        Some((Part::Field(key), rest)) => {
+            let max_len = text.find('/').unwrap_or(text.len());
+            let ends = text[..max_len].char_indices().map(|(idx, _)| idx).chain([max_len]);
+            for idx in ends.filter(|&idx| idx > 0) {
+                values.push((key, &text[..idx]));
+                if match_parts(rest, next, &text[idx..], values) {
+                    return true;
+                }
+                values.pop();
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FormatOptions, Part, Pattern, RenderOptions};
+    use crate::{error::ErrorKind, item::Item, tag::Tag};
+    use std::path::Path;
+
+    #[test]
+    fn parse() {
+        let pattern = Pattern::new("%track%. %ALBUM_ARTIST%/%Mood%").unwrap();
+        assert_eq!(
+            pattern.parts,
+            vec![
+                Part::Field(String::from("Track")),
+                Part::Literal(String::from(". ")),
+                Part::Field(String::from("Album Artist")),
+                Part::Literal(String::from("/")),
+                Part::Field(String::from("Mood")),
+            ]
+        );
+        assert_eq!(Pattern::new("%title").unwrap_err().kind(), ErrorKind::InvalidPattern);
+        assert_eq!(Pattern::new("%%").unwrap_err().kind(), ErrorKind::InvalidPattern);
+        assert_eq!(Pattern::new("%TAG%").unwrap_err().kind(), ErrorKind::ItemKeyDenied);
+
+        let pattern = Pattern::new("\\%\\[%title%[ (%year%[, %Mood%])]\\\\").unwrap();
+        assert_eq!(
+            pattern.parts,
+            vec![
+                Part::Literal(String::from("%[")),
+                Part::Field(String::from("Title")),
+                Part::Section(vec![
+                    Part::Literal(String::from(" (")),
+                    Part::Field(String::from("Year")),
+                    Part::Section(vec![
+                        Part::Literal(String::from(", ")),
+                        Part::Field(String::from("Mood"))
+                    ]),
+                    Part::Literal(String::from(")")),
+                ]),
+                Part::Literal(String::from("\\")),
+            ]
+        );
+        for pattern in ["[%title%", "%title%]", "[a/b]", "a\\"] {
+            assert_eq!(Pattern::new(pattern).unwrap_err().kind(), ErrorKind::InvalidPattern);
+        }
+    }
+
+    #[test]
+    fn match_path() {
+        let pattern = Pattern::new("%artist% - %album%/%track% %title%").unwrap();
+        let tag = pattern
+            .match_path("music/Artist - Album - Live/01 Some Title.flac.ape")
+            .unwrap();
+        let items = tag.iter().collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                &Item::from_text("Artist", "Artist").unwrap(),
+                &Item::from_text("Album", "Album - Live").unwrap(),
+                &Item::from_text("Track", "01").unwrap(),
+                &Item::from_text("Title", "Some Title.flac").unwrap(),
+            ]
+        );
+        assert!(pattern.match_path("Artist - Album/01").is_none());
+        assert!(pattern.match_path("01 Title.ape").is_none());
+        assert!(pattern.match_path("Artist/01 Title.ape").is_none());
+
+        let pattern = Pattern::new("%track%[ - %album%]. %title%").unwrap();
+        let tag = pattern.match_path("01. Title.ape").unwrap();
+        assert_eq!(tag["title"], Item::from_text("Title", "Title").unwrap());
+        let tag = pattern.match_path("01 - Album. Title.ape").unwrap();
+        assert_eq!(tag["album"], Item::from_text("Album", "Album").unwrap());
+        assert_eq!(tag.iter().count(), 3);
+
+        let pattern = Pattern::new("%track%%title%").unwrap();
+        let tag = pattern.match_path("1 .ape").unwrap();
+        assert_eq!(
+            tag.iter().collect::<Vec<_>>(),
+            vec![&Item::from_text("Track", "1").unwrap()]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Artist", " First\0Second: Live ").unwrap());
+        tag.add_item(Item::from_text("Album", "..").unwrap());
+        tag.add_item(Item::from_text("Track", "01").unwrap());
+        tag.add_item(Item::from_text("Title", "Title / Remix?").unwrap());
+        tag.add_item(Item::from_binary("Mood", vec![1]).unwrap());
+        let pattern = Pattern::new("%artist%/%album%/%track%. %title% \\[%mood%\\]").unwrap();
+        assert_eq!(
+            RenderOptions::new().render(&pattern, &tag),
+            Path::new("First, Second_ Live/Unknown/01. Title _ Remix_ [Unknown]")
+        );
+
+        let pattern = Pattern::new("%title%/%track% ...").unwrap();
+        let path = RenderOptions::new()
+            .fallback("?")
+            .replacement('-')
+            .max_len(7)
+            .render(&pattern, &tag);
+        assert_eq!(path, Path::new("Title -/01"));
+    }
+
+    #[test]
+    fn format() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Artist", "First\0 \0Second").unwrap());
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        tag.add_item(Item::from_binary("Year", vec![1]).unwrap());
+        assert_eq!(
+            tag.format("%artist% - %title%[ (%year%)][ \\[%title%\\]]").unwrap(),
+            "First, Second - Title [Title]"
+        );
+        let pattern = Pattern::new("%artist% - %album%[ (%title%)]").unwrap();
+        let text = FormatOptions::new().separator("/").fallback("?").format(&pattern, &tag);
+        assert_eq!(text, "First/Second - ? (Title)");
+        let text = FormatOptions::new().first_value(true).format(&pattern, &tag);
+        assert_eq!(text, "First -  (Title)");
+        assert!(tag.format("%title").is_err());
+    }
+}
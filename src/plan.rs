@@ -0,0 +1,307 @@
+use crate::{
+    error::{ErrorKind, Result},
+    meta::{Meta, MetaPosition},
+    options::WriteOptions,
+    tag::{existing_tags, plan_placement, precedes_trailers, Placement, Tag},
+    util::probe_trailers,
+};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Describes changes which would be made to a file by [`remove_from`](fn.remove_from.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemovePlan {
+    /// Regions occupied by the existing tags including header and footer,
+    /// a file may contain a tag at the end and a tag at the start.
+    pub tags: Vec<Range<u64>>,
+    /// Number of bytes following the tags which would be moved within the file.
+    ///
+    /// ID3v1 and LYRICS3v2 following a tag at the end are rewritten after truncating the file instead,
+    /// so they are not counted.
+    pub shifted_bytes: u64,
+    /// Range of the resulting file which would be rewritten.
+    pub rewritten: Range<u64>,
+    /// File size after the removal.
+    pub final_size: u64,
+}
+
+/// Describes changes which would be made to a file by [`write_to`](fn.write_to.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WritePlan {
+    /// Changes made while removing the existing tags.
+    ///
+    /// A tag updated in place or replaced at the start of a file is listed, but not removed.
+    pub remove: RemovePlan,
+    /// Range of the resulting file occupied by the new tag.
+    pub tag: Range<u64>,
+    /// Range of the resulting file which would be rewritten,
+    /// including the new tag and preserved ID3v1 and Lyrics3 v2.00 tags.
+    pub rewritten: Range<u64>,
+    /// File size after the write.
+    pub final_size: u64,
+}
+
+/// Describes how a tag would be removed from a reader without modifying it.
+///
+/// # Errors
+///
+/// See [`remove_from_path`](fn.remove_from_path.html)
+pub fn plan_remove<R: Read + Seek>(reader: &mut R) -> Result<RemovePlan> {
+    WriteOptions::new().plan_remove(reader)
+}
+
+/// Describes how a tag would be written to a reader without modifying it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{plan_write, Tag};
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// let plan = plan_write(&Tag::new(), &mut file).unwrap();
+/// if plan.remove.shifted_bytes > 1 << 30 {
+///     println!("Writing the tag will rewrite more than 1 GiB");
+/// }
+/// ```
+pub fn plan_write<R: Read + Seek>(tag: &Tag, reader: &mut R) -> Result<WritePlan> {
+    WriteOptions::new().plan_write(tag, reader)
+}
+
+pub(crate) fn plan_remove_with<R: Read + Seek>(reader: &mut R, options: &WriteOptions) -> Result<RemovePlan> {
+    let all = existing_tags(reader, options)?;
+    plan_regions(reader, &all)
+}
+
+pub(crate) fn plan_write_with<R: Read + Seek>(tag: &Tag, reader: &mut R, options: &WriteOptions) -> Result<WritePlan> {
+    let (placement, tag_size) = plan_placement(tag, reader, options)?;
+    match placement {
+        Placement::Start => {
+            let (start, end): (Vec<_>, Vec<_>) = existing_tags(reader, options)?
+                .into_iter()
+                .partition(|meta| meta.position == MetaPosition::Header);
+            let mut remove = plan_regions(reader, &end)?;
+            for meta in &start {
+                remove.tags.push(region(meta)?);
+            }
+            let old_size = start.first().map(Meta::region).transpose()?.map_or(0, |(_, size)| size);
+            let final_size = remove.final_size - old_size + tag_size;
+            // Bytes following the tag at the start are moved once by the difference of sizes
+            if old_size != tag_size {
+                remove.shifted_bytes += remove.final_size - old_size;
+            }
+            let is_moved = old_size != tag_size || !remove.rewritten.is_empty();
+            Ok(WritePlan {
+                tag: 0..tag_size,
+                rewritten: 0..if is_moved { final_size } else { tag_size },
+                final_size,
+                remove,
+            })
+        }
+        Placement::InPlace { offset, size } => {
+            let filesize = reader.seek(SeekFrom::End(0))?;
+            let tag = offset..offset + size;
+            Ok(WritePlan {
+                remove: RemovePlan {
+                    tags: vec![tag.clone()],
+                    shifted_bytes: 0,
+                    rewritten: filesize..filesize,
+                    final_size: filesize,
+                },
+                rewritten: tag.clone(),
+                tag,
+                final_size: filesize,
+            })
+        }
+        Placement::End => {
+            let remove = plan_remove_with(reader, options)?;
+            let trailers_size = probe_trailers(reader)?;
+            let tag_offset = remove.final_size - trailers_size;
+            let final_size = remove.final_size + tag_size;
+            Ok(WritePlan {
+                tag: tag_offset..tag_offset + tag_size,
+                rewritten: remove.rewritten.start.min(tag_offset)..final_size,
+                final_size,
+                remove,
+            })
+        }
+    }
+}
+
+/// Describes removal of the tags by `remove_regions`, the tag at the end goes first.
+fn plan_regions<R: Read + Seek>(reader: &mut R, all: &[Meta]) -> Result<RemovePlan> {
+    let mut filesize = reader.seek(SeekFrom::End(0))?;
+    let mut shifted_bytes = 0;
+    let mut rewritten_start = u64::MAX;
+    for meta in all {
+        let (offset, size) = meta.region()?;
+        // A tag may claim more bytes than the file contains
+        let movesize = filesize.checked_sub(offset + size).ok_or(ErrorKind::BadTagSize)?;
+        // A tag followed only by trailers is truncated and the trailers are written back
+        if !precedes_trailers(reader, (offset, size), filesize)? {
+            shifted_bytes += movesize;
+        }
+        if movesize > 0 {
+            rewritten_start = rewritten_start.min(offset);
+        }
+        filesize -= size;
+    }
+    Ok(RemovePlan {
+        tags: all.iter().map(region).collect::<Result<_>>()?,
+        shifted_bytes,
+        rewritten: rewritten_start.min(filesize)..filesize,
+        final_size: filesize,
+    })
+}
+
+fn region(meta: &Meta) -> Result<Range<u64>> {
+    let (offset, size) = meta.region()?;
+    Ok(offset..offset + size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{plan_remove, plan_write, RemovePlan, WritePlan};
+    use crate::{
+        error::ErrorKind,
+        item::Item,
+        meta::{Layout, Meta},
+        options::WriteOptions,
+        tag::{remove_from, serialize, write_to, Tag},
+    };
+    use std::{
+        fs::{remove_file, OpenOptions},
+        io::{Cursor, Seek, SeekFrom, Write},
+    };
+
+    #[test]
+    fn oversized_tag() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let at_start = Layout {
+            header: true,
+            footer: true,
+            at_start: true,
+        };
+        let mut bytes = serialize(&tag, &WriteOptions::new(), 0, at_start).unwrap();
+        bytes[12..16].copy_from_slice(&1000u32.to_le_bytes());
+        let mut data = Cursor::new([&bytes[..], &[1; 100]].concat());
+        assert_eq!(plan_remove(&mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+        assert_eq!(plan_write(&tag, &mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+        assert_eq!(remove_from(&mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+    }
+
+    #[test]
+    fn plan() {
+        let path = "data/plan.apev2";
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap();
+        file.write_all(&[0; 200]).unwrap();
+        file.write_all(b"TAG").unwrap();
+        file.write_all(&[0; 125]).unwrap();
+
+        assert_eq!(
+            plan_remove(&mut file).unwrap(),
+            RemovePlan {
+                tags: Vec::new(),
+                shifted_bytes: 0,
+                rewritten: 328..328,
+                final_size: 328,
+            }
+        );
+
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let plan = plan_write(&tag, &mut file).unwrap();
+        assert_eq!(plan.tag, 200..249);
+        assert_eq!(plan.rewritten, 200..377);
+        assert_eq!(plan.final_size, 377);
+
+        write_to(&tag, &mut file).unwrap();
+        assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), plan.final_size);
+
+        let plan = plan_write(&tag, &mut file).unwrap();
+        assert_eq!(plan.remove.tags, vec![200..249]);
+        // The tag is followed only by ID3v1, so the file is truncated instead of moving data
+        assert_eq!(plan.remove.shifted_bytes, 0);
+        assert_eq!(plan.remove.rewritten, 200..328);
+        assert_eq!(plan.remove.final_size, 328);
+        assert_eq!(plan.tag, 200..249);
+        assert_eq!(plan.final_size, 377);
+
+        remove_file(path).unwrap();
+    }
+
+    /// Writes the tag and checks the result against the plan.
+    fn write_as_planned(tag: &Tag, data: &[u8], options: &WriteOptions) -> WritePlan {
+        let plan = options.plan_write(tag, &mut Cursor::new(data)).unwrap();
+        let mut file = Cursor::new(data.to_vec());
+        options.write_to(tag, &mut file).unwrap();
+        let result = file.into_inner();
+        assert_eq!(result.len() as u64, plan.final_size);
+        let (offset, size) = Meta::read(&mut Cursor::new(&result)).unwrap().region().unwrap();
+        assert_eq!(offset..offset + size, plan.tag);
+        let unchanged = data.iter().zip(&result).take_while(|(a, b)| a == b).count() as u64;
+        assert!(unchanged >= plan.rewritten.start.min(data.len().min(result.len()) as u64));
+        if data.len() != result.len() {
+            assert_eq!(plan.rewritten.end, plan.final_size);
+        }
+        plan
+    }
+
+    #[test]
+    fn plan_with_options() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let audio = vec![1; 300];
+        let mut options = WriteOptions::new();
+
+        // A padded tag is updated in place
+        options.padding(64);
+        let data = [&audio[..], &serialize(&tag, &options, 64, Layout::default()).unwrap()].concat();
+        tag.set_item(Item::from_text("key", "longer value").unwrap());
+        let plan = write_as_planned(&tag, &data, &options);
+        assert_eq!(plan.tag, 300..data.len() as u64);
+        assert_eq!(plan.remove.shifted_bytes, 0);
+        assert_eq!(plan.final_size, data.len() as u64);
+
+        // Without padding the tag is removed and appended
+        options.padding(0);
+        let plan = write_as_planned(&tag, &data, &options);
+        assert_eq!(plan.remove.tags, vec![300..data.len() as u64]);
+        assert_eq!(plan.tag.start, 300);
+
+        // A tag at the start is replaced in place when the layout is preserved
+        options.preserve_layout(true);
+        let at_start = Layout {
+            header: true,
+            footer: true,
+            at_start: true,
+        };
+        let start = serialize(&tag, &options, 0, at_start).unwrap();
+        let data = [&start[..], &audio].concat();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let plan = write_as_planned(&tag, &data, &options);
+        assert_eq!(plan.remove.tags.len(), 1);
+        assert_eq!(plan.remove.tags[0], 0..start.len() as u64);
+        assert_eq!(plan.remove.shifted_bytes, 300);
+        assert_eq!(plan.tag.start, 0);
+        assert_eq!(plan.rewritten, 0..plan.final_size);
+        assert_eq!(plan.final_size, plan.tag.end + 300);
+
+        // Both tags are removed, the layout of the tag at the end is kept
+        let end = serialize(&tag, &options, 0, Layout::default()).unwrap();
+        let data = [&start[..], &audio, &end].concat();
+        let plan = write_as_planned(&tag, &data, &options);
+        assert_eq!(plan.remove.tags.len(), 2);
+        assert_eq!(plan.remove.shifted_bytes, 300);
+        assert_eq!(plan.tag, 300..300 + end.len() as u64);
+    }
+}
@@ -0,0 +1,98 @@
+//! Predicates for [`Tag::find`](../struct.Tag.html#method.find).
+//!
+//! Predicates check Text and Locator values, multiple values separated by zero bytes are checked separately.
+//!
+//! # Examples
+//!
+//! ```
+//! use ape::{query, Tag};
+//!
+//! let mut tag = Tag::new();
+//! tag.set_text("Label", "Old Label").unwrap();
+//! tag.set_text("Comment", "Released by Old Label").unwrap();
+//! assert_eq!(tag.find(query::contains("Old Label")).len(), 2);
+//! assert_eq!(tag.find(query::equals_ignore_case("old label")).len(), 1);
+//! ```
+
+use crate::{
+    item::{Item, ItemValue},
+    tag::Tag,
+};
+
+impl Tag {
+    /// Returns items for which the predicate returns `true`, in the order they are stored in the tag.
+    ///
+    /// The predicate receives a key and an item, see the [`query`](query/index.html) module for common ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::{ItemValue, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_binary("Cover Art (Front)", vec![0; 16]).unwrap();
+    /// let binary = tag.find(|_, item| matches!(item.value, ItemValue::Binary(_)));
+    /// assert_eq!(&*binary[0].key, "Cover Art (Front)");
+    /// ```
+    pub fn find<F: FnMut(&str, &Item) -> bool>(&self, mut predicate: F) -> Vec<&Item> {
+        self.iter().filter(|item| predicate(&item.key, item)).collect()
+    }
+}
+
+/// Matches items with a value containing the given text, case-sensitive.
+pub fn contains<S: Into<String>>(text: S) -> impl Fn(&str, &Item) -> bool {
+    let text = text.into();
+    move |_, item| values(item).any(|value| value.contains(&text))
+}
+
+/// Matches items with a value equal to the given one ignoring ASCII case.
+pub fn equals_ignore_case<S: Into<String>>(value: S) -> impl Fn(&str, &Item) -> bool {
+    let expected = value.into();
+    move |_, item| values(item).any(|value| value.eq_ignore_ascii_case(&expected))
+}
+
+/// Matches items with a value matching the given regular expression.
+#[cfg(feature = "regex")]
+pub fn regex(regex: regex::Regex) -> impl Fn(&str, &Item) -> bool {
+    move |_, item| values(item).any(|value| regex.is_match(value))
+}
+
+/// Returns values of Text and Locator items.
+fn values(item: &Item) -> impl Iterator<Item = &str> {
+    let value = match item.value {
+        ItemValue::Text(ref value) | ItemValue::Locator(ref value) => Some(value.split('\0')),
+        ItemValue::Binary(_) | ItemValue::Reserved(_) => None,
+    };
+    value.into_iter().flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{contains, equals_ignore_case};
+    use crate::{item::Item, tag::Tag};
+
+    #[test]
+    fn find() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Label", "First Label\0Second Label").unwrap());
+        tag.add_item(Item::from_locator("Related", "http://example.com/label").unwrap());
+        tag.add_item(Item::from_binary("Label Logo", b"Label".as_ref()).unwrap());
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        assert_eq!(tag.find(contains("Label")).len(), 1);
+        assert_eq!(&*tag.find(contains("label"))[0].key, "Related");
+        assert_eq!(&*tag.find(equals_ignore_case("second label"))[0].key, "Label");
+        assert!(tag.find(equals_ignore_case("label")).is_empty());
+        assert_eq!(tag.find(|key, _| key.starts_with("Label")).len(), 2);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Year", "2001\u{0}2002").unwrap());
+        tag.add_item(Item::from_text("Title", "Title 2003").unwrap());
+        let regex = regex::Regex::new("^[0-9]{4}$").unwrap();
+        assert_eq!(&*tag.find(super::regex(regex))[0].key, "Year");
+        assert_eq!(tag.find(super::regex(regex::Regex::new("20").unwrap())).len(), 2);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    item::{Item, ItemValue},
+    key::ItemKey,
+    tag::Tag,
+};
+
+/// A scale used by a player to store ratings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RatingScale {
+    /// From 0 to 5 stars.
+    Stars,
+    /// From 0 to 100.
+    Percent,
+    /// From 0 to 255.
+    Byte,
+}
+
+impl RatingScale {
+    fn max(self) -> f64 {
+        match self {
+            RatingScale::Stars => 5.0,
+            RatingScale::Percent => 100.0,
+            RatingScale::Byte => 255.0,
+        }
+    }
+}
+
+/// A rating normalized to the range from 0.0 to 1.0.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{Rating, RatingScale};
+///
+/// let rating = Rating::from_scale(4.0, RatingScale::Stars);
+/// assert_eq!(rating.to_scale(RatingScale::Percent), 80);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Rating(f64);
+
+impl Rating {
+    /// Creates a rating from a normalized value.
+    ///
+    /// The value is clamped to the range from 0.0 to 1.0.
+    pub fn new(value: f64) -> Rating {
+        Rating(if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) })
+    }
+
+    /// Creates a rating from a value in the given scale.
+    pub fn from_scale(value: f64, scale: RatingScale) -> Rating {
+        Rating::new(value / scale.max())
+    }
+
+    /// Returns the normalized value.
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Converts the rating to the given scale rounding to the nearest integer.
+    pub fn to_scale(self, scale: RatingScale) -> u32 {
+        (self.0 * scale.max()).round() as u32
+    }
+}
+
+impl Tag {
+    /// Returns a rating stored in the given scale.
+    ///
+    /// Returns `None` when there is no Rating item or its value is not a number.
+    pub fn rating(&self, scale: RatingScale) -> Option<Rating> {
+        match self.item(ItemKey::Rating)?.value {
+            ItemValue::Text(ref value) => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|value| Rating::from_scale(value, scale)),
+            _ => None,
+        }
+    }
+
+    /// Sets a rating stored in the given scale.
+    pub fn set_rating(&mut self, rating: Rating, scale: RatingScale) {
+        self.set_item(Item {
+            key: ItemKey::Rating.into(),
+            value: ItemValue::Text(rating.to_scale(scale).to_string()),
+            read_only: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rating, RatingScale};
+    use crate::{item::Item, tag::Tag};
+
+    #[test]
+    fn scales() {
+        assert_eq!(Rating::from_scale(5.0, RatingScale::Stars).value(), 1.0);
+        assert_eq!(Rating::from_scale(300.0, RatingScale::Byte).value(), 1.0);
+        assert_eq!(Rating::new(-1.0).value(), 0.0);
+        assert_eq!(Rating::new(f64::NAN).value(), 0.0);
+        let rating = Rating::from_scale(3.0, RatingScale::Stars);
+        assert_eq!(rating.to_scale(RatingScale::Percent), 60);
+        assert_eq!(rating.to_scale(RatingScale::Byte), 153);
+        assert_eq!(
+            Rating::from_scale(153.0, RatingScale::Byte).to_scale(RatingScale::Stars),
+            3
+        );
+    }
+
+    #[test]
+    fn tag_rating() {
+        let mut tag = Tag::new();
+        assert!(tag.rating(RatingScale::Stars).is_none());
+        tag.set_rating(Rating::new(0.8), RatingScale::Percent);
+        assert_eq!(&*tag.item("rating").unwrap().key, "Rating");
+        assert_eq!(
+            tag.rating(RatingScale::Percent).unwrap().to_scale(RatingScale::Stars),
+            4
+        );
+        assert_eq!(tag.rating(RatingScale::Percent).unwrap().value(), 0.8);
+        tag.set_item(Item::from_text("Rating", "bad").unwrap());
+        assert!(tag.rating(RatingScale::Stars).is_none());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    error::{ErrorKind, Result},
+    meta::{Meta, MetaPosition, APE_HEADER_SIZE, HAS_HEADER, HAS_NO_FOOTER, IS_HEADER},
+    scan::scan_for_tags,
+    util::APE_PREAMBLE,
+};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Size of the area searched for a misplaced header.
+const HEADER_WINDOW: u64 = 16 * 1024 * 1024;
+
+/// Describes an inconsistency fixed by [`repair`](fn.repair.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagFix {
+    /// Tag size did not match the actual size of items.
+    Size,
+    /// Item count did not match the actual number of items.
+    ItemCount,
+    /// Flags of the header did not match flags of the footer.
+    HeaderFlags,
+    /// Footer claimed a header which is missing.
+    MissingHeader,
+}
+
+/// Fixes common inconsistencies of a tag located at the end of a file.
+///
+/// Items are located using the header (if any) or the size declared in the footer,
+/// then the size, the item count and the flags of the header and footer are rewritten in place.
+/// Items themselves are not changed.
+/// Returns a list of fixes, it is empty when the tag is consistent or is located at the start of a file.
+///
+/// # Errors
+///
+/// It is considered a error when a file contains no tag or items can not be located.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{read_from, repair};
+/// use std::fs::OpenOptions;
+///
+/// let mut file = OpenOptions::new().read(true).write(true).open("path/to/file").unwrap();
+/// let fixes = repair(&mut file).unwrap();
+/// println!("{:?}", fixes);
+/// let tag = read_from(&mut file).unwrap();
+/// ```
+pub fn repair<F: Read + Write + Seek>(file: &mut F) -> Result<Vec<TagFix>> {
+    // The footer is not parsed, so a size which does not fit into the file can be repaired
+    let footer_offset = Meta::locate(file)?;
+    let mut footer = [0; APE_HEADER_SIZE as usize];
+    file.seek(SeekFrom::Start(footer_offset))?;
+    file.read_exact(&mut footer)?;
+    let declared_size = LittleEndian::read_u32(&footer[12..]);
+    let declared_count = LittleEndian::read_u32(&footer[16..]);
+    let footer_flags = LittleEndian::read_u32(&footer[20..]);
+    if footer_flags & IS_HEADER != 0 {
+        return Ok(Vec::new());
+    }
+
+    // Candidates for the header position, `None` means there is no header
+    let declared_start = (footer_offset + APE_HEADER_SIZE).checked_sub(declared_size as u64);
+    let mut candidates = Vec::new();
+    if let Some(start) = declared_start.and_then(|start| start.checked_sub(APE_HEADER_SIZE)) {
+        candidates.push(Some(start));
+    }
+    let headers = scan_for_tags(file, Some(HEADER_WINDOW.min(footer_offset)))?
+        .into_iter()
+        .rev()
+        .filter(|location| location.position == MetaPosition::Header && location.offset < footer_offset);
+    candidates.extend(headers.map(|location| Some(location.offset)));
+
+    let mut found = None;
+    for header in candidates.into_iter().chain([None]) {
+        let start = match header {
+            Some(header) if is_header(file, header)? => header + APE_HEADER_SIZE,
+            Some(_) => continue,
+            None => match declared_start {
+                Some(start) => start,
+                None => break,
+            },
+        };
+        if let Some(count) = count_items(file, start, footer_offset)? {
+            found = Some((header, start, count));
+            break;
+        }
+    }
+    let (header, start, count) = found.ok_or(ErrorKind::BadTagSize)?;
+    let size = (footer_offset + APE_HEADER_SIZE - start) as u32;
+
+    let mut fixes = Vec::new();
+    if size != declared_size {
+        fixes.push(TagFix::Size);
+    }
+    if count != declared_count {
+        fixes.push(TagFix::ItemCount);
+    }
+    let mut flags = footer_flags & !(HAS_HEADER | HAS_NO_FOOTER | IS_HEADER);
+    match header {
+        Some(_) => flags |= HAS_HEADER,
+        None if footer_flags & HAS_HEADER != 0 => fixes.push(TagFix::MissingHeader),
+        None => {}
+    }
+    if let Some(header) = header {
+        let mut data = [0; APE_HEADER_SIZE as usize];
+        file.seek(SeekFrom::Start(header))?;
+        file.read_exact(&mut data)?;
+        if footer_flags & HAS_HEADER == 0 || LittleEndian::read_u32(&data[20..]) != flags | IS_HEADER {
+            fixes.push(TagFix::HeaderFlags);
+        }
+        if LittleEndian::read_u32(&data[12..]) != size && !fixes.contains(&TagFix::Size) {
+            fixes.push(TagFix::Size);
+        }
+        if LittleEndian::read_u32(&data[16..]) != count && !fixes.contains(&TagFix::ItemCount) {
+            fixes.push(TagFix::ItemCount);
+        }
+        write_meta(file, header, size, count, flags | IS_HEADER)?;
+    }
+    if footer_flags & HAS_NO_FOOTER != 0 || footer_flags & IS_HEADER != 0 {
+        fixes.push(TagFix::HeaderFlags);
+    }
+    if !fixes.is_empty() {
+        write_meta(file, footer_offset, size, count, flags)?;
+        file.flush()?;
+    }
+    Ok(fixes)
+}
+
+/// Whether there is a header at the given offset.
+fn is_header<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<bool> {
+    let mut data = [0; APE_HEADER_SIZE as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    if reader.read_exact(&mut data).is_err() {
+        return Ok(false);
+    }
+    Ok(&data[..8] == APE_PREAMBLE && LittleEndian::read_u32(&data[20..]) & IS_HEADER != 0)
+}
+
+/// Returns the number of valid items between the offsets followed by optional zero padding.
+fn count_items<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Option<u32>> {
+    // A size smaller than a footer places items after it
+    if start > end {
+        return Ok(None);
+    }
+    let mut data = Vec::new();
+    reader.seek(SeekFrom::Start(start))?;
+    reader.take(end - start).read_to_end(&mut data)?;
+    let mut data = &data[..];
+    let mut count = 0;
+    while !data.iter().all(|&byte| byte == 0) {
+        let value_len = match data.read_u32::<LittleEndian>() {
+            Ok(len) => len as usize,
+            Err(_) => return Ok(None),
+        };
+        if data.read_u32::<LittleEndian>().is_err() {
+            return Ok(None);
+        }
+        let key_len = match data.iter().position(|&byte| byte == 0) {
+            Some(len) if (2..=255).contains(&len) => len,
+            _ => return Ok(None),
+        };
+        if !data[..key_len].iter().all(|byte| (0x20..=0x7e).contains(byte)) || data.len() < key_len + 1 + value_len {
+            return Ok(None);
+        }
+        data = &data[key_len + 1 + value_len..];
+        count += 1;
+    }
+    Ok(Some(count))
+}
+
+fn write_meta<W: Write + Seek>(writer: &mut W, offset: u64, size: u32, count: u32, flags: u32) -> Result<()> {
+    // Preamble and version are kept
+    writer.seek(SeekFrom::Start(offset + 12))?;
+    let mut data = [0; 12];
+    LittleEndian::write_u32(&mut data[..4], size);
+    LittleEndian::write_u32(&mut data[4..8], count);
+    LittleEndian::write_u32(&mut data[8..], flags);
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{repair, TagFix};
+    use crate::{
+        error::ErrorKind,
+        item::Item,
+        options::WriteOptions,
+        tag::{read_from, Tag},
+    };
+    use std::io::Cursor;
+
+    fn tag_with_header() -> Cursor<Vec<u8>> {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("key1", "value").unwrap());
+        tag.add_item(Item::from_text("key2", "value").unwrap());
+        let mut data = Cursor::new(vec![1; 100]);
+        WriteOptions::new().write_to(&tag, &mut data).unwrap();
+        // Add a header
+        let mut bytes = data.into_inner();
+        let footer = bytes[bytes.len() - 32..].to_vec();
+        let mut header = footer.clone();
+        header[20..24].copy_from_slice(&0xa000_0000u32.to_le_bytes());
+        bytes.splice(100..100, header);
+        let len = bytes.len();
+        bytes[len - 12..len - 8].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        Cursor::new(bytes)
+    }
+
+    #[test]
+    fn consistent() {
+        let mut data = tag_with_header();
+        assert_eq!(read_from(&mut data).unwrap().iter().count(), 2);
+        assert!(repair(&mut data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn wrong_size_and_count() {
+        let mut data = tag_with_header();
+        let len = data.get_ref().len();
+        data.get_mut()[len - 20..len - 12].copy_from_slice(&[10, 0, 0, 0, 5, 0, 0, 0]);
+        assert!(read_from(&mut data).is_err());
+        assert_eq!(repair(&mut data).unwrap(), vec![TagFix::Size, TagFix::ItemCount]);
+        assert_eq!(read_from(&mut data).unwrap().iter().count(), 2);
+        assert!(repair(&mut data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn footer_size_out_of_range() {
+        for size in [0, u32::MAX] {
+            let mut data = tag_with_header();
+            let len = data.get_ref().len();
+            data.get_mut()[len - 20..len - 16].copy_from_slice(&size.to_le_bytes());
+            assert!(read_from(&mut data).is_err());
+            assert_eq!(repair(&mut data).unwrap(), vec![TagFix::Size]);
+            assert_eq!(read_from(&mut data).unwrap().iter().count(), 2);
+
+            // Items can not be located without a header
+            let mut tag = Tag::new();
+            tag.add_item(Item::from_text("key", "value").unwrap());
+            let mut data = Cursor::new(vec![1; 100]);
+            WriteOptions::new().write_to(&tag, &mut data).unwrap();
+            let len = data.get_ref().len();
+            data.get_mut()[len - 20..len - 16].copy_from_slice(&size.to_le_bytes());
+            assert_eq!(repair(&mut data).unwrap_err().kind(), ErrorKind::BadTagSize);
+        }
+    }
+
+    #[test]
+    fn missing_header() {
+        let mut data = tag_with_header();
+        // Overwrite the header with audio data
+        data.get_mut()[100..132].copy_from_slice(&[1; 32]);
+        assert_eq!(repair(&mut data).unwrap(), vec![TagFix::MissingHeader]);
+        assert_eq!(read_from(&mut data).unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn header_flags() {
+        let mut data = tag_with_header();
+        data.get_mut()[120..124].copy_from_slice(&0x2000_0000u32.to_le_bytes());
+        assert_eq!(repair(&mut data).unwrap(), vec![TagFix::HeaderFlags]);
+        assert_eq!(data.get_ref()[120..124], 0xa000_0000u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    error::Result,
+    meta::{MetaFlags, MetaPosition, APE_HEADER_SIZE, APE_VERSION, APE_VERSION_1},
+    util::APE_PREAMBLE,
+};
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Seek, SeekFrom};
+
+/// Size of a chunk read at once.
+const CHUNK_SIZE: usize = 65536;
+
+/// A header or footer of an APE tag found by [`scan_for_tags`](fn.scan_for_tags.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagLocation {
+    /// Offset of the `APETAGEX` preamble.
+    pub offset: u64,
+    /// Whether it is a header or a footer.
+    pub position: MetaPosition,
+    /// Tag version, 1000 or 2000.
+    pub version: u32,
+    /// Tag size including items and footer, excluding header.
+    pub size: u32,
+    /// Number of items.
+    pub item_count: u32,
+    /// Tag contains a header.
+    pub has_header: bool,
+    /// Tag contains a footer.
+    pub has_footer: bool,
+}
+
+/// Searches a reader for APE tag headers and footers regardless of where they are expected to be.
+///
+/// Only the last `window` bytes are searched if a window is given, otherwise the whole reader.
+/// Allows to rescue metadata of corrupted files, e.g. truncated downloads or bad concatenations.
+/// Locations are returned in the order of their offsets.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::scan_for_tags;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// for location in scan_for_tags(&mut file, Some(1024 * 1024)).unwrap() {
+///     println!("{:?} at {}", location.position, location.offset);
+/// }
+/// ```
+pub fn scan_for_tags<R: Read + Seek>(reader: &mut R, window: Option<u64>) -> Result<Vec<TagLocation>> {
+    let filesize = reader.seek(SeekFrom::End(0))?;
+    let start = window.map_or(0, |window| filesize.saturating_sub(window));
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut locations = Vec::new();
+    // Offset of the first byte in the buffer
+    let mut base = start;
+    let mut buff = Vec::<u8>::with_capacity(CHUNK_SIZE + APE_HEADER_SIZE as usize);
+    loop {
+        let len = buff.len();
+        reader.take(CHUNK_SIZE as u64).read_to_end(&mut buff)?;
+        let eof = buff.len() == len;
+        let mut consumed = 0;
+        while let Some(pos) = find(&buff[consumed..], APE_PREAMBLE) {
+            let pos = consumed + pos;
+            if buff.len() - pos < APE_HEADER_SIZE as usize && !eof {
+                // The rest of the header is in the next chunk
+                consumed = pos;
+                break;
+            }
+            if let Some(location) = parse(&buff[pos..], base + pos as u64) {
+                locations.push(location);
+            }
+            consumed = pos + 1;
+        }
+        if eof {
+            break;
+        }
+        // Keep the bytes which may contain the beginning of a preamble or an incomplete header
+        let keep = if consumed > 0 && buff.len() - consumed < APE_HEADER_SIZE as usize {
+            consumed
+        } else {
+            buff.len().saturating_sub(APE_PREAMBLE.len() - 1)
+        };
+        buff.drain(..keep);
+        base += keep as u64;
+    }
+    Ok(locations)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Parses a header or footer, returns `None` if it is not valid.
+fn parse(data: &[u8], offset: u64) -> Option<TagLocation> {
+    let data = data.get(..APE_HEADER_SIZE as usize)?;
+    let version = LittleEndian::read_u32(&data[8..]);
+    if version != APE_VERSION && version != APE_VERSION_1 {
+        return None;
+    }
+    let flags = MetaFlags::from_raw(LittleEndian::read_u32(&data[20..]));
+    Some(TagLocation {
+        offset,
+        position: flags.position,
+        version,
+        size: LittleEndian::read_u32(&data[12..]),
+        item_count: LittleEndian::read_u32(&data[16..]),
+        has_header: flags.has_header,
+        has_footer: flags.has_footer,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{scan_for_tags, CHUNK_SIZE};
+    use crate::{item::Item, meta::MetaPosition, options::WriteOptions, tag::Tag};
+    use std::io::Cursor;
+
+    #[test]
+    fn scan() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let mut bytes = Cursor::new(Vec::new());
+        WriteOptions::new().write_to(&tag, &mut bytes).unwrap();
+        let bytes = bytes.into_inner();
+
+        // The footer crosses the boundary of chunks
+        let mut data = vec![1; CHUNK_SIZE - 20];
+        data.extend(&bytes);
+        data.extend(vec![2; 1000]);
+        data.extend(b"APETAGEX garbage");
+        data.extend(&bytes);
+        let mut data = Cursor::new(data);
+
+        let locations = scan_for_tags(&mut data, None).unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].offset, (CHUNK_SIZE - 20 + 17) as u64);
+        assert_eq!(locations[0].position, MetaPosition::Footer);
+        assert_eq!(locations[0].version, 2000);
+        assert_eq!(locations[0].size, 49);
+        assert_eq!(locations[0].item_count, 1);
+        assert_eq!(locations[1].offset, data.get_ref().len() as u64 - 32);
+
+        let locations = scan_for_tags(&mut data, Some(100)).unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].offset, data.get_ref().len() as u64 - 32);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{item::ItemValue, key::ItemKey, tag::Tag};
+
+/// Keys of ReplayGain values in decibels.
+const GAIN_KEYS: [&str; 2] = ["REPLAYGAIN_TRACK_GAIN", "REPLAYGAIN_ALBUM_GAIN"];
+
+/// A problem of a field value found by [`Tag::validate_semantics`](struct.Tag.html#method.validate_semantics).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FindingKind {
+    /// A checked field has a Binary or Locator value.
+    NotText,
+    /// `Year` is neither a year nor a date such as `2001-05-30`.
+    InvalidYear,
+    /// `Track` or `Disc` does not match `n` or `n/total`.
+    InvalidNumber,
+    /// A ReplayGain value is not a number of decibels such as `-6.54 dB`.
+    InvalidGain,
+    /// `ISRC` does not match `CC-XXX-YY-NNNNN`, hyphens are optional.
+    InvalidIsrc,
+    /// `EAN/UPC` is not a barcode of 8, 12 or 13 digits with a valid check digit.
+    InvalidBarcode,
+}
+
+/// A field which value does not match its format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Item key as it is written in the tag.
+    pub key: String,
+    /// The invalid value, one of several ones separated by zero bytes.
+    pub value: String,
+    /// What is wrong with the value.
+    pub kind: FindingKind,
+}
+
+impl Tag {
+    /// Checks values of well-known fields, e.g. for library QA tooling.
+    ///
+    /// Unlike reading, which checks the structure of a tag only, it validates that
+    /// `Year` is a plausible year or date, `Track` and `Disc` match `n` or `n/total`,
+    /// ReplayGain values are numbers of decibels and `ISRC` and `EAN/UPC` match their formats.
+    /// Other fields are not checked. Returns a finding for every invalid value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::{FindingKind, Item, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_item(Item::from_text("Track", "3/2").unwrap());
+    /// let findings = tag.validate_semantics();
+    /// assert_eq!(findings[0].kind, FindingKind::InvalidNumber);
+    /// ```
+    pub fn validate_semantics(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for item in self.iter() {
+            let (check, kind): (fn(&str) -> bool, _) = match ItemKey::from(&*item.key) {
+                ItemKey::Year => (is_year, FindingKind::InvalidYear),
+                ItemKey::Track | ItemKey::Disc => (is_number, FindingKind::InvalidNumber),
+                ItemKey::Isrc => (is_isrc, FindingKind::InvalidIsrc),
+                ItemKey::EanUpc => (is_barcode, FindingKind::InvalidBarcode),
+                _ if GAIN_KEYS.iter().any(|key| key.eq_ignore_ascii_case(&item.key)) => {
+                    (is_gain, FindingKind::InvalidGain)
+                }
+                _ => continue,
+            };
+            let text = match item.value {
+                ItemValue::Text(ref text) => text,
+                _ => {
+                    findings.push(Finding {
+                        key: item.key.to_string(),
+                        value: String::new(),
+                        kind: FindingKind::NotText,
+                    });
+                    continue;
+                }
+            };
+            // Multiple values are separated by zero bytes
+            for value in text.split('\0').filter(|value| !check(value)) {
+                findings.push(Finding {
+                    key: item.key.to_string(),
+                    value: value.to_string(),
+                    kind,
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Parses a number of the given length consisting of ASCII digits only.
+fn digits(value: &str, len: usize) -> Option<u32> {
+    if value.len() == len && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        value.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Matches `YYYY`, `YYYY-MM` and `YYYY-MM-DD`.
+fn is_year(value: &str) -> bool {
+    let mut parts = value.trim().split('-');
+    let year = match parts.next().and_then(|year| digits(year, 4)) {
+        Some(year @ 1000..=2999) => year,
+        _ => return false,
+    };
+    let month = match parts.next() {
+        None => return true,
+        Some(month) => match digits(month, 2) {
+            Some(month @ 1..=12) => month,
+            _ => return false,
+        },
+    };
+    let days = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    match parts.next() {
+        None => true,
+        Some(day) => matches!(digits(day, 2), Some(day) if (1..=days).contains(&day)) && parts.next().is_none(),
+    }
+}
+
+/// Matches `n` or `n/total` where `n` is not greater than `total`.
+fn is_number(value: &str) -> bool {
+    let number = |value: &str| {
+        let value = value.trim();
+        digits(value, value.len()).filter(|&number| number > 0)
+    };
+    match value.split_once('/') {
+        None => number(value).is_some(),
+        Some((value, total)) => matches!((number(value), number(total)), (Some(value), Some(total)) if value <= total),
+    }
+}
+
+/// Matches a number of decibels with an optional ` dB` suffix.
+fn is_gain(value: &str) -> bool {
+    let value = value.trim();
+    let value = match value.len().checked_sub(2) {
+        Some(end) if value.is_char_boundary(end) && value[end..].eq_ignore_ascii_case("db") => &value[..end],
+        _ => value,
+    };
+    matches!(value.trim_end().parse::<f64>(), Ok(gain) if gain.is_finite())
+}
+
+/// Matches `CC-XXX-YY-NNNNN` with optional hyphens.
+fn is_isrc(value: &str) -> bool {
+    let value = value.trim().replace('-', "");
+    let bytes = value.as_bytes();
+    bytes.len() == 12
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..5]
+            .iter()
+            .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+        && bytes[5..].iter().all(u8::is_ascii_digit)
+}
+
+/// Matches EAN-8, UPC-A and EAN-13 barcodes with a valid check digit.
+fn is_barcode(value: &str) -> bool {
+    let value = value.trim();
+    if !matches!(value.len(), 8 | 12 | 13) || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return false;
+    }
+    // Digits are weighted 3 and 1 alternately starting from the one preceding the check digit
+    let sum = value
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(idx, byte)| u32::from(byte - b'0') * if idx % 2 == 1 { 3 } else { 1 })
+        .sum::<u32>();
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_barcode, is_gain, is_isrc, is_number, is_year, Finding, FindingKind};
+    use crate::{item::Item, tag::Tag};
+
+    #[test]
+    fn formats() {
+        assert!(["2001", "2001-05", "2000-02-29", " 1999 "].iter().all(|x| is_year(x)));
+        assert!(["01", "2001-13", "1900-02-29", "2001-05-30-1", "99999"]
+            .iter()
+            .all(|x| !is_year(x)));
+        assert!(["1", "03", "3/12", "12/12"].iter().all(|x| is_number(x)));
+        assert!(["0", "3/2", "a", "1/", "-1"].iter().all(|x| !is_number(x)));
+        assert!(["-6.54 dB", "+1.2 db", "0", "-3.1dB"].iter().all(|x| is_gain(x)));
+        assert!(["dB", "loud", "inf dB"].iter().all(|x| !is_gain(x)));
+        assert!(["USRC17607839", "US-RC1-76-07839"].iter().all(|x| is_isrc(x)));
+        assert!(["usrc17607839", "USRC1760783", "U1RC17607839"]
+            .iter()
+            .all(|x| !is_isrc(x)));
+        assert!(["4006381333931", "036000291452", "96385074"]
+            .iter()
+            .all(|x| is_barcode(x)));
+        assert!(["4006381333932", "123", "40063813339a1"].iter().all(|x| !is_barcode(x)));
+    }
+
+    #[test]
+    fn validate_semantics() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Year", "2001-05-30").unwrap());
+        tag.add_item(Item::from_text("track", "1/10\u{0}11/10").unwrap());
+        tag.add_item(Item::from_text("Title", "0/0").unwrap());
+        tag.add_item(Item::from_text("replaygain_track_gain", "-6.54 dB").unwrap());
+        tag.add_item(Item::from_binary("ISRC", vec![0; 12]).unwrap());
+        assert_eq!(
+            tag.validate_semantics(),
+            vec![
+                Finding {
+                    key: String::from("track"),
+                    value: String::from("11/10"),
+                    kind: FindingKind::InvalidNumber,
+                },
+                Finding {
+                    key: String::from("ISRC"),
+                    value: String::new(),
+                    kind: FindingKind::NotText,
+                },
+            ]
+        );
+    }
+}
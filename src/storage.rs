@@ -0,0 +1,254 @@
+use std::{
+    fs::File,
+    io::{Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
+};
+
+/// A storage which length can be changed, required to write or remove a tag.
+///
+/// Implemented for `File` and `Cursor<Vec<u8>>`, so tags can be edited in memory.
+pub trait Truncate {
+    /// Truncates or extends the storage to the given length, see `File::set_len`.
+    fn truncate(&mut self, len: u64) -> Result<()>;
+
+    /// Flushes written data to a durable storage.
+    ///
+    /// It is used by journaled modifications and [`WriteOptions::sync`](struct.WriteOptions.html#method.sync),
+    /// does nothing by default.
+    fn sync_data(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Truncate for File {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.set_len(len)
+    }
+
+    fn sync_data(&mut self) -> Result<()> {
+        File::sync_data(self)
+    }
+}
+
+impl Truncate for Cursor<Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.get_mut().resize(len as usize, 0);
+        Ok(())
+    }
+}
+
+/// A storage providing positioned access to a file-like object.
+///
+/// Allows to read and edit tags on objects which are not a `File`, e.g. served by a virtual file system.
+/// Wrap it into [`StorageAdapter`](struct.StorageAdapter.html) to read, write or remove a tag.
+///
+/// Methods are prefixed, so they do not clash with methods of the same names,
+/// e.g. `std::os::unix::fs::FileExt::read_at` or [`Truncate::truncate`](trait.Truncate.html#tymethod.truncate).
+pub trait ApeStorage {
+    /// Reads bytes starting at the given offset, returns a number of bytes read.
+    fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize>;
+
+    /// Writes bytes starting at the given offset, returns a number of bytes written.
+    fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> Result<usize>;
+
+    /// Truncates or extends the storage to the given length.
+    fn ape_truncate(&mut self, len: u64) -> Result<()>;
+
+    /// Returns the length of the storage.
+    fn ape_len(&mut self) -> Result<u64>;
+
+    /// Whether the storage is empty.
+    fn ape_is_empty(&mut self) -> Result<bool> {
+        Ok(self.ape_len()? == 0)
+    }
+}
+
+impl ApeStorage for File {
+    fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read(buf)
+    }
+
+    fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> Result<usize> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.write(buf)
+    }
+
+    fn ape_truncate(&mut self, len: u64) -> Result<()> {
+        self.set_len(len)
+    }
+
+    fn ape_len(&mut self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl ApeStorage for Vec<u8> {
+    fn ape_read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        let data = self.get(offset as usize..).unwrap_or_default();
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn ape_write_at(&mut self, buf: &[u8], offset: u64) -> Result<usize> {
+        let (start, end) = (offset as usize, offset as usize + buf.len());
+        if self.len() < end {
+            self.resize(end, 0);
+        }
+        self[start..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn ape_truncate(&mut self, len: u64) -> Result<()> {
+        self.resize(len as usize, 0);
+        Ok(())
+    }
+
+    fn ape_len(&mut self) -> Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+/// Provides `Read`, `Write`, `Seek` and [`Truncate`](trait.Truncate.html) over an [`ApeStorage`](trait.ApeStorage.html).
+///
+/// # Examples
+///
+/// ```
+/// use ape::{read_from, write_to, Item, StorageAdapter, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// let mut storage = StorageAdapter::new(vec![0; 200]);
+/// write_to(&tag, &mut storage).unwrap();
+/// assert!(read_from(&mut storage).unwrap().item("artist").is_some());
+/// ```
+#[derive(Debug)]
+pub struct StorageAdapter<S> {
+    storage: S,
+    position: u64,
+}
+
+impl<S: ApeStorage> StorageAdapter<S> {
+    /// Wraps a storage, the position is set to the start.
+    pub fn new(storage: S) -> StorageAdapter<S> {
+        StorageAdapter { storage, position: 0 }
+    }
+
+    /// Returns the wrapped storage.
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+impl<S: ApeStorage> Read for StorageAdapter<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.storage.ape_read_at(buf, self.position)?;
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<S: ApeStorage> Write for StorageAdapter<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = self.storage.ape_write_at(buf, self.position)?;
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: ApeStorage> Seek for StorageAdapter<S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (self.storage.ape_len()?, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl<S: ApeStorage> Truncate for StorageAdapter<S> {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.storage.ape_truncate(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ApeStorage, StorageAdapter, Truncate};
+    use crate::{
+        item::Item,
+        tag::{read_from, remove_from, write_to, Tag},
+    };
+    use std::{
+        fs::{remove_file, OpenOptions},
+        io::{Cursor, Read, Seek, SeekFrom},
+    };
+
+    #[test]
+    fn truncate_cursor() {
+        let mut cursor = Cursor::new(vec![1; 10]);
+        cursor.truncate(4).unwrap();
+        assert_eq!(cursor.get_ref(), &vec![1; 4]);
+        cursor.truncate(6).unwrap();
+        assert_eq!(cursor.get_ref(), &vec![1, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn adapter() {
+        let mut storage = StorageAdapter::new(vec![1; 200]);
+        let mut buf = [0; 10];
+        assert_eq!(storage.seek(SeekFrom::End(-4)).unwrap(), 196);
+        assert_eq!(storage.read(&mut buf).unwrap(), 4);
+        assert!(storage.seek(SeekFrom::Current(-300)).is_err());
+
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut storage).unwrap();
+        assert_eq!(1, read_from(&mut storage).unwrap().items("key").len());
+        remove_from(&mut storage).unwrap();
+        assert_eq!(storage.into_inner(), vec![1; 200]);
+    }
+
+    #[test]
+    fn file_methods() {
+        let path = "data/storage.apev2";
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap();
+        // Methods of all traits implemented for File can be called unambiguously
+        assert_eq!(file.ape_write_at(&[1; 10], 0).unwrap(), 10);
+        file.truncate(4).unwrap();
+        assert_eq!(file.ape_len().unwrap(), 4);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileExt;
+            let mut buf = [0; 4];
+            assert_eq!(file.read_at(&mut buf, 0).unwrap(), 4);
+            assert_eq!(buf, [1; 4]);
+        }
+        file.ape_truncate(0).unwrap();
+        assert!(file.ape_is_empty().unwrap());
+        remove_file(path).unwrap();
+    }
+}
@@ -1,19 +1,35 @@
 use crate::{
-    error::{Error, Result},
-    item::{Item, KIND_BINARY, KIND_LOCATOR, KIND_TEXT},
-    meta::{Meta, MetaPosition, APE_VERSION},
-    util::{probe_id3v1, probe_lyrics3v2, APE_PREAMBLE},
+    container::{
+        detect_container,
+        mpeg::{check_frames, FrameCheck},
+        ContainerKind,
+    },
+    error::{Error, ErrorKind, Result},
+    item::{join_values, write_item, Item, ItemType, ItemValue, KIND_BINARY, KIND_LOCATOR, KIND_RESERVED, KIND_TEXT},
+    journal::Journal,
+    lazy::ValueHandle,
+    meta::{Layout, Meta, MetaPosition, APE_HEADER_SIZE, APE_VERSION, APE_VERSION_1, IS_HEADER},
+    metrics,
+    options::{ApeVersion, CopyOptions, DuplicateKeys, ItemOrder, ReadOptions, WriteOptions},
+    storage::Truncate,
+    trace::{debug, span, trace},
+    util::{decode_latin1, encode_latin1, probe_trailers, APE_PREAMBLE},
 };
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::{
-    fs::{File, OpenOptions},
-    io::{Read, Seek, SeekFrom, Write},
+    collections::BTreeMap,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    ops::{Index, Range},
     path::Path,
     slice::Iter as SliceIter,
     str,
+    sync::Arc,
     vec::IntoIter as VecIntoIter,
 };
 
+/// Size of a chunk of bytes moved at once when a modification is journaled.
+const JOURNAL_BUFFER_SIZE: u64 = 65536;
+
 /// An APE Tag containing APE Tag Items.
 ///
 /// # Examples
@@ -42,7 +58,13 @@ use std::{
 /// write_to_path(&tag, path).unwrap();
 /// ```
 #[derive(Debug, Clone, Default)]
-pub struct Tag(Vec<Item>);
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Tag(
+    pub(crate) Vec<Item>,
+    /// Whether Binary items have been spilled while reading, see [`Tag::is_partial`].
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) bool,
+);
 
 impl Tag {
     /// Creates a new empty tag.
@@ -50,30 +72,209 @@ impl Tag {
         Self::default()
     }
 
+    /// Whether Binary items have been left out while reading,
+    /// see [`ReadOptions::spill_binary_values`](struct.ReadOptions.html#method.spill_binary_values).
+    ///
+    /// Such a tag can not be written, since writing it would remove the items from a file.
+    /// Collect its items into a new tag to write it without them deliberately.
+    pub fn is_partial(&self) -> bool {
+        self.1
+    }
+
     /// Returns a first found item by key.
-    pub fn item(&self, key: &str) -> Option<&Item> {
+    pub fn item<K: AsRef<str>>(&self, key: K) -> Option<&Item> {
+        let key = key.as_ref();
         self.0.iter().find(|item| item.key.eq_ignore_ascii_case(key))
     }
 
     /// Returns all items by key.
-    pub fn items(&self, key: &str) -> Vec<&Item> {
+    pub fn items<K: AsRef<str>>(&self, key: K) -> Vec<&Item> {
+        let key = key.as_ref();
         self.0
             .iter()
             .filter(move |item| item.key.eq_ignore_ascii_case(key))
             .collect()
     }
 
+    /// Returns all items with keys matching a glob pattern, e.g. `MUSICBRAINZ_*`.
+    ///
+    /// `*` matches any sequence of characters, `?` matches a single character,
+    /// other characters are compared case-insensitively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_text("MUSICBRAINZ_TRACKID", "id").unwrap();
+    /// tag.set_text("MusicBrainz_AlbumId", "id").unwrap();
+    /// tag.set_text("Title", "Title").unwrap();
+    /// assert_eq!(tag.items_matching("musicbrainz_*").len(), 2);
+    /// ```
+    pub fn items_matching<P: AsRef<str>>(&self, pattern: P) -> Vec<&Item> {
+        let pattern = pattern.as_ref().chars().collect::<Vec<_>>();
+        self.0
+            .iter()
+            .filter(|item| glob_matches(&pattern, &item.key.chars().collect::<Vec<_>>()))
+            .collect()
+    }
+
+    /// Returns an iterator over distinct keys in the order of items.
+    ///
+    /// Keys are compared case-insensitively, each one is returned as written in its first item.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(move |(idx, item)| !self.0[..*idx].iter().any(|x| x.key.eq_ignore_ascii_case(&item.key)))
+            .map(|(_, item)| &*item.key)
+    }
+
+    /// Returns items grouped by key.
+    ///
+    /// Keys are compared case-insensitively, each group is keyed as written in its first item
+    /// and keeps the order of items.
+    pub fn as_map(&self) -> BTreeMap<&str, Vec<&Item>> {
+        self.keys().map(|key| (key, self.items(key))).collect()
+    }
+
     /// Sets a new item.
     ///
     /// If there is an item with the same key, it will be removed.
     pub fn set_item(&mut self, item: Item) {
-        self.remove_items(item.key.as_ref());
+        self.remove_items(&item.key);
         self.add_item(item)
     }
 
+    /// Sets a new item with Text value, see [`set_item`](#method.set_item).
+    ///
+    /// # Errors
+    ///
+    /// Keys are validated the same way as by [`Item`](struct.Item.html) constructors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_text("artist", "Artist Name").unwrap();
+    /// assert!(tag.set_text("TAG", "value").is_err());
+    /// ```
+    pub fn set_text<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Result<()> {
+        self.set_item(Item::from_text(key, value)?);
+        Ok(())
+    }
+
+    /// Sets a new item with Locator value, see [`set_text`](#method.set_text).
+    pub fn set_locator<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Result<()> {
+        self.set_item(Item::from_locator(key, value)?);
+        Ok(())
+    }
+
+    /// Sets a new item with Binary value, see [`set_text`](#method.set_text).
+    pub fn set_binary<K: Into<String>, V: Into<Arc<[u8]>>>(&mut self, key: K, value: V) -> Result<()> {
+        self.set_item(Item::from_binary(key, value)?);
+        Ok(())
+    }
+
+    /// Sets a new item with multiple Text values joined with a zero byte, see [`set_text`](#method.set_text).
+    ///
+    /// Empty values are skipped, existing items are removed when all values are empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::{ItemValue, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_multi("genre", ["Rock", "", "Blues"]).unwrap();
+    /// assert_eq!(tag["genre"].value, ItemValue::Text("Rock\0Blues".into()));
+    /// ```
+    pub fn set_multi<K, I, V>(&mut self, key: K, values: I) -> Result<()>
+    where
+        K: Into<String>,
+        I: IntoIterator<Item = V>,
+        V: AsRef<str>,
+    {
+        self.set_values(key, values, false)
+    }
+
+    /// Sets a new item with multiple Text values like [`set_multi`](#method.set_multi),
+    /// repeated values are skipped.
+    pub fn set_multi_unique<K, I, V>(&mut self, key: K, values: I) -> Result<()>
+    where
+        K: Into<String>,
+        I: IntoIterator<Item = V>,
+        V: AsRef<str>,
+    {
+        self.set_values(key, values, true)
+    }
+
+    fn set_values<K, I, V>(&mut self, key: K, values: I, unique: bool) -> Result<()>
+    where
+        K: Into<String>,
+        I: IntoIterator<Item = V>,
+        V: AsRef<str>,
+    {
+        let item = Item::from_text(key, join_values(values, unique))?;
+        if item.value == ItemValue::Text(String::new()) {
+            self.remove_items(&item.key);
+        } else {
+            self.set_item(item);
+        }
+        Ok(())
+    }
+
+    /// Sets a new item handling existing items with the same key according to the policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::{Item, ItemValue, SetPolicy, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// for artist in ["First", "Second"] {
+    ///     tag.set_item_with(Item::from_text("Artist", artist).unwrap(), SetPolicy::MergeValues);
+    /// }
+    /// assert_eq!(tag.item("artist").unwrap().value, ItemValue::Text("First\0Second".into()));
+    /// ```
+    pub fn set_item_with(&mut self, item: Item, policy: SetPolicy) {
+        match policy {
+            SetPolicy::Replace => self.set_item(item),
+            SetPolicy::Append => self.add_item(item),
+            SetPolicy::MergeValues => {
+                let first = self
+                    .0
+                    .iter_mut()
+                    .filter(|x| !x.read_only && x.key.eq_ignore_ascii_case(&item.key))
+                    .find_map(|x| match (&mut x.value, &item.value) {
+                        (ItemValue::Text(first), ItemValue::Text(_))
+                        | (ItemValue::Locator(first), ItemValue::Locator(_)) => Some(first),
+                        _ => None,
+                    });
+                match (first, item.value) {
+                    (Some(first), ItemValue::Text(value) | ItemValue::Locator(value)) => {
+                        if !value.is_empty() {
+                            first.push('\0');
+                            first.push_str(&value);
+                        }
+                    }
+                    (_, value) => self.add_item(Item {
+                        key: item.key,
+                        value,
+                        read_only: item.read_only,
+                    }),
+                }
+            }
+        }
+    }
+
     /// Adds a new item.
     ///
-    /// Unlike `set_item`, existing items with the same key are not removed.
+    /// Unlike `set_item`, existing items with the same key are not removed,
+    /// such items are written in the order they were added.
     pub fn add_item(&mut self, item: Item) {
         self.0.push(item)
     }
@@ -81,28 +282,102 @@ impl Tag {
     /// Removes all items by key.
     ///
     /// Returns a number of deleted items
-    pub fn remove_items(&mut self, key: &str) -> usize {
-        let mut count = 0;
-        self.0 = self
-            .0
-            .iter()
-            .cloned()
-            .filter_map(|item| {
-                if item.key.eq_ignore_ascii_case(key) {
-                    count += 1;
-                    None
-                } else {
-                    Some(item)
-                }
-            })
-            .collect();
-        count
+    pub fn remove_items<K: AsRef<str>>(&mut self, key: K) -> usize {
+        let key = key.as_ref();
+        let count = self.0.len();
+        self.0.retain(|item| !item.key.eq_ignore_ascii_case(key));
+        count - self.0.len()
     }
 
     /// Returns an iterator over the tag
-    pub fn iter(&self) -> SliceIter<Item> {
+    pub fn iter(&self) -> SliceIter<'_, Item> {
         self.0.iter()
     }
+
+    /// Parses a tag from a buffer containing either a whole file or the tag only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ape::{Item, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+    /// let bytes = tag.to_bytes().unwrap();
+    /// assert_eq!(Tag::parse(&bytes).unwrap().item("artist"), tag.item("artist"));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Tag> {
+        read_from(&mut io::Cursor::new(bytes))
+    }
+
+    /// Parses a standalone tag, the inverse of [`to_bytes`](#method.to_bytes).
+    ///
+    /// Unlike [`parse`](#method.parse), the buffer must contain exactly one tag:
+    /// a header and/or a footer with items and nothing else, e.g. a tag region stored in a database.
+    ///
+    /// # Errors
+    ///
+    /// It is considered a error when bytes precede or follow the tag,
+    /// other errors are described in [`read_from_path`](fn.read_from_path.html).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tag> {
+        let mut reader = io::Cursor::new(bytes);
+        let meta = Meta::read(&mut reader)?;
+        if meta.region()? != (0, bytes.len() as u64) {
+            return Err(ErrorKind::BadTagSize.into());
+        }
+        let (items, _) = read_items(&mut reader, &meta, &ReadOptions::new(), None)?;
+        metrics::record(|m| m.tag_read());
+        Ok(Tag(items, false))
+    }
+
+    /// Serializes the tag to bytes, the same ones [`write_to`](fn.write_to.html) appends to a file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serialize(self, &WriteOptions::new(), 0, Layout::default())
+    }
+
+    /// Returns the number of bytes [`to_bytes`](#method.to_bytes) produces, items and footer, without serializing.
+    ///
+    /// A tag written at the start of a file also has a header of 32 bytes
+    /// and [`WriteOptions::padding`](struct.WriteOptions.html#method.padding) adds padding.
+    pub fn serialized_size(&self) -> u64 {
+        self.0.iter().map(Item::size).sum::<u64>() + APE_HEADER_SIZE
+    }
+
+    /// Whether both tags contain the same items regardless of their order.
+    pub(crate) fn has_same_items(&self, other: &Tag) -> bool {
+        if self.0.len() != other.0.len() {
+            return false;
+        }
+        let mut matched = vec![false; other.0.len()];
+        self.0.iter().all(|item| {
+            match other
+                .0
+                .iter()
+                .enumerate()
+                .position(|(idx, other)| !matched[idx] && other == item)
+            {
+                Some(idx) => {
+                    matched[idx] = true;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+/// Handling of existing items with the same key, see [`Tag::set_item_with`](struct.Tag.html#method.set_item_with).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SetPolicy {
+    /// Existing items are removed, the same as [`Tag::set_item`](struct.Tag.html#method.set_item) does.
+    #[default]
+    Replace,
+    /// Existing items are kept, the same as [`Tag::add_item`](struct.Tag.html#method.add_item) does.
+    Append,
+    /// A Text or Locator value is appended to the first existing item with the same key and kind which is not read only,
+    /// separated by a zero byte, which is how the specification stores lists; an empty value is ignored then.
+    /// Otherwise the item is appended.
+    MergeValues,
 }
 
 impl IntoIterator for Tag {
@@ -114,242 +389,1483 @@ impl IntoIterator for Tag {
     }
 }
 
-/// Attempts to write the APE tag to the file at the specified path.
-pub fn write_to_path<P: AsRef<Path>>(tag: &Tag, path: P) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
-    write_to(tag, &mut file)?;
-
-    Ok(())
+impl FromIterator<Item> for Tag {
+    fn from_iter<I: IntoIterator<Item = Item>>(items: I) -> Tag {
+        Tag(items.into_iter().collect(), false)
+    }
 }
 
-/// Attempts to write the APE tag to a File.
-pub fn write_to(tag: &Tag, file: &mut File) -> Result<()> {
-    // Convert items to bytes
-    // Do it as early as possible because if there is any error,
-    // we return it without modifying the file
-    let mut items = tag.iter().map(|item| item.to_vec()).collect::<Result<Vec<_>>>()?;
-
-    // APE tag items should be sorted ascending by size
-    items.sort_by_key(|a| a.len());
-
-    remove_from(file)?;
-
-    // Keep ID3v1 and LYRICS3v2 (if any)
-    let mut id3 = Vec::<u8>::new();
-    let filesize = file.seek(SeekFrom::End(0))?;
+impl Index<&str> for Tag {
+    type Output = Item;
 
-    if probe_id3v1(file)? {
-        let mut end_size: i64 = 128;
-        let lyrcis3v2_size = probe_lyrics3v2(file)?;
+    /// Returns a first found item by key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no item with the key, see [`item`](#method.item).
+    fn index(&self, key: &str) -> &Item {
+        self.item(key).expect("no item with the key")
+    }
+}
 
-        if lyrcis3v2_size != -1 {
-            end_size += lyrcis3v2_size;
+/// Matches a key against a glob pattern, backtracks to the last `*` on a mismatch.
+fn glob_matches(pattern: &[char], key: &[char]) -> bool {
+    let (mut pattern_idx, mut key_idx) = (0, 0);
+    let mut star = None;
+    while key_idx < key.len() {
+        match pattern.get(pattern_idx) {
+            Some('*') => {
+                star = Some((pattern_idx, key_idx));
+                pattern_idx += 1;
+            }
+            Some(&ch) if ch == '?' || ch.eq_ignore_ascii_case(&key[key_idx]) => {
+                pattern_idx += 1;
+                key_idx += 1;
+            }
+            _ => match star {
+                Some((star_idx, star_key_idx)) => {
+                    // Let the star match one more character
+                    pattern_idx = star_idx + 1;
+                    key_idx = star_key_idx + 1;
+                    star = Some((star_idx, key_idx));
+                }
+                None => return false,
+            },
         }
-
-        file.seek(SeekFrom::End(-end_size))?;
-        file.take(end_size as u64).read_to_end(&mut id3)?;
-        file.seek(SeekFrom::End(-end_size))?;
-        file.set_len(filesize - end_size as u64)?;
     }
+    pattern[pattern_idx..].iter().all(|&ch| ch == '*')
+}
 
-    file.seek(SeekFrom::End(0))?;
-
-    let mut size = 32; // Tag size including footer
+/// Copies the APE tag of a file to another file replacing its tag.
+///
+/// Use [`CopyOptions`](struct.CopyOptions.html) to filter copied items.
+///
+/// # Errors
+///
+/// See [`CopyOptions::copy_tag`](struct.CopyOptions.html#method.copy_tag)
+pub fn copy_tag<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    CopyOptions::new().copy_tag(src, dst)
+}
 
-    // Write items
-    for item in items {
-        size += item.len();
-        file.write_all(&item)?;
-    }
+/// Attempts to write the APE tag to the file at the specified path.
+pub fn write_to_path<P: AsRef<Path>>(tag: &Tag, path: P) -> Result<()> {
+    WriteOptions::new().write_to_path(tag, path)
+}
 
-    // Write footer
-    file.write_all(APE_PREAMBLE)?;
-    file.write_u32::<LittleEndian>(APE_VERSION)?;
-    // Tag size including footer
-    file.write_u32::<LittleEndian>(size as u32)?;
-    // Item count
-    file.write_u32::<LittleEndian>(tag.0.len() as u32)?;
-    // Tag flags
-    file.write_u32::<LittleEndian>(0)?;
+/// Attempts to write the APE tag to a File or any other truncatable storage, e.g. `Cursor<Vec<u8>>`.
+pub fn write_to<F: Read + Write + Seek + Truncate>(tag: &Tag, file: &mut F) -> Result<()> {
+    WriteOptions::new().write_to(tag, file)
+}
 
-    // Reserved
-    for _ in 0..8 {
-        file.write_u8(0)?;
-    }
+/// Attempts to write the APE tag to the file at the specified path
+/// unless the file already contains the same items.
+///
+/// Items are compared regardless of their order.
+/// Returns whether the file has been written.
+pub fn write_to_path_if_changed<P: AsRef<Path>>(tag: &Tag, path: P) -> Result<bool> {
+    WriteOptions::new().write_to_path_if_changed(tag, path)
+}
 
-    // Write ID3v1 and LYRICS3v2 (if any)
-    file.write_all(&id3)?;
+/// Attempts to write the APE tag to a File unless it already contains the same items.
+///
+/// See [`write_to_path_if_changed`](fn.write_to_path_if_changed.html)
+pub fn write_to_if_changed<F: Read + Write + Seek + Truncate>(tag: &Tag, file: &mut F) -> Result<bool> {
+    WriteOptions::new().write_to_if_changed(tag, file)
+}
 
-    Ok(())
+/// Replaces the APE tag of a file loaded into memory.
+///
+/// Works without file system access, e.g. on `wasm32-unknown-unknown`.
+///
+/// # Examples
+///
+/// ```
+/// use ape::{replace_tag_in_buffer, Item, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// let buffer = replace_tag_in_buffer(vec![0; 100], &tag).unwrap();
+/// assert_eq!(Tag::parse(&buffer).unwrap().item("artist"), tag.item("artist"));
+/// ```
+pub fn replace_tag_in_buffer(buffer: Vec<u8>, tag: &Tag) -> Result<Vec<u8>> {
+    let mut cursor = io::Cursor::new(buffer);
+    write_to(tag, &mut cursor)?;
+    Ok(cursor.into_inner())
 }
 
-/// Attempts to read an APE tag from the file at the specified path.
+/// Writes the APE tag into a region of exactly `max_len` bytes at the given offset.
 ///
-/// # Errors
+/// The rest of the region is filled with padding, so the region always contains a valid tag.
+/// Nothing else is changed, it is useful when a tag is embedded into a container managed by a caller.
 ///
-/// It is considered a error when:
+/// # Errors
 ///
-/// - APE tag does not exists.
-/// - Tag version is not 2.000.
-/// - Item key is not valid.
-/// - Kind of an item is unknown.
-/// - Tag size declared in the APE header does not match with actual size.
+/// It is considered a error when the tag does not fit into the region.
 ///
 /// # Examples
 ///
-/// ```no_run
-/// use ape::read_from_path;
+/// ```
+/// use ape::{read_from_at, write_at, Item, Tag};
+/// use std::io::Cursor;
 ///
-/// let tag = read_from_path("path/to/file").unwrap();
-/// let item = tag.item("artist").unwrap();
-/// println!("{:?}", item.value);
+/// let mut tag = Tag::new();
+/// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// let mut data = Cursor::new(vec![0; 1024]);
+/// write_at(&tag, &mut data, 100, 256).unwrap();
+/// // The footer is located at the end of the region
+/// assert!(read_from_at(&mut data, 100 + 256 - 32).is_ok());
 /// ```
-pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Tag> {
-    let mut file = OpenOptions::new().read(true).open(path)?;
-    read_from(&mut file)
+pub fn write_at<W: Write + Seek>(tag: &Tag, writer: &mut W, offset: u64, max_len: u64) -> Result<()> {
+    WriteOptions::new().write_at(tag, writer, offset, max_len)
 }
 
-/// Attempts to read an APE tag from a reader
+pub(crate) fn write_tag_at<W: Write + Seek>(
+    tag: &Tag,
+    writer: &mut W,
+    offset: u64,
+    max_len: u64,
+    options: &WriteOptions,
+) -> Result<()> {
+    let serialized = Serialized::new(tag, options, 0, Layout::default())?;
+    let size = serialized.len();
+    if size > max_len || max_len - size > u32::MAX as u64 {
+        return Err(ErrorKind::TagTooLarge.into());
+    }
+    writer.seek(SeekFrom::Start(offset))?;
+    serialized.padded((max_len - size) as u32).write_buffered(writer)?;
+    metrics::record(|m| m.tag_written());
+    Ok(())
+}
+
+/// Copies a source to a writer replacing the APE tag with the given one.
 ///
-/// # Errors
+/// Audio data is streamed as is, an existing APE tag is skipped,
+/// the new tag is written after the audio data and followed by ID3v1 and LYRICS3v2 (if any).
+/// Unlike [`write_to`](fn.write_to.html), the destination does not need to be seekable,
+/// so it can be a pipe or an upload stream.
 ///
-/// See [`read_from_path`](fn.read_from_path.html)
-pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Tag> {
-    let meta = Meta::read(reader)?;
-    let mut items = Vec::<Item>::new();
+/// # Examples
+///
+/// ```no_run
+/// use ape::{copy_with_tag, Item, Tag};
+/// use std::{fs::File, io::stdout};
+///
+/// let mut tag = Tag::new();
+/// tag.set_item(Item::from_text("artist", "Artist Name").unwrap());
+/// let mut src = File::open("path/to/file").unwrap();
+/// copy_with_tag(&tag, &mut src, &mut stdout()).unwrap();
+/// ```
+pub fn copy_with_tag<R: Read + Seek, W: Write>(tag: &Tag, src: &mut R, dst: &mut W) -> Result<()> {
+    let serialized = Serialized::new(tag, &WriteOptions::new(), 0, Layout::default())?;
 
-    reader.seek(SeekFrom::Start(meta.start_pos))?;
+    let region = match Meta::read(src) {
+        Ok(meta) => Some(meta.region()?),
+        Err(err) if err.kind() == ErrorKind::TagNotFound => None,
+        Err(err) => return Err(err),
+    };
+    let filesize = src.seek(SeekFrom::End(0))?;
+    // A tag at the very end of a file is not followed by ID3v1 and LYRICS3v2
+    let audio_end = match region {
+        Some((offset, size)) if offset + size >= filesize => offset,
+        _ => filesize - probe_trailers(src)?,
+    };
+
+    src.seek(SeekFrom::Start(0))?;
+    match region {
+        Some((offset, size)) if offset < audio_end => {
+            io::copy(&mut src.take(offset), dst)?;
+            src.seek(SeekFrom::Start(offset + size))?;
+            io::copy(&mut src.take(audio_end.saturating_sub(offset + size)), dst)?;
+        }
+        _ => {
+            io::copy(&mut src.take(audio_end), dst)?;
+        }
+    }
+    serialized.write_to(dst)?;
+
+    // Copy ID3v1 and LYRICS3v2 (if any)
+    src.seek(SeekFrom::Start(
+        audio_end.max(region.map_or(0, |(offset, size)| offset + size)),
+    ))?;
+    io::copy(src, dst)?;
+
+    metrics::record(|m| m.tag_written());
+
+    Ok(())
+}
+
+/// Whether the tag differs from the one stored in a reader.
+pub(crate) fn is_changed<R: Read + Seek>(tag: &Tag, reader: &mut R) -> Result<bool> {
+    match read_from(reader) {
+        Ok(existing) => Ok(!existing.has_same_items(tag)),
+        Err(err) if err.kind() == ErrorKind::Io => Err(err),
+        // A missing or broken tag has to be written anyway
+        Err(_) => Ok(true),
+    }
+}
+
+pub(crate) fn write_tag<F: Read + Write + Seek + Truncate>(
+    tag: &Tag,
+    file: &mut F,
+    options: &WriteOptions,
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let layout = target_layout(file, options)?;
+    write_layout(tag, file, options, layout, journal)
+}
 
-    for _ in 0..meta.item_count {
-        let item_size = reader.read_u32::<LittleEndian>()?;
-        let item_flags = reader.read_u32::<LittleEndian>()?;
-        let mut item_key = Vec::<u8>::new();
-        let mut k = reader.read_u8()?;
+/// Returns the layout of a tag written with the given options.
+fn target_layout<R: Read + Seek>(reader: &mut R, options: &WriteOptions) -> Result<Layout> {
+    match options.preserves_layout() && options.ape_version() == ApeVersion::V2 {
+        true => existing_layout(reader),
+        // APEv1 tags have no header and are located at the end of a file
+        false => Ok(Layout::default()),
+    }
+}
+
+/// Describes where a tag is written, see [`write_layout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Placement {
+    /// The tag replaces a tag at the start of a file (if any), tags at the end are removed.
+    Start,
+    /// The tag overwrites the existing tag occupying the `size` bytes at `offset`.
+    InPlace { offset: u64, size: u64 },
+    /// Existing tags are removed and the tag is appended before ID3v1 and LYRICS3v2.
+    End,
+}
 
-        while k != 0 {
-            item_key.push(k);
-            k = reader.read_u8()?;
+/// Decides where the tag is written and checks it can be written there.
+fn placement<R: Read + Seek>(serialized: &Serialized, reader: &mut R, options: &WriteOptions) -> Result<Placement> {
+    if serialized.layout.at_start {
+        if detect_container(reader)?.forbids_header_tag() {
+            return Err(ErrorKind::UnsafeTagPlacement.into());
+        }
+        return Ok(Placement::Start);
+    }
+    if options.padding_size() > 0 {
+        if let Some((offset, size)) = in_place_region(serialized, reader, options)? {
+            return Ok(Placement::InPlace { offset, size });
+        }
+    }
+    if options.verifies_mpeg_frames() && detect_container(reader)? == ContainerKind::Mp3 {
+        match check_frames(reader)? {
+            FrameCheck::TruncatedFrame { .. } => return Err(ErrorKind::TruncatedFrame.into()),
+            FrameCheck::Junk { .. } => return Err(ErrorKind::JunkBeforeTag.into()),
+            FrameCheck::Clean | FrameCheck::NoFrames => {}
         }
+    }
+    Ok(Placement::End)
+}
 
-        let mut item_value = Vec::<u8>::with_capacity(item_size as usize);
-        reader.take(item_size as u64).read_to_end(&mut item_value)?;
+/// Returns where the tag would be written by [`write_tag`] and its size, nothing is modified.
+pub(crate) fn plan_placement<R: Read + Seek>(
+    tag: &Tag,
+    reader: &mut R,
+    options: &WriteOptions,
+) -> Result<(Placement, u64)> {
+    let layout = target_layout(reader, options)?;
+    let serialized = Serialized::new(tag, options, options.padding_size(), layout)?;
+    Ok((placement(&serialized, reader, options)?, serialized.len()))
+}
 
-        let item_key = str::from_utf8(&item_key)?;
-        items.push(match (item_flags & 6) >> 1 {
-            KIND_BINARY => Item::from_binary(item_key, item_value)?,
-            KIND_LOCATOR => Item::from_locator(item_key, str::from_utf8(&item_value)?)?,
-            KIND_TEXT => Item::from_text(item_key, str::from_utf8(&item_value)?)?,
-            _ => {
-                return Err(Error::BadItemKind);
+/// Writes the tag replacing an existing one using the given layout.
+fn write_layout<F: Read + Write + Seek + Truncate>(
+    tag: &Tag,
+    file: &mut F,
+    options: &WriteOptions,
+    layout: Layout,
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    span!("write_tag", at_start = layout.at_start);
+    // Convert items to bytes
+    // Do it as early as possible because if there is any error,
+    // we return it without modifying the file
+    let serialized = Serialized::new(tag, options, options.padding_size(), layout)?;
+
+    match placement(&serialized, file, options)? {
+        Placement::Start => replace_at_start(file, &serialized, options, journal)?,
+        Placement::InPlace { offset, size } => update_in_place(&serialized, file, (offset, size), journal)?,
+        Placement::End => match journal {
+            Some(journal) => {
+                // The journal keeps the whole tag to append it after recovery
+                let bytes = serialized.to_vec()?;
+                remove_tag(file, options, Some(&mut *journal), &bytes)?;
+                append_tag(file, &bytes, Some(journal))?;
             }
-        });
+            None => {
+                remove_tag(file, options, None, &[])?;
+                append_serialized(file, &serialized)?;
+            }
+        },
     }
 
-    if reader.stream_position()? != meta.end_pos {
-        Err(Error::BadTagSize)
-    } else {
-        Ok(Tag(items))
-    }
+    metrics::record(|m| m.tag_written());
+
+    Ok(())
 }
 
-/// Attempts to remove APE tag from the file at the specified path.
+/// Moves an existing tag to the start (header position) or to the end (footer position) of a file.
+///
+/// Items keep their order, a tag at the start of a file always contains a header.
+/// Nothing is changed if the tag is already at the given position.
 ///
 /// # Errors
 ///
-/// - It is considered a error when tag version is not 2.000.
-/// - It is **not** considered a error when tag does not exists.
+/// It is considered a error when a file contains no tag or the tag can not be read,
+/// see [`read_from_path`](fn.read_from_path.html).
+/// Moving a tag to the start of Monkey's Audio, Musepack, WavPack or MP3 stream is refused,
+/// since decoders of these formats expect the stream at the start,
+/// see [`detect_container`](container/fn.detect_container.html).
 ///
 /// # Examples
 ///
 /// ```no_run
-/// use ape::remove_from_path;
+/// use ape::{relocate_tag, MetaPosition};
+/// use std::fs::OpenOptions;
 ///
-/// remove_from_path("path/to/file").unwrap();
+/// let mut file = OpenOptions::new().read(true).write(true).open("path/to/file").unwrap();
+/// relocate_tag(&mut file, MetaPosition::Header).unwrap();
 /// ```
-pub fn remove_from_path<P: AsRef<Path>>(path: P) -> Result<()> {
-    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
-    remove_from(&mut file)?;
+pub fn relocate_tag<F: Read + Write + Seek + Truncate>(file: &mut F, position: MetaPosition) -> Result<()> {
+    let layout = Meta::read(file)?.layout();
+    let layout = match position {
+        MetaPosition::Header if !layout.at_start => Layout {
+            header: true,
+            at_start: true,
+            ..layout
+        },
+        MetaPosition::Footer if layout.at_start => Layout {
+            footer: true,
+            at_start: false,
+            ..layout
+        },
+        _ => return Ok(()),
+    };
+    let tag = read_from(file)?;
+    let mut options = WriteOptions::new();
+    options.preserve_layout(true);
+    write_layout(&tag, file, &options, layout, None)
+}
 
-    Ok(())
+/// Returns the size of an item written to a tag of the given version.
+fn item_size(item: &Item, version: u32) -> u64 {
+    match item.value {
+        // APEv1 text is encoded as ISO-8859-1, so every character is a byte
+        ItemValue::Text(ref value) if version == APE_VERSION_1 => {
+            item.size() - value.len() as u64 + value.chars().count() as u64
+        }
+        _ => item.size(),
+    }
 }
 
-/// Attempts to remove an APE tag from a File
-///
-/// # Errors
+/// Converts the tag to bytes including header, padding and footer according to the layout.
+pub(crate) fn serialize(tag: &Tag, options: &WriteOptions, padding: u32, layout: Layout) -> Result<Vec<u8>> {
+    Serialized::new(tag, options, padding, layout)?.to_vec()
+}
+
+/// Returns indices of items sorted ascending by size, as suggested by the specification.
 ///
-/// See [`remove_from_path`](fn.remove_from_path.html)
-pub fn remove_from(file: &mut File) -> Result<()> {
-    let meta = match Meta::read(file) {
-        Ok(meta) => meta,
-        Err(error) => {
-            return match error {
-                Error::TagNotFound => {
-                    // It's ok, nothing to remove.
-                    Ok(())
-                }
-                _ => Err(error),
-            };
+/// Items with the same key keep their relative order, so multiple values are read in the order they were added.
+pub(crate) fn size_order<'a, I: Iterator<Item = (&'a str, u64)>>(items: I) -> Vec<usize> {
+    let items = items.collect::<Vec<_>>();
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| items[idx].1);
+    let mut done = vec![false; order.len()];
+    for pos in 0..order.len() {
+        if done[pos] {
+            continue;
         }
-    };
+        // Items keep the positions they were sorted to, only items with the same key are swapped
+        let key = items[order[pos]].0;
+        let slots = (pos..order.len())
+            .filter(|&slot| items[order[slot]].0.eq_ignore_ascii_case(key))
+            .collect::<Vec<_>>();
+        let mut group = slots.iter().map(|&slot| order[slot]).collect::<Vec<_>>();
+        group.sort_unstable();
+        for (slot, idx) in slots.into_iter().zip(group) {
+            order[slot] = idx;
+            done[slot] = true;
+        }
+    }
+    order
+}
 
-    let mut size = meta.size as u64;
-    let mut offset;
+/// Items of a tag ordered for writing.
+///
+/// The tag is written item by item, so values are never copied into an intermediate buffer.
+struct Serialized<'a> {
+    items: Vec<&'a Item>,
+    version: u32,
+    padding: u32,
+    layout: Layout,
+}
 
-    match meta.position {
-        MetaPosition::Header => {
-            offset = 0;
-            size += 32;
+impl<'a> Serialized<'a> {
+    fn new(tag: &'a Tag, options: &WriteOptions, padding: u32, layout: Layout) -> Result<Serialized<'a>> {
+        if tag.is_partial() {
+            return Err(ErrorKind::PartialTag.into());
         }
-        MetaPosition::Footer => {
-            offset = meta.start_pos;
-            if meta.has_header {
-                offset -= 32;
-                size += 32;
+        let version = match options.ape_version() {
+            ApeVersion::V1 => {
+                // APEv1 items have no flags, so only text values are allowed
+                for item in tag.iter() {
+                    match item.value {
+                        ItemValue::Text(ref value) if encode_latin1(value).is_none() => {
+                            return Err(Error::from(ErrorKind::UnencodableText).with_key(&item.key));
+                        }
+                        ItemValue::Text(_) => {}
+                        _ => return Err(ErrorKind::BadItemKind.into()),
+                    }
+                }
+                APE_VERSION_1
+            }
+            ApeVersion::V2 => APE_VERSION,
+        };
+        for item in tag.iter() {
+            options.validate_key(&item.key)?;
+        }
+        let mut items = tag.iter().collect::<Vec<_>>();
+
+        if !options.preserves_layout() {
+            match options.order() {
+                // APE tag items should be sorted ascending by size
+                ItemOrder::Size => {
+                    let order = size_order(items.iter().map(|item| (&*item.key, item_size(item, version))));
+                    items = order.into_iter().map(|idx| &tag.0[idx]).collect();
+                }
+                ItemOrder::Key => items.sort_by(|a, b| {
+                    let a = a.key.bytes().map(|x| x.to_ascii_lowercase());
+                    let b = b.key.bytes().map(|x| x.to_ascii_lowercase());
+                    a.cmp(b)
+                }),
+                ItemOrder::Insertion => {}
+                ItemOrder::Custom(compare) => items.sort_by(|a, b| compare(a, b)),
             }
         }
-    }
 
-    let filesize = file.seek(SeekFrom::End(0))?;
-    let movesize = filesize - offset - size;
+        Ok(Serialized {
+            items,
+            version,
+            padding,
+            layout,
+        })
+    }
 
-    const BUFFER_SIZE: u64 = 65536;
+    /// Replaces the number of zero bytes following items.
+    fn padded(&self, padding: u32) -> Serialized<'a> {
+        Serialized {
+            items: self.items.clone(),
+            padding,
+            ..*self
+        }
+    }
 
-    if movesize > 0 {
-        file.flush()?;
-        file.seek(SeekFrom::Start(offset + size))?;
+    /// Tag size including padding and footer.
+    fn size(&self) -> u64 {
+        let items = self.items.iter().map(|item| item_size(item, self.version)).sum::<u64>();
+        items + u64::from(self.padding) + if self.layout.footer { APE_HEADER_SIZE } else { 0 }
+    }
 
-        let mut buff = Vec::<u8>::with_capacity(BUFFER_SIZE as usize);
-        file.take(BUFFER_SIZE).read_to_end(&mut buff)?;
+    /// Number of bytes written including header.
+    fn len(&self) -> u64 {
+        self.size() + if self.layout.header { APE_HEADER_SIZE } else { 0 }
+    }
 
-        while !buff.is_empty() {
-            file.seek(SeekFrom::Start(offset))?;
-            file.write_all(&buff)?;
-            offset += buff.len() as u64;
-            file.seek(SeekFrom::Start(offset + size))?;
-            buff.clear();
-            file.take(BUFFER_SIZE).read_to_end(&mut buff)?;
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let (size, count) = (self.size() as u32, self.items.len() as u32);
+        if self.layout.header {
+            write_meta(writer, self.version, size, count, self.layout.flags() | IS_HEADER)?;
+        }
+        for item in &self.items {
+            match item.value {
+                // Text has been checked to be encodable
+                ItemValue::Text(ref value) if self.version == APE_VERSION_1 => {
+                    write_item(writer, &item.key, 0, &encode_latin1(value).unwrap_or_default())?
+                }
+                _ => item.write_to(writer)?,
+            }
+        }
+        io::copy(&mut io::repeat(0).take(u64::from(self.padding)), writer)?;
+        if self.layout.footer {
+            write_meta(writer, self.version, size, count, self.layout.flags())?;
         }
+        Ok(())
     }
 
-    file.set_len(filesize - size)?;
-    file.flush()?;
+    /// Writes the tag to a file, small pieces are buffered while large values are written directly.
+    fn write_buffered<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut writer = BufWriter::new(writer);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
 
-    Ok(())
+    fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.len() as usize);
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
 }
 
-#[cfg(test)]
-mod test {
-    use super::{read_from_path, remove_from_path, write_to_path, Tag};
-    use crate::item::{Item, ItemValue};
-    use std::{
-        fs::{remove_file, File},
-        io::Write,
-    };
+/// Joins serialized items and adds header, padding and footer according to the layout.
+pub(crate) fn assemble(items: Vec<Vec<u8>>, version: u32, padding: u32, layout: Layout) -> Result<Vec<u8>> {
+    let count = items.len() as u32;
+    let mut body = items.into_iter().flatten().collect::<Vec<_>>();
+    body.resize(body.len() + padding as usize, 0);
+    // Tag size including padding and footer
+    let size = body.len() + if layout.footer { APE_HEADER_SIZE as usize } else { 0 };
+
+    let mut bytes = Vec::with_capacity(size + APE_HEADER_SIZE as usize);
+    if layout.header {
+        write_meta(&mut bytes, version, size as u32, count, layout.flags() | IS_HEADER)?;
+    }
+    bytes.append(&mut body);
+    if layout.footer {
+        write_meta(&mut bytes, version, size as u32, count, layout.flags())?;
+    }
 
-    #[test]
-    fn items() {
-        let mut tag = Tag::new();
-        assert_eq!(0, tag.0.len());
+    Ok(bytes)
+}
+
+/// Writes a header or footer of a tag.
+fn write_meta<W: Write>(bytes: &mut W, version: u32, size: u32, item_count: u32, flags: u32) -> Result<()> {
+    bytes.write_all(APE_PREAMBLE)?;
+    bytes.write_u32::<LittleEndian>(version)?;
+    bytes.write_u32::<LittleEndian>(size)?;
+    bytes.write_u32::<LittleEndian>(item_count)?;
+    bytes.write_u32::<LittleEndian>(flags)?;
+    // Reserved
+    bytes.write_all(&[0; 8])?;
+    Ok(())
+}
+
+/// Returns the layout of an existing tag or the default one if there is no tag.
+fn existing_layout<R: Read + Seek>(reader: &mut R) -> Result<Layout> {
+    match Meta::read(reader) {
+        Ok(meta) => Ok(meta.layout()),
+        Err(err) if err.kind() == ErrorKind::TagNotFound => Ok(Layout::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the region of an existing tag which the new one fits into including padding.
+///
+/// Only a tag without header located at the end of a file (before ID3v1 and LYRICS3v2) is updated.
+fn in_place_region<R: Read + Seek>(
+    serialized: &Serialized,
+    reader: &mut R,
+    options: &WriteOptions,
+) -> Result<Option<(u64, u64)>> {
+    let meta = match existing_tags(reader, options)? {
+        // A tag at the start has to be removed
+        all if all.len() == 1 => all.into_iter().next().unwrap(),
+        _ => return Ok(None),
+    };
+    if meta.position != MetaPosition::Footer || meta.layout() != serialized.layout {
+        return Ok(None);
+    }
+    let (offset, size) = meta.region()?;
+    let filesize = reader.seek(SeekFrom::End(0))?;
+    if !precedes_trailers(reader, (offset, size), filesize)? || serialized.padded(0).len() > size {
+        return Ok(None);
+    }
+    Ok(Some((offset, size)))
+}
+
+/// Overwrites the `size` bytes at `offset` of an existing tag, the new tag is padded to the same size.
+fn update_in_place<F: Read + Write + Seek>(
+    serialized: &Serialized,
+    file: &mut F,
+    (offset, size): (u64, u64),
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let unpadded_size = serialized.padded(0).len();
+    let serialized = serialized.padded((size - unpadded_size) as u32);
+
+    if let Some(journal) = journal {
+        let mut data = serialized.to_vec()?;
+        file.seek(SeekFrom::Start(offset + size))?;
+        file.read_to_end(&mut data)?;
+        journal.begin_append(offset, &data)?;
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+    serialized.write_buffered(file)?;
+
+    Ok(())
+}
+
+/// Writes serialized tag at the start of a file replacing an existing one (if any).
+///
+/// A tag at the end of a file is removed, bytes following the tag at the start are moved once
+/// by the difference of sizes of the tags.
+fn replace_at_start<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    tag: &Serialized,
+    options: &WriteOptions,
+    mut journal: Option<&mut Journal>,
+) -> Result<()> {
+    let (start, end): (Vec<_>, Vec<_>) = existing_tags(file, options)?
+        .into_iter()
+        .partition(|meta| meta.position == MetaPosition::Header);
+    remove_regions(file, end, options, journal.as_deref_mut(), &[])?;
+
+    let size = start.first().map(Meta::region).transpose()?.map_or(0, |(_, size)| size);
+    let filesize = file.seek(SeekFrom::End(0))?;
+    let buffer_size = shift_buffer_size(options, journal.is_some());
+    match journal {
+        Some(journal) => {
+            // The journal keeps the whole tag to write it after recovery
+            let bytes = tag.to_vec()?;
+            journal.begin_replace(0, size, filesize, buffer_size, &bytes)?;
+            move_following(
+                file,
+                (0, size, tag.len()),
+                filesize,
+                None,
+                buffer_size,
+                options,
+                Some(journal),
+            )?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&bytes)?;
+        }
+        None => {
+            move_following(file, (0, size, tag.len()), filesize, None, buffer_size, options, None)?;
+            file.seek(SeekFrom::Start(0))?;
+            tag.write_buffered(file)?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Moves bytes following the `old` bytes at `offset` to `offset + new`, so the `old` bytes can be replaced with `new` ones.
+///
+/// `last_chunk` is the progress and the length of a chunk moved before an interruption (if any),
+/// it is used to resume the movement.
+pub(crate) fn move_following<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    (offset, old, new): (u64, u64, u64),
+    filesize: u64,
+    last_chunk: Option<(u64, u64)>,
+    buffer_size: u64,
+    options: &WriteOptions,
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let movesize = filesize - offset - old;
+    if new < old {
+        let progress = last_chunk.map_or(0, |(progress, len)| progress + len);
+        shift_left(
+            file,
+            (offset + new, old - new),
+            filesize,
+            progress,
+            buffer_size,
+            options,
+            journal,
+        )
+    } else if new > old {
+        // Moving to the right starts from the end, so the progress is the start of the last chunk
+        let end = last_chunk.map_or(movesize, |(start, _)| start);
+        shift_right(file, (offset + old, new - old), 0..end, buffer_size, journal, |done| {
+            options.report_progress(movesize - end + done, movesize)
+        })?;
+        if end > 0 {
+            debug!(bytes = end, "moved bytes following a tag");
+            metrics::record(|m| m.bytes_shifted(end));
+        }
+        Ok(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the size of chunks of moved bytes.
+fn shift_buffer_size(options: &WriteOptions, journaled: bool) -> u64 {
+    // Every chunk is copied into the journal, so it is kept small
+    match journaled {
+        true => options.shift_buffer_size().min(JOURNAL_BUFFER_SIZE),
+        false => options.shift_buffer_size(),
+    }
+}
+
+/// Appends serialized tag to the end of a file keeping ID3v1 and LYRICS3v2 (if any) after it.
+pub(crate) fn append_tag<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    tag: &[u8],
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let (base, id3) = stash_trailers(file)?;
+
+    if let Some(journal) = journal {
+        journal.begin_append(base, &[tag, &id3].concat())?;
+    }
+
+    file.truncate(base)?;
+    file.seek(SeekFrom::Start(base))?;
+    file.write_all(tag)?;
+    file.write_all(&id3)?;
+
+    Ok(())
+}
+
+/// Appends a tag to the end of a file writing it item by item, see [`append_tag`].
+fn append_serialized<F: Read + Write + Seek + Truncate>(file: &mut F, tag: &Serialized) -> Result<()> {
+    let (base, id3) = stash_trailers(file)?;
+
+    file.truncate(base)?;
+    file.seek(SeekFrom::Start(base))?;
+    tag.write_buffered(file)?;
+    file.write_all(&id3)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Reads ID3v1 and LYRICS3v2 (if any), returns their offset and bytes.
+fn stash_trailers<R: Read + Seek>(reader: &mut R) -> Result<(u64, Vec<u8>)> {
+    let end_size = probe_trailers(reader)?;
+    let filesize = reader.seek(SeekFrom::End(0))?;
+    let base = filesize - end_size;
+
+    let mut id3 = Vec::<u8>::new();
+    reader.seek(SeekFrom::Start(base))?;
+    reader.take(end_size).read_to_end(&mut id3)?;
+    Ok((base, id3))
+}
+
+/// Attempts to read an APE tag from the file at the specified path.
+///
+/// # Errors
+///
+/// It is considered a error when:
+///
+/// - APE tag does not exists.
+/// - Tag version is neither 1.000 nor 2.000. Items of APEv1 tags are read as text ones encoded as ISO-8859-1.
+/// - Item key is not valid.
+/// - Kind of an item is unknown.
+/// - Tag size declared in the APE header does not match with actual size.
+///
+/// Errors of functions accepting a path carry the path, see [`Error::path`](struct.Error.html#method.path),
+/// errors caused by a malformed item carry its offset, see [`Error::offset`](struct.Error.html#method.offset).
+///
+/// When a file contains a tag at the start and another one at the end, items are merged.
+/// Items of the tag at the end take precedence, items with other keys are taken from the tag at the start.
+/// Both tags are removed when a tag is written or removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_from_path;
+///
+/// let tag = read_from_path("path/to/file").unwrap();
+/// let item = tag.item("artist").unwrap();
+/// println!("{:?}", item.value);
+/// ```
+pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Tag> {
+    ReadOptions::new().read_from_path(path)
+}
+
+/// Attempts to read an APE tag from a reader
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Tag> {
+    ReadOptions::new().read_from(reader)
+}
+
+/// Attempts to read an APE tag from the end of a stream which can't be seeked, e.g. an HTTP body or a pipe.
+///
+/// The stream is read until the end, only the last `tail_hint` bytes are kept in memory
+/// and the tag is searched for in them the same way [`read_from`](fn.read_from.html) does.
+/// A tag at the start of the stream is found only when the whole stream fits into `tail_hint` bytes.
+///
+/// # Errors
+///
+/// If the tag (including a trailing ID3v1 tag, if any) doesn't fit into `tail_hint` bytes,
+/// `ErrorKind::BadTagSize` is returned; other errors are described in [`read_from_path`](fn.read_from_path.html).
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_from_stream;
+/// use std::io::stdin;
+///
+/// let tag = read_from_stream(stdin().lock(), 1 << 20).unwrap();
+/// ```
+pub fn read_from_stream<R: Read>(reader: R, tail_hint: usize) -> Result<Tag> {
+    ReadOptions::new().read_from_stream(reader, tail_hint)
+}
+
+pub(crate) fn read_tag_from_stream<R: Read>(mut reader: R, tail_hint: usize, options: &ReadOptions) -> Result<Tag> {
+    span!("read_tag_from_stream", tail_hint);
+    let mut tail = Vec::new();
+    // The buffer grows up to twice the hint, so each byte is moved at most once on average
+    while (&mut reader).take(tail_hint as u64).read_to_end(&mut tail)? > 0 {
+        if tail.len() > tail_hint {
+            tail.drain(..tail.len() - tail_hint);
+        }
+    }
+    read_tag(&mut io::Cursor::new(tail), options)
+}
+
+/// Attempts to read an APE tag which header or footer is located at the given offset.
+///
+/// No probing is performed, it is useful when a container format stores the location of a tag.
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_from_at;
+/// use std::fs::File;
+///
+/// let mut file = File::open("path/to/file").unwrap();
+/// let tag = read_from_at(&mut file, 1024).unwrap();
+/// ```
+pub fn read_from_at<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<Tag> {
+    ReadOptions::new().read_from_at(reader, offset)
+}
+
+pub(crate) fn read_tag_at<R: Read + Seek>(reader: &mut R, offset: u64, options: &ReadOptions) -> Result<Tag> {
+    let meta = Meta::read_at(reader, offset)?;
+    let (items, partial) = read_items(reader, &meta, options, None)?;
+    let items = resolve_duplicates(items, options.duplicate_key_policy())?;
+    metrics::record(|m| m.tag_read());
+    Ok(Tag(items, partial))
+}
+
+pub(crate) fn read_tag<R: Read + Seek>(reader: &mut R, options: &ReadOptions) -> Result<Tag> {
+    read_tag_deferred(reader, options, None)
+}
+
+/// Reads a tag, Binary values are located only and collected into `deferred` if it is given.
+pub(crate) fn read_tag_deferred<R: Read + Seek>(
+    reader: &mut R,
+    options: &ReadOptions,
+    mut deferred: Option<&mut Vec<(String, ValueHandle)>>,
+) -> Result<Tag> {
+    span!("read_tag");
+    let all = Meta::read_all(reader, options.garbage_window())?;
+    let (mut items, mut partial) = read_items(reader, &all[0], options, deferred.as_deref_mut())?;
+    if let Some(start) = all.get(1) {
+        // The tag at the end takes precedence, only items with other keys are taken from the start
+        let mut start_deferred = Vec::new();
+        let (start_items, start_partial) = read_items(
+            reader,
+            start,
+            options,
+            deferred.is_some().then_some(&mut start_deferred),
+        )?;
+        partial |= start_partial;
+        let end_deferred = deferred.as_deref().map_or(&[][..], Vec::as_slice);
+        let is_new = |key: &str| {
+            !items.iter().any(|x| x.key.eq_ignore_ascii_case(key))
+                && !end_deferred.iter().any(|(x, _)| x.eq_ignore_ascii_case(key))
+        };
+        let start_items = start_items
+            .into_iter()
+            .filter(|item| is_new(&item.key))
+            .collect::<Vec<_>>();
+        let start_deferred = start_deferred
+            .into_iter()
+            .filter(|(key, _)| is_new(key))
+            .collect::<Vec<_>>();
+        items.extend(start_items);
+        if let Some(deferred) = deferred {
+            deferred.extend(start_deferred);
+        }
+    }
+    let items = resolve_duplicates(items, options.duplicate_key_policy())?;
+    metrics::record(|m| m.tag_read());
+    Ok(Tag(items, partial))
+}
+
+/// Handles items with the same key according to the policy.
+fn resolve_duplicates(items: Vec<Item>, policy: DuplicateKeys) -> Result<Vec<Item>> {
+    if policy == DuplicateKeys::KeepAll {
+        return Ok(items);
+    }
+    let mut result = Vec::<Item>::with_capacity(items.len());
+    for item in items {
+        let first = match result.iter_mut().find(|x| x.key.eq_ignore_ascii_case(&item.key)) {
+            Some(first) => first,
+            None => {
+                result.push(item);
+                continue;
+            }
+        };
+        match (policy, &mut first.value, item.value) {
+            (DuplicateKeys::Error, _, _) => return Err(Error::from(ErrorKind::DuplicateKey).with_key(&item.key)),
+            (DuplicateKeys::MergeValues, ItemValue::Text(ref mut first), ItemValue::Text(value))
+            | (DuplicateKeys::MergeValues, ItemValue::Locator(ref mut first), ItemValue::Locator(value)) => {
+                first.push('\0');
+                first.push_str(&value);
+            }
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+/// Reads items of the tag described by the metadata and returns whether Binary items have been spilled.
+///
+/// Binary values are skipped and their locations are collected into `deferred` if it is given.
+fn read_items<R: Read + Seek>(
+    reader: &mut R,
+    meta: &Meta,
+    options: &ReadOptions,
+    mut deferred: Option<&mut Vec<(String, ValueHandle)>>,
+) -> Result<(Vec<Item>, bool)> {
+    let mut items = Vec::<Item>::new();
+    let item_count = item_count(reader, meta, options)?;
+
+    reader.seek(SeekFrom::Start(meta.start_pos))?;
+
+    let mut partial = false;
+    for index in 0..item_count {
+        let offset = reader.stream_position()?;
+        partial |= read_item(reader, meta.version, options, deferred.as_deref_mut(), &mut items)
+            .map_err(|err| err.at_item(index, offset))?;
+    }
+
+    // Items may be followed by zero padding
+    let position = reader.stream_position()?;
+    let mut padding = Vec::new();
+    if position <= meta.end_pos {
+        reader.take(meta.end_pos - position).read_to_end(&mut padding)?;
+    }
+    if position > meta.end_pos || padding.iter().any(|&byte| byte != 0) {
+        Err(ErrorKind::BadTagSize.into())
+    } else {
+        Ok((items, partial))
+    }
+}
+
+/// Returns the number of items to read, see [`ReadOptions::tolerate_item_count`].
+pub(crate) fn item_count<R: Read + Seek>(reader: &mut R, meta: &Meta, options: &ReadOptions) -> Result<u32> {
+    if !options.tolerates_item_count() {
+        return Ok(meta.item_count);
+    }
+    let found = count_items(reader, meta)?;
+    if found != meta.item_count {
+        debug!(declared = meta.item_count, found, "item count mismatch");
+        metrics::record(|m| m.lenient_recovery());
+    }
+    Ok(found)
+}
+
+/// Counts complete items fitting into the tag described by the metadata.
+pub(crate) fn count_items<R: Read + Seek>(reader: &mut R, meta: &Meta) -> Result<u32> {
+    let mut count = 0;
+    let mut position = meta.start_pos;
+    while position < meta.end_pos {
+        reader.seek(SeekFrom::Start(position))?;
+        let (item_size, _, item_key) = match read_raw_item_header(&mut (&mut *reader).take(meta.end_pos - position)) {
+            Ok(header) => header,
+            // The rest of the tag is too short to contain an item
+            Err(err) if err.kind() == ErrorKind::Io => break,
+            Err(err) => return Err(err),
+        };
+        if item_key.is_empty() {
+            // Zero padding
+            break;
+        }
+        position += 8 + item_key.len() as u64 + 1 + u64::from(item_size);
+        if position > meta.end_pos {
+            break;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Reads an item and returns whether it has been spilled, see [`read_items`].
+///
+/// APEv1 items are text ones, which are encoded as ISO-8859-1.
+fn read_item<R: Read + Seek>(
+    reader: &mut R,
+    version: u32,
+    options: &ReadOptions,
+    deferred: Option<&mut Vec<(String, ValueHandle)>>,
+    items: &mut Vec<Item>,
+) -> Result<bool> {
+    let header = read_item_header(reader, version, options)?;
+
+    if header.kind() == KIND_BINARY && options.spills(header.size as u64) {
+        trace!(key = header.key.as_str(), size = header.size, "spilled item");
+        let start = reader.stream_position()?;
+        options.spill(&header.key, &mut reader.take(header.size as u64))?;
+        reader.seek(SeekFrom::Start(start + header.size as u64))?;
+        return Ok(true);
+    }
+    if let Some(deferred) = deferred.filter(|_| header.kind() == KIND_BINARY) {
+        let offset = reader.stream_position()?;
+        reader.seek(SeekFrom::Start(offset + header.size as u64))?;
+        let handle = ValueHandle {
+            offset,
+            len: header.size as u64,
+            read_only: header.is_read_only(),
+        };
+        deferred.push((header.key, handle));
+        return Ok(false);
+    }
+
+    let mut item_value = Vec::<u8>::with_capacity(header.size as usize);
+    reader.take(header.size as u64).read_to_end(&mut item_value)?;
+
+    trace!(
+        key = header.key.as_str(),
+        size = header.size,
+        flags = header.flags,
+        "parsed item"
+    );
+    let value = match header.kind() {
+        KIND_BINARY => ItemValue::Binary(item_value.into()),
+        KIND_LOCATOR => ItemValue::Locator(decode_text_value(item_value, version, options)?),
+        KIND_TEXT => ItemValue::Text(decode_text_value(item_value, version, options)?),
+        _ => ItemValue::Reserved(item_value),
+    };
+    items.push(Item {
+        key: options.intern_key(&header.key),
+        value,
+        read_only: header.is_read_only(),
+    });
+    Ok(false)
+}
+
+/// A value size, flags and a key of an item.
+pub(crate) struct ItemHeader {
+    pub(crate) size: u32,
+    pub(crate) flags: u32,
+    pub(crate) key: String,
+}
+
+impl ItemHeader {
+    /// Returns the kind of the value, e.g. `KIND_TEXT`.
+    pub(crate) fn kind(&self) -> u32 {
+        (self.flags & 6) >> 1
+    }
+
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+/// Reads a value size, flags and a key of an item, the key and the kind are validated.
+///
+/// APEv1 items have no flags, so they are always read as Text ones.
+pub(crate) fn read_item_header<R: Read>(reader: &mut R, version: u32, options: &ReadOptions) -> Result<ItemHeader> {
+    let (size, flags, key) = read_raw_item_header(reader)?;
+    let key = String::from_utf8(key).map_err(|err| err.utf8_error())?;
+    options.validate_key(&key)?;
+    let flags = if version == APE_VERSION_1 { 0 } else { flags };
+    if (flags & 6) >> 1 == KIND_RESERVED && !options.allows_reserved() {
+        return Err(ErrorKind::BadItemKind.into());
+    }
+    Ok(ItemHeader { size, flags, key })
+}
+
+/// Reads a value size, flags and a key of an item as they are.
+fn read_raw_item_header<R: Read>(reader: &mut R) -> Result<(u32, u32, Vec<u8>)> {
+    let item_size = reader.read_u32::<LittleEndian>()?;
+    let item_flags = reader.read_u32::<LittleEndian>()?;
+    let mut item_key = Vec::<u8>::new();
+    let mut k = reader.read_u8()?;
+
+    while k != 0 {
+        item_key.push(k);
+        k = reader.read_u8()?;
+    }
+    Ok((item_size, item_flags, item_key))
+}
+
+/// Decodes a Text or Locator value, APEv1 values are encoded as ISO-8859-1.
+pub(crate) fn decode_text_value(value: Vec<u8>, version: u32, options: &ReadOptions) -> Result<String> {
+    if version == APE_VERSION_1 {
+        Ok(decode_latin1(&value))
+    } else {
+        options.decode_text(value)
+    }
+}
+
+/// Attempts to read keys, types and value sizes of items, values are skipped.
+///
+/// It allows to find out which items exist without reading values, e.g. covers.
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::{read_keys, ItemType};
+/// use std::fs::File;
+///
+/// let keys = read_keys(&mut File::open("path/to/file").unwrap()).unwrap();
+/// let has_cover = keys.iter().any(|(key, kind, _)| key.starts_with("Cover Art") && *kind == ItemType::Binary);
+/// ```
+pub fn read_keys<R: Read + Seek>(reader: &mut R) -> Result<Vec<(String, ItemType, u32)>> {
+    ReadOptions::new().read_keys(reader)
+}
+
+pub(crate) fn read_tag_keys<R: Read + Seek>(
+    reader: &mut R,
+    options: &ReadOptions,
+) -> Result<Vec<(String, ItemType, u32)>> {
+    let all = Meta::read_all(reader, options.garbage_window())?;
+    let mut keys = read_item_keys(reader, &all[0], options)?;
+    if let Some(start) = all.get(1) {
+        // The tag at the end takes precedence, only items with other keys are taken from the start
+        let count = keys.len();
+        for key in read_item_keys(reader, start, options)? {
+            if !keys[..count].iter().any(|x| x.0.eq_ignore_ascii_case(&key.0)) {
+                keys.push(key);
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// Reads keys of the tag described by the metadata, values are skipped.
+pub(crate) fn read_item_keys<R: Read + Seek>(
+    reader: &mut R,
+    meta: &Meta,
+    options: &ReadOptions,
+) -> Result<Vec<(String, ItemType, u32)>> {
+    let item_count = item_count(reader, meta, options)?;
+    reader.seek(SeekFrom::Start(meta.start_pos))?;
+    let mut keys = Vec::with_capacity(meta.items_capacity(item_count));
+    for index in 0..item_count {
+        let offset = reader.stream_position()?;
+        keys.push(read_item_key(reader, meta.version, options).map_err(|err| err.at_item(index, offset))?);
+    }
+    if reader.stream_position()? > meta.end_pos {
+        return Err(ErrorKind::BadTagSize.into());
+    }
+    Ok(keys)
+}
+
+/// Reads a key of an item, the value is skipped.
+fn read_item_key<R: Read + Seek>(
+    reader: &mut R,
+    version: u32,
+    options: &ReadOptions,
+) -> Result<(String, ItemType, u32)> {
+    let header = read_item_header(reader, version, options)?;
+    reader.seek(SeekFrom::Current(i64::from(header.size)))?;
+    Ok((header.key, ItemType::from_flags(header.flags), header.size))
+}
+
+/// Attempts to remove APE tag from the file at the specified path.
+///
+/// # Errors
+///
+/// - It is considered a error when tag version is neither 1.000 nor 2.000.
+/// - It is **not** considered a error when tag does not exists.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::remove_from_path;
+///
+/// remove_from_path("path/to/file").unwrap();
+/// ```
+pub fn remove_from_path<P: AsRef<Path>>(path: P) -> Result<()> {
+    WriteOptions::new().remove_from_path(path)
+}
+
+/// Attempts to remove an APE tag from a File or any other truncatable storage
+///
+/// # Errors
+///
+/// See [`remove_from_path`](fn.remove_from_path.html)
+pub fn remove_from<F: Read + Write + Seek + Truncate>(file: &mut F) -> Result<()> {
+    WriteOptions::new().remove_from(file)
+}
+
+/// Removes APE, ID3v1 and LYRICS3v2 tags from a File or any other truncatable storage,
+/// so only audio data remains.
+///
+/// # Errors
+///
+/// See [`remove_from_path`](fn.remove_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::strip_all_metadata;
+/// use std::fs::OpenOptions;
+///
+/// let mut file = OpenOptions::new().read(true).write(true).open("path/to/file").unwrap();
+/// strip_all_metadata(&mut file).unwrap();
+/// ```
+pub fn strip_all_metadata<F: Read + Write + Seek + Truncate>(file: &mut F) -> Result<()> {
+    remove_from(file)?;
+    let end_size = probe_trailers(file)?;
+    if end_size > 0 {
+        let filesize = file.seek(SeekFrom::End(0))?;
+        file.truncate(filesize - end_size)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// Removes APE tag from a file, both tags are removed when there is a tag at the start and at the end.
+///
+/// A journal (if any) also records the `pending` tag going to be appended after removal.
+/// Returns the number of removed tags.
+pub(crate) fn remove_tag<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    options: &WriteOptions,
+    journal: Option<&mut Journal>,
+    pending: &[u8],
+) -> Result<usize> {
+    let all = existing_tags(file, options)?;
+    let removed = all.len();
+    remove_regions(file, all, options, journal, pending)?;
+    Ok(removed)
+}
+
+/// Removes APE tags on behalf of `remove_from` functions, unlike a tag replaced by a write removed tags are counted.
+pub(crate) fn remove_tags<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    options: &WriteOptions,
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let removed = remove_tag(file, options, journal, &[])?;
+    metrics::record(|m| (0..removed).for_each(|_| m.tag_removed()));
+    Ok(())
+}
+
+/// Returns the metadata of the tag at the end and the tag at the start of a file, no tag is ok.
+pub(crate) fn existing_tags<R: Read + Seek>(reader: &mut R, options: &WriteOptions) -> Result<Vec<Meta>> {
+    match Meta::read_all(reader, options.garbage_window()) {
+        Ok(all) => Ok(all),
+        Err(err) if err.kind() == ErrorKind::TagNotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether the `size` bytes at `offset` of a file of `filesize` bytes are followed only by ID3v1 and LYRICS3v2 (if any).
+pub(crate) fn precedes_trailers<R: Read + Seek>(
+    reader: &mut R,
+    (offset, size): (u64, u64),
+    filesize: u64,
+) -> Result<bool> {
+    Ok(offset + size + probe_trailers(reader)? == filesize)
+}
+
+/// Removes tags described by the metadata, see [`remove_tag`].
+fn remove_regions<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    all: Vec<Meta>,
+    options: &WriteOptions,
+    mut journal: Option<&mut Journal>,
+    pending: &[u8],
+) -> Result<()> {
+    let buffer_size = shift_buffer_size(options, journal.is_some());
+    // The tag at the end goes first, so the offset of the tag at the start stays valid
+    for meta in all {
+        let (offset, size) = meta.region()?;
+        debug!(offset, size, "removing APE tag");
+        let filesize = file.seek(SeekFrom::End(0))?;
+        if offset + size > filesize {
+            return Err(ErrorKind::BadTagSize.into());
+        }
+
+        if journal.is_none() && precedes_trailers(file, (offset, size), filesize)? {
+            truncate_tag(file, (offset, size), filesize, options)?;
+            continue;
+        }
+        if let Some(journal) = journal.as_deref_mut() {
+            journal.begin_shift(offset, size, filesize, buffer_size, pending)?;
+        }
+        shift_left(
+            file,
+            (offset, size),
+            filesize,
+            0,
+            buffer_size,
+            options,
+            journal.as_deref_mut(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Removes the `size` bytes at `offset` followed by ID3v1 and LYRICS3v2 only.
+///
+/// The trailers are stashed and written back after the file is truncated, so audio data is not touched.
+fn truncate_tag<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    (offset, size): (u64, u64),
+    filesize: u64,
+    options: &WriteOptions,
+) -> Result<()> {
+    let movesize = filesize - offset - size;
+    let mut trailers = Vec::with_capacity(movesize as usize);
+    file.seek(SeekFrom::Start(offset + size))?;
+    file.take(movesize).read_to_end(&mut trailers)?;
+    if movesize > 0 && options.is_cancelled() {
+        return Err(ErrorKind::Cancelled.into());
+    }
+
+    file.truncate(offset)?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(&trailers)?;
+    file.flush()?;
+    if movesize > 0 {
+        options.report_progress(movesize, movesize);
+        debug!(bytes = movesize, "restored trailers following a tag");
+    }
+    Ok(())
+}
+
+/// Moves bytes following the `size` bytes at `offset` to `offset` and truncates the file.
+///
+/// Starts from `progress` bytes already moved.
+/// Every chunk is recorded in a journal (if any) before it is written.
+pub(crate) fn shift_left<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    (offset, size): (u64, u64),
+    filesize: u64,
+    mut progress: u64,
+    buffer_size: u64,
+    options: &WriteOptions,
+    mut journal: Option<&mut Journal>,
+) -> Result<()> {
+    let movesize = filesize - offset - size;
+
+    if movesize > progress {
+        file.flush()?;
+
+        // Keep the tag to restore it when cancelled
+        let mut original = Vec::new();
+        if options.is_cancellable() {
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(size).read_to_end(&mut original)?;
+        }
+        let start = progress;
+
+        // A single buffer is reused for all chunks
+        let mut buff = vec![0; buffer_size.min(movesize - progress) as usize];
+        while progress < movesize {
+            if options.is_cancelled() {
+                shift_back(file, (offset, size), start..progress, &original, buffer_size)?;
+                if let Some(journal) = journal {
+                    journal.discard()?;
+                }
+                return Err(ErrorKind::Cancelled.into());
+            }
+            let chunk = &mut buff[..(movesize - progress).min(buffer_size) as usize];
+            file.seek(SeekFrom::Start(offset + size + progress))?;
+            file.read_exact(chunk)?;
+            if let Some(journal) = journal.as_deref_mut() {
+                journal.record_chunk(progress, chunk)?;
+            }
+            file.seek(SeekFrom::Start(offset + progress))?;
+            file.write_all(chunk)?;
+            if journal.is_some() {
+                file.sync_data()?;
+            }
+            progress += chunk.len() as u64;
+            options.report_progress(progress, movesize);
+        }
+        debug!(bytes = movesize, "moved bytes following a tag");
+        metrics::record(|m| m.bytes_shifted(movesize));
+    }
+
+    file.truncate(filesize - size)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Reverts moving of the `moved` bytes by `shift_left` and restores the original bytes of the tag.
+fn shift_back<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    (offset, size): (u64, u64),
+    moved: Range<u64>,
+    original: &[u8],
+    buffer_size: u64,
+) -> Result<()> {
+    shift_right(file, (offset, size), moved, buffer_size, None, |_| {})?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(original)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Moves the `range` of bytes following `offset` by `size` bytes to the right.
+///
+/// `on_chunk` is called with the number of bytes moved so far.
+/// Every chunk is recorded in a journal (if any) with its start before it is written.
+fn shift_right<F: Read + Write + Seek + Truncate>(
+    file: &mut F,
+    (offset, size): (u64, u64),
+    range: Range<u64>,
+    buffer_size: u64,
+    mut journal: Option<&mut Journal>,
+    mut on_chunk: impl FnMut(u64),
+) -> Result<()> {
+    let mut buff = vec![0; buffer_size.min(range.end - range.start) as usize];
+    // Move from the end, so no bytes are overwritten before they are moved
+    let mut end = range.end;
+    while end > range.start {
+        let start = range.start.max(end.saturating_sub(buffer_size));
+        let chunk = &mut buff[..(end - start) as usize];
+        file.seek(SeekFrom::Start(offset + start))?;
+        file.read_exact(chunk)?;
+        if let Some(journal) = journal.as_deref_mut() {
+            journal.record_chunk(start, chunk)?;
+        }
+        file.seek(SeekFrom::Start(offset + size + start))?;
+        file.write_all(chunk)?;
+        if journal.is_some() {
+            file.sync_data()?;
+        }
+        end = start;
+        on_chunk(range.end - end);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        copy_with_tag, read_from, read_from_at, read_from_path, read_from_stream, read_keys, relocate_tag, remove_from,
+        remove_from_path, replace_tag_in_buffer, strip_all_metadata, write_at, write_to, write_to_path,
+        write_to_path_if_changed, SetPolicy, Tag,
+    };
+    use crate::{
+        cow::CowTag,
+        error::ErrorKind,
+        item::{Item, ItemType, ItemValue},
+        meta::{Layout, MetaPosition},
+        options::{ApeVersion, ItemOrder, WriteOptions},
+        storage::Truncate,
+    };
+    use std::{
+        fs::{remove_file, File},
+        io::{self, Cursor, Read, Seek, SeekFrom, Write},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    #[test]
+    fn items() {
+        let mut tag = Tag::new();
+        assert_eq!(0, tag.0.len());
 
         let item = Item::from_text("key", "value").unwrap();
         let item_duplicate = Item::from_text("key", "value-added").unwrap();
@@ -384,6 +1900,177 @@ mod test {
         assert_eq!(1, tag.0.len());
     }
 
+    #[test]
+    fn shared_binary_values() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_binary("Cover Art (Front)", vec![1; 1024]).unwrap());
+        tag.add_item(Item::from_text("Artist", "Artist Name").unwrap());
+        let mut copy = tag.clone();
+        copy.set_item(Item::from_text("Artist", "Other Name").unwrap());
+        match (&tag.0[0].value, &copy.item("cover art (front)").unwrap().value) {
+            (ItemValue::Binary(original), ItemValue::Binary(copied)) => assert!(Arc::ptr_eq(original, copied)),
+            _ => panic!("Invalid value"),
+        }
+    }
+
+    #[test]
+    fn distinct_keys() {
+        let mut tag = Tag::new();
+        assert_eq!(tag.keys().count(), 0);
+        tag.add_item(Item::from_text("Artist", "First").unwrap());
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_text("ARTIST", "Second").unwrap());
+        assert_eq!(tag.keys().collect::<Vec<_>>(), vec!["Artist", "title"]);
+    }
+
+    #[test]
+    fn as_map() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Artist", "First").unwrap());
+        tag.add_item(Item::from_text("title", "Title").unwrap());
+        tag.add_item(Item::from_text("ARTIST", "Second").unwrap());
+        let map = tag.as_map();
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&"Artist", &"title"]);
+        assert_eq!(map["Artist"], vec![&tag.0[0], &tag.0[2]]);
+        assert_eq!(map["title"], vec![&tag.0[1]]);
+    }
+
+    #[test]
+    fn index() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Artist", "Artist").unwrap());
+        assert_eq!(&tag["artist"], tag.item("artist").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "no item with the key")]
+    fn index_missing() {
+        let _ = &Tag::new()["title"];
+    }
+
+    #[test]
+    fn duplicates_order() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("artist", "Long Artist Name").unwrap());
+        tag.add_item(Item::from_text("title", "Some Title").unwrap());
+        tag.add_item(Item::from_text("ARTIST", "Name").unwrap());
+        let bytes = tag.to_bytes().unwrap();
+        let result = Tag::parse(&bytes).unwrap();
+        assert_eq!(result.items("artist"), tag.items("artist"));
+        let cow = CowTag::parse(&bytes).unwrap();
+        assert_eq!(cow.to_bytes().unwrap(), bytes);
+        // Items are still sorted by size
+        assert_eq!(result.keys().collect::<Vec<_>>(), vec!["artist", "title"]);
+        assert_eq!(&*result.0[0].key, "artist");
+    }
+
+    #[test]
+    fn set_policy() {
+        let mut tag = Tag::new();
+        let artist = |value| Item::from_text("Artist", value).unwrap();
+        tag.set_item_with(artist("First"), SetPolicy::MergeValues);
+        tag.set_item_with(artist("Second"), SetPolicy::MergeValues);
+        assert_eq!(tag.items("artist"), vec![&artist("First\0Second")]);
+        tag.set_item_with(Item::from_binary("artist", vec![0]).unwrap(), SetPolicy::MergeValues);
+        assert_eq!(tag.items("artist").len(), 2);
+        tag.set_item_with(artist(""), SetPolicy::MergeValues);
+        assert_eq!(tag.items("artist")[0], &artist("First\0Second"));
+        tag.set_item_with(artist("Third"), SetPolicy::Append);
+        assert_eq!(tag.items("artist").len(), 3);
+        tag.set_item_with(artist("Fourth"), SetPolicy::Replace);
+        assert_eq!(tag.items("artist"), vec![&artist("Fourth")]);
+    }
+
+    #[test]
+    fn set_policy_merge_skips_items() {
+        let mut tag = Tag::new();
+        let artist = |value| Item::from_text("Artist", value).unwrap();
+        tag.add_item(Item::from_binary("Artist", vec![0]).unwrap());
+        tag.add_item(Item::builder("Artist").text("First").read_only(true).build().unwrap());
+        tag.add_item(artist("Second"));
+        tag.set_item_with(artist("Third"), SetPolicy::MergeValues);
+        let items = tag.items("artist");
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1].value, ItemValue::Text("First".into()));
+        assert_eq!(items[2], &artist("Second\0Third"));
+    }
+
+    #[test]
+    fn read_only_items() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::builder("Artist").text("Artist").read_only(true).build().unwrap());
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        let mut data = Cursor::new(Vec::new());
+        write_to(&tag, &mut data).unwrap();
+        let tag = read_from(&mut data).unwrap();
+        assert!(tag["artist"].read_only);
+        assert!(!tag["title"].read_only);
+    }
+
+    #[test]
+    fn items_matching() {
+        let mut tag = Tag::new();
+        for key in [
+            "MUSICBRAINZ_TRACKID",
+            "musicbrainz_albumid",
+            "MusicBrainz Album Type",
+            "Album",
+            "Album Artist",
+        ] {
+            tag.add_item(Item::from_text(key, "value").unwrap());
+        }
+        let keys = |pattern| {
+            tag.items_matching(pattern)
+                .iter()
+                .map(|item| &*item.key)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            keys("MusicBrainz_*"),
+            vec!["MUSICBRAINZ_TRACKID", "musicbrainz_albumid"]
+        );
+        assert_eq!(
+            keys("*album*"),
+            vec!["musicbrainz_albumid", "MusicBrainz Album Type", "Album", "Album Artist"]
+        );
+        assert_eq!(
+            keys("musicbrainz?album*"),
+            vec!["musicbrainz_albumid", "MusicBrainz Album Type"]
+        );
+        assert_eq!(keys("*a*a*t"), vec!["Album Artist"]);
+        assert_eq!(keys("Album"), vec!["Album"]);
+        assert!(keys("Album?").is_empty());
+    }
+
+    #[test]
+    fn setters() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("title", "Old").unwrap());
+        tag.add_item(Item::from_text("title", "Older").unwrap());
+        tag.set_text("Title", "Title").unwrap();
+        tag.set_locator("url", "http://example.com").unwrap();
+        tag.set_binary("cover", vec![1, 2]).unwrap();
+        assert_eq!(tag.items("title"), vec![&Item::from_text("Title", "Title").unwrap()]);
+        assert_eq!(tag["url"], Item::from_locator("url", "http://example.com").unwrap());
+        assert_eq!(tag["cover"], Item::from_binary("cover", vec![1, 2]).unwrap());
+        let err = tag.set_text("k", "value").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidItemKeyLen);
+        assert_eq!(tag.iter().count(), 3);
+    }
+
+    #[test]
+    fn set_multi() {
+        let mut tag = Tag::new();
+        tag.set_multi("genre", ["Rock", "", "Blues", "Rock"]).unwrap();
+        assert_eq!(tag["genre"], Item::from_text("genre", "Rock\0Blues\0Rock").unwrap());
+        tag.set_multi_unique("genre", vec![String::from("Rock"), String::from("Rock")])
+            .unwrap();
+        assert_eq!(tag["genre"], Item::from_text("genre", "Rock").unwrap());
+        tag.set_multi("genre", [""]).unwrap();
+        assert!(tag.item("genre").is_none());
+        assert!(tag.set_multi("ID3", ["value"]).is_err());
+    }
+
     #[test]
     fn read_write_remove() {
         let path = "data/read-write-remove.apev2";
@@ -414,6 +2101,508 @@ mod test {
         remove_file(path).unwrap();
     }
 
+    #[test]
+    fn write_if_changed() {
+        let path = "data/write-if-changed.apev2";
+
+        let mut data = File::create(path).unwrap();
+        data.write_all(&[0; 200]).unwrap();
+
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("key", "value").unwrap());
+        tag.add_item(Item::from_binary("cover", vec![1, 2, 3]).unwrap());
+        assert!(write_to_path_if_changed(&tag, path).unwrap());
+        assert!(!write_to_path_if_changed(&tag, path).unwrap());
+
+        let mut reordered = Tag::new();
+        reordered.add_item(Item::from_binary("cover", vec![1, 2, 3]).unwrap());
+        reordered.add_item(Item::from_text("key", "value").unwrap());
+        assert!(!write_to_path_if_changed(&reordered, path).unwrap());
+
+        reordered.add_item(Item::from_text("key", "value").unwrap());
+        assert!(write_to_path_if_changed(&reordered, path).unwrap());
+        assert_eq!(2, read_from_path(path).unwrap().items("key").len());
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn write_to_empty_file() {
+        let path = "data/write-to-empty-file.apev2";
+        File::create(path).unwrap();
+
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to_path(&tag, path).unwrap();
+        assert_eq!(1, read_from_path(path).unwrap().0.len());
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn in_memory() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut data).unwrap();
+        assert_eq!(1, read_from(&mut data).unwrap().0.len());
+        remove_from(&mut data).unwrap();
+        assert_eq!(data.into_inner(), vec![1; 200]);
+    }
+
+    #[test]
+    fn copy() {
+        let mut data = vec![1; 200];
+        data.extend(b"TAG");
+        data.extend([2; 125]);
+        let mut src = Cursor::new(data.clone());
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut src).unwrap();
+
+        tag.set_item(Item::from_text("key", "new value").unwrap());
+        let mut dst = Vec::new();
+        copy_with_tag(&tag, &mut src, &mut dst).unwrap();
+        write_to(&tag, &mut src).unwrap();
+        assert_eq!(&dst, src.get_ref());
+
+        let mut dst = Vec::new();
+        copy_with_tag(&Tag::new(), &mut Cursor::new(vec![1; 200]), &mut dst).unwrap();
+        assert_eq!(&dst[..200], &[1; 200]);
+        assert!(read_from(&mut Cursor::new(dst)).unwrap().iter().next().is_none());
+    }
+
+    #[test]
+    fn remove_before_trailers() {
+        /// Counts bytes written to a cursor.
+        struct Counter(Cursor<Vec<u8>>, usize);
+
+        impl Read for Counter {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0.read(buf)
+            }
+        }
+
+        impl Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.1 += buf.len();
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl Seek for Counter {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        impl Truncate for Counter {
+            fn truncate(&mut self, len: u64) -> io::Result<()> {
+                self.0.truncate(len)
+            }
+        }
+
+        let mut original = vec![1; 100000];
+        original.extend(b"TAG");
+        original.extend([2; 125]);
+        let mut data = Counter(Cursor::new(original.clone()), 0);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut data).unwrap();
+        data.1 = 0;
+        remove_from(&mut data).unwrap();
+        assert_eq!(data.1, 128);
+        assert_eq!(data.0.into_inner(), original);
+    }
+
+    #[test]
+    fn stream_items() {
+        /// Records lengths of writes.
+        struct Writes(Vec<usize>);
+
+        impl Write for Writes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.push(buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        tag.set_item(Item::from_binary("cover", vec![1; 1 << 20]).unwrap());
+        let mut writes = Writes(Vec::new());
+        copy_with_tag(&tag, &mut Cursor::new(vec![1; 100]), &mut writes).unwrap();
+        // The value is passed to the writer as is
+        assert!(writes.0.contains(&(1 << 20)));
+        assert_eq!(writes.0.iter().sum::<usize>(), 100 + tag.to_bytes().unwrap().len());
+    }
+
+    #[test]
+    fn cancel_shift() {
+        let original = (0..100).collect::<Vec<u8>>();
+        let mut data = Cursor::new(original.clone());
+        let checks = AtomicUsize::new(0);
+        let mut options = WriteOptions::new();
+        options.should_cancel(move || checks.fetch_add(1, Ordering::SeqCst) == 3);
+        let err = super::shift_left(&mut data, (10, 15), 100, 0, 7, &options, None).unwrap_err();
+        assert_eq!(err.to_string(), "operation cancelled");
+        assert_eq!(data.into_inner(), original);
+    }
+
+    #[test]
+    fn preserve_layout() {
+        let mut options = WriteOptions::new();
+        options.preserve_layout(true);
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("key2", "long value").unwrap());
+        tag.add_item(Item::from_text("key1", "value").unwrap());
+
+        for at_start in [true, false] {
+            let layout = Layout {
+                header: true,
+                footer: true,
+                at_start,
+            };
+            let bytes = super::serialize(&tag, &options, 0, layout).unwrap();
+            let audio = vec![1; 100];
+            let mut data = Cursor::new(if at_start {
+                [&bytes[..], &audio].concat()
+            } else {
+                [&audio[..], &bytes].concat()
+            });
+            let mut existing = read_from(&mut data).unwrap();
+            let keys = existing.iter().map(|item| &*item.key).collect::<Vec<_>>();
+            assert_eq!(keys, vec!["key2", "key1"]);
+
+            existing.0[0] = Item::from_text("key2", "new value").unwrap();
+            options.write_to(&existing, &mut data).unwrap();
+            let bytes = super::serialize(&existing, &options, 0, layout).unwrap();
+            if at_start {
+                assert_eq!(data.into_inner(), [&bytes[..], &audio].concat());
+            } else {
+                assert_eq!(data.into_inner(), [&audio[..], &bytes].concat());
+            }
+        }
+    }
+
+    #[test]
+    fn item_order() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("bb", "long value").unwrap());
+        tag.add_item(Item::from_text("Cc", "value").unwrap());
+        tag.add_item(Item::from_text("aa", "longest value").unwrap());
+        let keys = |order: ItemOrder| {
+            let mut data = Cursor::new(Vec::new());
+            WriteOptions::new().item_order(order).write_to(&tag, &mut data).unwrap();
+            let tag = read_from(&mut data).unwrap();
+            tag.iter().map(|item| item.key.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(ItemOrder::Size), vec!["Cc", "bb", "aa"]);
+        assert_eq!(keys(ItemOrder::Key), vec!["aa", "bb", "Cc"]);
+        assert_eq!(keys(ItemOrder::Insertion), vec!["bb", "Cc", "aa"]);
+        let reversed = ItemOrder::Custom(Arc::new(|a: &Item, b: &Item| b.key.cmp(&a.key)));
+        assert_eq!(keys(reversed), vec!["bb", "aa", "Cc"]);
+    }
+
+    #[test]
+    fn write_ape_version_1() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let mut options = WriteOptions::new();
+        options.version(ApeVersion::V1);
+        options.write_to(&tag, &mut data).unwrap();
+        assert_eq!(&data.get_ref()[200 + 17 + 8..][..4], &1000u32.to_le_bytes());
+        assert!(read_from(&mut data).unwrap().has_same_items(&tag));
+
+        tag.set_item(Item::from_text("title", "Caf\u{e9}").unwrap());
+        options.write_to(&tag, &mut data).unwrap();
+        assert!(data.get_ref().windows(4).any(|bytes| bytes == b"Caf\xe9"));
+        assert!(read_from(&mut data).unwrap().has_same_items(&tag));
+        assert_eq!(read_keys(&mut data).unwrap().len(), 2);
+        tag.set_item(Item::from_text("title", "\u{422}\u{435}\u{441}\u{442}").unwrap());
+        let err = options.write_to(&tag, &mut data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnencodableText);
+        assert_eq!(err.key(), Some("title"));
+        tag.remove_items("title");
+
+        tag.set_item(Item::from_binary("cover", vec![1]).unwrap());
+        let err = options.write_to(&tag, &mut data).unwrap_err().to_string();
+        assert_eq!(err, "unexpected item kind");
+
+        write_to(&tag, &mut data).unwrap();
+        assert_eq!(2, read_from(&mut data).unwrap().0.len());
+        remove_from(&mut data).unwrap();
+        assert_eq!(data.into_inner(), vec![1; 200]);
+    }
+
+    #[test]
+    fn relocate() {
+        let audio = vec![1; 200];
+        let mut data = Cursor::new(audio.clone());
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("key2", "long value").unwrap());
+        tag.add_item(Item::from_text("key1", "value").unwrap());
+        WriteOptions::new()
+            .item_order(ItemOrder::Insertion)
+            .write_to(&tag, &mut data)
+            .unwrap();
+        let written = data.get_ref()[200..].to_vec();
+
+        relocate_tag(&mut data, MetaPosition::Header).unwrap();
+        assert_eq!(&data.get_ref()[data.get_ref().len() - 200..], &audio[..]);
+        assert_eq!(&data.get_ref()[..8], b"APETAGEX");
+        let relocated = read_from(&mut data).unwrap();
+        assert_eq!(relocated.0, tag.0);
+        relocate_tag(&mut data, MetaPosition::Header).unwrap();
+        assert_eq!(read_from(&mut data).unwrap().0, tag.0);
+
+        relocate_tag(&mut data, MetaPosition::Footer).unwrap();
+        assert_eq!(&data.get_ref()[..200], &audio[..]);
+        assert_eq!(read_from(&mut data).unwrap().0, tag.0);
+        // The header is kept
+        assert_eq!(data.get_ref().len(), 200 + written.len() + 32);
+
+        let err = relocate_tag(&mut Cursor::new(audio), MetaPosition::Header).unwrap_err();
+        assert_eq!(err.to_string(), "APE tag does not exists");
+    }
+
+    #[test]
+    fn strip_all() {
+        let mut data = vec![1; 200];
+        data.extend(b"LYRICSBEGIN");
+        data.extend(b"000011LYRICS200");
+        data.extend(b"TAG");
+        data.extend([2; 125]);
+        let mut data = Cursor::new(data);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        write_to(&tag, &mut data).unwrap();
+
+        strip_all_metadata(&mut data).unwrap();
+        assert_eq!(data.get_ref(), &vec![1; 200]);
+        strip_all_metadata(&mut data).unwrap();
+        assert_eq!(data.into_inner(), vec![1; 200]);
+    }
+
+    #[test]
+    fn start_and_end_tags() {
+        let mut options = WriteOptions::new();
+        options.preserve_layout(true);
+        let layout = Layout {
+            header: true,
+            footer: false,
+            at_start: true,
+        };
+        let mut start = Tag::new();
+        start.add_item(Item::from_text("key1", "start").unwrap());
+        start.add_item(Item::from_text("key2", "start").unwrap());
+        let mut end = Tag::new();
+        end.add_item(Item::from_text("KEY2", "end").unwrap());
+        end.add_item(Item::from_text("key3", "end").unwrap());
+        let audio = vec![1; 100];
+        let mut data = Cursor::new(
+            [
+                super::serialize(&start, &options, 0, layout).unwrap(),
+                audio.clone(),
+                super::serialize(&end, &options, 0, Layout::default()).unwrap(),
+            ]
+            .concat(),
+        );
+
+        let tag = read_from(&mut data).unwrap();
+        let values = tag
+            .iter()
+            .map(|item| (&*item.key, item.value.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                ("KEY2", ItemValue::Text(String::from("end"))),
+                ("key3", ItemValue::Text(String::from("end"))),
+                ("key1", ItemValue::Text(String::from("start"))),
+            ]
+        );
+
+        let mut copy = data.clone();
+        remove_from(&mut copy).unwrap();
+        assert_eq!(copy.into_inner(), audio);
+
+        write_to(&tag, &mut data).unwrap();
+        assert_eq!(&data.get_ref()[..100], &audio[..]);
+        assert_eq!(read_from(&mut data).unwrap().0.len(), 3);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        let options = WriteOptions::new();
+        for layout in [
+            Layout::default(),
+            Layout {
+                header: true,
+                footer: false,
+                at_start: true,
+            },
+            Layout {
+                header: true,
+                footer: true,
+                at_start: false,
+            },
+        ] {
+            let bytes = super::serialize(&tag, &options, 0, layout).unwrap();
+            assert!(Tag::from_bytes(&bytes).unwrap().has_same_items(&tag));
+            assert!(Tag::from_bytes(&[vec![1; 10], bytes.clone()].concat()).is_err());
+        }
+        let bytes = tag.to_bytes().unwrap();
+        assert!(Tag::parse(&[vec![1; 10], bytes.clone()].concat()).is_ok());
+        assert!(Tag::from_bytes(&[b"TAG", &[0; 125][..]].concat()).is_err());
+
+        // A lone footer claiming a header
+        let mut footer = Tag::new().to_bytes().unwrap();
+        footer[20..24].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        let err = Tag::from_bytes(&footer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadTagSize);
+    }
+
+    #[test]
+    fn stream() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        let mut data = Cursor::new(vec![1; 10000]);
+        write_to(&tag, &mut data).unwrap();
+        let data = data.into_inner();
+        let size = tag.serialized_size() as usize;
+
+        assert!(read_from_stream(&data[..], 4096).unwrap().has_same_items(&tag));
+        assert!(read_from_stream(&data[..], size).unwrap().has_same_items(&tag));
+        let kind = |tail_hint| read_from_stream(&data[..], tail_hint).unwrap_err().kind();
+        assert_eq!(kind(size - 1), ErrorKind::BadTagSize);
+        assert_eq!(kind(0), ErrorKind::TagNotFound);
+        assert_eq!(
+            read_from_stream(&data[..10000], 4096).unwrap_err().kind(),
+            ErrorKind::TagNotFound
+        );
+    }
+
+    #[test]
+    fn serialized_size() {
+        let mut tag = Tag::new();
+        assert_eq!(tag.serialized_size(), 32);
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        tag.set_item(Item::from_binary("cover", vec![3; 1000]).unwrap());
+        assert_eq!(tag.serialized_size(), tag.to_bytes().unwrap().len() as u64);
+    }
+
+    #[test]
+    fn keys() {
+        let mut data = Cursor::new(vec![1; 200]);
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        tag.set_item(Item::from_binary("cover", vec![3; 1000]).unwrap());
+        write_to(&tag, &mut data).unwrap();
+        let mut keys = read_keys(&mut data).unwrap();
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            keys,
+            vec![
+                ("cover".to_string(), ItemType::Binary, 1000),
+                ("title".to_string(), ItemType::Text, 5)
+            ]
+        );
+        assert!(read_keys(&mut Cursor::new(vec![1; 200])).is_err());
+    }
+
+    #[test]
+    fn keys_huge_item_count() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("title", "Title").unwrap());
+        let mut bytes = tag.to_bytes().unwrap();
+        let count = bytes.len() - 16;
+        bytes[count..count + 4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert!(read_keys(&mut Cursor::new(&bytes)).is_err());
+        assert!(read_from(&mut Cursor::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn read_at() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let mut data = Cursor::new(Vec::new());
+        write_to(&tag, &mut data).unwrap();
+        let mut data = Cursor::new([data.get_ref().clone(), vec![1; 100]].concat());
+        assert!(read_from(&mut data).is_err());
+        assert_eq!(read_from_at(&mut data, 17).unwrap().item("key"), tag.item("key"));
+        let err = read_from_at(&mut data, 16).unwrap_err().to_string();
+        assert_eq!(err, "APE tag does not exists");
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut checked = 0;
+        for seed in 0..64u32 {
+            let data = (0..4096u32)
+                .map(|idx| (idx.wrapping_mul(2654435761).wrapping_add(seed.wrapping_mul(40503)) >> 13) as u8)
+                .collect::<Vec<_>>();
+            let tag = Tag::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            for item in tag.iter() {
+                assert!(Item::from_binary(&*item.key, vec![]).is_ok());
+            }
+            if tag.iter().next().is_none() {
+                continue;
+            }
+            let result = Tag::parse(&tag.to_bytes().unwrap()).unwrap();
+            assert!(result.has_same_items(&tag));
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn buffers() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let bytes = tag.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 17 + 32);
+        assert_eq!(Tag::parse(&bytes).unwrap().item("key"), tag.item("key"));
+
+        let buffer = replace_tag_in_buffer(vec![1; 100], &tag).unwrap();
+        assert_eq!(buffer, [vec![1; 100], bytes.clone()].concat());
+        tag.set_item(Item::from_text("key", "other").unwrap());
+        let buffer = replace_tag_in_buffer(buffer, &tag).unwrap();
+        assert_eq!(buffer.len(), 100 + bytes.len());
+        assert_eq!(Tag::parse(&buffer).unwrap().item("key"), tag.item("key"));
+        assert!(Tag::parse(&[1; 100]).is_err());
+    }
+
+    #[test]
+    fn write_at_offset() {
+        let mut tag = Tag::new();
+        tag.set_item(Item::from_text("key", "value").unwrap());
+        let mut data = Cursor::new(vec![1; 200]);
+        write_at(&tag, &mut data, 50, 100).unwrap();
+        assert_eq!(data.get_ref().len(), 200);
+        assert_eq!(&data.get_ref()[..50], &[1; 50]);
+        assert_eq!(&data.get_ref()[150..], &[1; 50]);
+        assert_eq!(read_from_at(&mut data, 118).unwrap().item("key"), tag.item("key"));
+
+        let err = write_at(&tag, &mut data, 50, 48).unwrap_err().to_string();
+        assert_eq!(err, "APE tag does not fit into the given region");
+        write_at(&tag, &mut data, 50, 49).unwrap();
+    }
+
     #[test]
     fn read_with_empty_tag() {
         assert!(read_from_path("data/empty-tag.apev2").is_ok());
@@ -426,13 +2615,21 @@ mod test {
 
     #[test]
     fn read_failed_with_bad_item_kind() {
-        let err = read_from_path("data/bad-item-kind.apev2").unwrap_err().to_string();
-        assert_eq!(err, "unexpected item kind");
+        let err = read_from_path("data/bad-item-kind.apev2").unwrap_err();
+        assert_eq!(err.kind().to_string(), "unexpected item kind");
+        assert_eq!(
+            err.to_string(),
+            "data/bad-item-kind.apev2: item 0 at offset 50: unexpected item kind"
+        );
+        assert_eq!(err.offset(), Some(50));
     }
 
     #[test]
     fn read_failed_with_bad_tag_size() {
-        let err = read_from_path("data/bad-tag-size.apev2").unwrap_err().to_string();
+        let err = read_from_path("data/bad-tag-size.apev2")
+            .unwrap_err()
+            .kind()
+            .to_string();
         assert_eq!(err, "APE header contains invalid tag size");
     }
 
@@ -0,0 +1,141 @@
+//! [Proptest](https://docs.rs/proptest) strategies producing items and tags.
+//!
+//! Valid strategies always produce data which can be written and read back,
+//! including edge cases: keys of 2 and 255 characters, huge values and tags with many items.
+//! [`invalid_key`](fn.invalid_key.html) produces keys which are rejected by item constructors.
+//!
+//! # Examples
+//!
+//! ```
+//! use ape::{testing, Tag};
+//! use proptest::test_runner::TestRunner;
+//!
+//! TestRunner::default()
+//!     .run(&testing::tag(), |tag| {
+//!         let bytes = tag.to_bytes().unwrap();
+//!         let count = Tag::parse(&bytes).map_or(0, |tag| tag.iter().count());
+//!         assert_eq!(count, tag.iter().count());
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use crate::{
+    item::{Item, ItemValue, DENIED_KEYS},
+    tag::Tag,
+};
+use proptest::{collection::vec, prelude::*};
+
+/// Maximum number of items in tags produced by [`tag`](fn.tag.html).
+const MAX_ITEMS: usize = 16;
+
+/// Produces keys in the range from 0x20 (Space) until 0x7E (Tilde) with the given length.
+fn printable_key(len: impl Into<proptest::collection::SizeRange>) -> impl Strategy<Value = String> {
+    vec(0x20u8..=0x7e, len)
+        .prop_map(|bytes| bytes.into_iter().map(char::from).collect::<String>())
+        .prop_filter("denied key", |key| !DENIED_KEYS.contains(&key.as_str()))
+}
+
+/// Produces valid item keys.
+pub fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => printable_key(2..=16),
+        1 => edge_length_key(),
+    ]
+}
+
+/// Produces valid item keys of the minimal or the maximal length, 2 or 255 characters.
+pub fn edge_length_key() -> impl Strategy<Value = String> {
+    prop_oneof![printable_key(2), printable_key(255)]
+}
+
+/// Produces keys which are rejected: too short, too long, denied or containing non-ASCII characters.
+pub fn invalid_key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        printable_key(0..=1),
+        printable_key(256..=300),
+        prop::sample::select(DENIED_KEYS.to_vec()).prop_map(String::from),
+        (printable_key(1..=16), "[^\\x00-\\x7f]").prop_map(|(key, c)| key + &c),
+    ]
+}
+
+/// Produces item values of all kinds up to 256 bytes.
+pub fn value() -> impl Strategy<Value = ItemValue> {
+    prop_oneof![
+        vec(any::<u8>(), 0..=256).prop_map(|value| ItemValue::Binary(value.into())),
+        ".{0,64}".prop_map(ItemValue::Locator),
+        ".{0,64}".prop_map(ItemValue::Text),
+    ]
+}
+
+/// Produces values of 64 KiB up to 1 MiB.
+pub fn huge_value() -> impl Strategy<Value = ItemValue> {
+    prop_oneof![
+        vec(any::<u8>(), 65536..=1048576).prop_map(|value| ItemValue::Binary(value.into())),
+        (65536usize..=1048576, any::<char>()).prop_map(|(len, c)| ItemValue::Text(c.to_string().repeat(len))),
+    ]
+}
+
+/// Produces valid items.
+pub fn item() -> impl Strategy<Value = Item> {
+    (key(), value(), any::<bool>()).prop_map(|(key, value, read_only)| Item {
+        key: key.into(),
+        value,
+        read_only,
+    })
+}
+
+/// Produces tags with up to 16 items.
+pub fn tag() -> impl Strategy<Value = Tag> {
+    vec(item(), 0..=MAX_ITEMS).prop_map(|items| Tag(items, false))
+}
+
+/// Produces tags with a thousand items and more.
+pub fn tag_with_many_items() -> impl Strategy<Value = Tag> {
+    vec(item(), 1000..=2000).prop_map(|items| Tag(items, false))
+}
+
+/// Produces tags containing a huge value among regular items.
+pub fn tag_with_huge_value() -> impl Strategy<Value = Tag> {
+    (vec(item(), 0..=MAX_ITEMS), key(), huge_value()).prop_map(|(mut items, key, value)| {
+        items.push(Item {
+            key: key.into(),
+            value,
+            read_only: false,
+        });
+        Tag(items, false)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{invalid_key, tag, tag_with_huge_value, tag_with_many_items};
+    use crate::{item::Item, tag::Tag};
+    use proptest::prelude::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn valid_tags_round_trip(tag in tag()) {
+            prop_assume!(tag.iter().next().is_some());
+            let result = Tag::parse(&tag.to_bytes().unwrap()).unwrap();
+            prop_assert!(result.has_same_items(&tag));
+        }
+
+        #[test]
+        fn invalid_keys_rejected(key in invalid_key()) {
+            prop_assert!(Item::from_text(key, "value").is_err());
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2))]
+
+        #[test]
+        fn large_tags_round_trip(many in tag_with_many_items(), huge in tag_with_huge_value()) {
+            prop_assert!(Tag::parse(&many.to_bytes().unwrap()).unwrap().has_same_items(&many));
+            prop_assert!(Tag::parse(&huge.to_bytes().unwrap()).unwrap().has_same_items(&huge));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Macros emitting [tracing](https://docs.rs/tracing) spans and events when `tracing` feature is enabled.
+//!
+//! Without the feature they expand to nothing, so arguments must not have side effects.
+
+/// Emits a debug event.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+/// Emits a trace event.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+/// Enters a debug span until the end of the current block.
+macro_rules! span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+pub(crate) use {debug, span, trace};
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use crate::{tag::write_to, Item, Tag};
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicU64, AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+    use tracing::{
+        span::{Attributes, Id, Record},
+        subscriber::with_default,
+        Event, Metadata, Subscriber,
+    };
+
+    #[derive(Default)]
+    struct Counter {
+        spans: AtomicU64,
+        events: Arc<AtomicUsize>,
+    }
+
+    impl Subscriber for Counter {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(self.spans.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {
+            self.events.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn events() {
+        let counter = Counter::default();
+        let events = counter.events.clone();
+        with_default(counter, || {
+            let mut data = Cursor::new(vec![0; 100]);
+            let mut tag = Tag::new();
+            tag.set_item(Item::from_text("key", "value").unwrap());
+            write_to(&tag, &mut data).unwrap();
+            crate::read_from(&mut data).unwrap();
+        });
+        assert!(events.load(Ordering::Relaxed) > 0);
+    }
+}
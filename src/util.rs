@@ -16,9 +16,19 @@ pub(super) const ID3V1_OFFSET: i64 = -128;
 /// in the Lyrics3 v2.00 tag field.
 const LYRICS3V2_SIZE: i64 = 6;
 
+/// Whether a reader contains at least the given number of bytes
+fn has_len<R: Seek>(reader: &mut R, len: i64) -> Result<bool> {
+    Ok(reader.seek(SeekFrom::End(0))? as i64 >= len)
+}
+
 /// Checks whether APE tag exists
 pub(super) fn probe_ape<R: Read + Seek>(reader: &mut R, pos: SeekFrom) -> Result<bool> {
     let capacity = APE_PREAMBLE.len();
+    if let SeekFrom::End(offset) = pos {
+        if !has_len(reader, -offset)? {
+            return Ok(false);
+        }
+    }
     let mut preamble = Vec::<u8>::with_capacity(capacity);
     reader.seek(pos)?;
     reader.take(capacity as u64).read_to_end(&mut preamble)?;
@@ -28,6 +38,9 @@ pub(super) fn probe_ape<R: Read + Seek>(reader: &mut R, pos: SeekFrom) -> Result
 /// Whether ID3v1 tag exists
 pub(super) fn probe_id3v1<R: Read + Seek>(reader: &mut R) -> Result<bool> {
     let capacity = ID3V1_HEADER.len();
+    if !has_len(reader, -ID3V1_OFFSET)? {
+        return Ok(false);
+    }
     let mut header = Vec::<u8>::with_capacity(capacity);
     reader.seek(SeekFrom::End(ID3V1_OFFSET))?;
     reader.take(capacity as u64).read_to_end(&mut header)?;
@@ -38,6 +51,9 @@ pub(super) fn probe_id3v1<R: Read + Seek>(reader: &mut R) -> Result<bool> {
 /// See http://id3.org/Lyrics3v2 for more details.
 pub(super) fn probe_lyrics3v2<R: Read + Seek>(reader: &mut R) -> Result<i64> {
     let capacity = LYRICS3V2_HEADER.len();
+    if !has_len(reader, -ID3V1_OFFSET + LYRICS3V2_SIZE + capacity as i64)? {
+        return Ok(-1);
+    }
     let mut header = Vec::<u8>::with_capacity(capacity);
     reader.seek(SeekFrom::End(ID3V1_OFFSET - capacity as i64))?;
     reader.take(capacity as u64).read_to_end(&mut header)?;
@@ -53,3 +69,26 @@ pub(super) fn probe_lyrics3v2<R: Read + Seek>(reader: &mut R) -> Result<i64> {
         Ok(-1)
     }
 }
+
+/// Returns the size of ID3v1 and Lyrics3 v2.00 tags at the end of a file.
+pub(super) fn probe_trailers<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+    if !probe_id3v1(reader)? {
+        return Ok(0);
+    }
+    let mut size = -ID3V1_OFFSET;
+    let lyrics3v2_size = probe_lyrics3v2(reader)?;
+    if lyrics3v2_size != -1 {
+        size += lyrics3v2_size;
+    }
+    Ok(size as u64)
+}
+
+/// Decodes ISO-8859-1 text, every byte is a character.
+pub(super) fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// Encodes text as ISO-8859-1, returns `None` if it contains other characters.
+pub(super) fn encode_latin1(text: &str) -> Option<Vec<u8>> {
+    text.chars().map(|ch| u8::try_from(ch).ok()).collect()
+}
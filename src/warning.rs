@@ -0,0 +1,190 @@
+use crate::{
+    error::Result,
+    item::{is_recommended_key, ItemValue},
+    key::ItemKey,
+    meta::{Meta, APE_HEADER_SIZE, IS_HEADER},
+    options::{KeyValidation, ReadOptions},
+    tag::{count_items, read_item_keys, read_tag, Tag},
+    util::APE_PREAMBLE,
+};
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Seek, SeekFrom};
+
+/// A recoverable oddity of a tag which has been read successfully.
+///
+/// Returned by [`read_with_warnings`](fn.read_with_warnings.html), so audit tools can flag files without failing on them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Size, item count or flags of the header do not match the ones of the footer,
+    /// or the header claimed by the footer is missing.
+    HeaderMismatch,
+    /// Items are followed by the given number of zero bytes.
+    ///
+    /// Tags written with [`WriteOptions::padding`](struct.WriteOptions.html#method.padding) are reported as well.
+    Padding(u64),
+    /// A standard key is written with another capitalization, e.g. `ARTIST` instead of `Artist`.
+    KeyCapitalization(String),
+    /// An item with the given key has an empty value.
+    EmptyValue(String),
+    /// A key contains characters other than letters, digits, spaces and `()-./_`,
+    /// reported when keys are validated strictly, see [`KeyValidation`](enum.KeyValidation.html).
+    KeyCharacters(String),
+    /// An item has a denied key, see [`ReadOptions::denied_keys`](struct.ReadOptions.html#method.denied_keys).
+    DeniedKey(String),
+    /// An item count of a tag differs from the number of items found,
+    /// reported when [`ReadOptions::tolerate_item_count`](struct.ReadOptions.html#method.tolerate_item_count) is enabled.
+    ItemCount {
+        /// The count written in the tag.
+        declared: u32,
+        /// The number of complete items.
+        found: u32,
+    },
+}
+
+/// Attempts to read an APE tag, recoverable oddities are returned alongside the tag.
+///
+/// # Errors
+///
+/// See [`read_from_path`](fn.read_from_path.html)
+///
+/// # Examples
+///
+/// ```no_run
+/// use ape::read_with_warnings;
+/// use std::fs::File;
+///
+/// let (tag, warnings) = read_with_warnings(&mut File::open("path/to/file").unwrap()).unwrap();
+/// for warning in warnings {
+///     println!("{:?}", warning);
+/// }
+/// ```
+pub fn read_with_warnings<R: Read + Seek>(reader: &mut R) -> Result<(Tag, Vec<Warning>)> {
+    ReadOptions::new().read_with_warnings(reader)
+}
+
+pub(crate) fn read_tag_with_warnings<R: Read + Seek>(
+    reader: &mut R,
+    options: &ReadOptions,
+) -> Result<(Tag, Vec<Warning>)> {
+    let tag = read_tag(reader, options)?;
+    let mut warnings = Vec::new();
+    for meta in Meta::read_all(reader, options.garbage_window())? {
+        check_meta(reader, &meta, options, &mut warnings)?;
+    }
+    for item in tag.iter() {
+        if item.has_denied_key() {
+            warnings.push(Warning::DeniedKey(item.key.to_string()));
+        }
+        let key = ItemKey::from(&*item.key);
+        if !matches!(key, ItemKey::Custom(_)) && key.as_str() != &*item.key {
+            warnings.push(Warning::KeyCapitalization(item.key.to_string()));
+        }
+        if options.key_validation_mode() == KeyValidation::Strict && !is_recommended_key(&item.key) {
+            warnings.push(Warning::KeyCharacters(item.key.to_string()));
+        }
+        let is_empty = match item.value {
+            ItemValue::Binary(ref value) => value.is_empty(),
+            ItemValue::Reserved(ref value) => value.is_empty(),
+            ItemValue::Locator(ref value) | ItemValue::Text(ref value) => value.is_empty(),
+        };
+        if is_empty {
+            warnings.push(Warning::EmptyValue(item.key.to_string()));
+        }
+    }
+    Ok((tag, warnings))
+}
+
+/// Compares the header with the footer and looks for padding following items.
+fn check_meta<R: Read + Seek>(
+    reader: &mut R,
+    meta: &Meta,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<()> {
+    let layout = meta.layout();
+    if layout.header && layout.footer {
+        // Items follow the header regardless of the position of the tag
+        let consistent = match meta.start_pos.checked_sub(APE_HEADER_SIZE) {
+            Some(header_offset) => {
+                let header = read_meta(reader, header_offset)?;
+                let footer = read_meta(reader, meta.end_pos)?;
+                header[..8] == *APE_PREAMBLE
+                    && header[8..20] == footer[8..20]
+                    && LittleEndian::read_u32(&header[20..]) == LittleEndian::read_u32(&footer[20..]) | IS_HEADER
+            }
+            None => false,
+        };
+        if !consistent {
+            warnings.push(Warning::HeaderMismatch);
+        }
+    }
+    if options.tolerates_item_count() {
+        let found = count_items(reader, meta)?;
+        if found != meta.item_count {
+            warnings.push(Warning::ItemCount {
+                declared: meta.item_count,
+                found,
+            });
+        }
+    }
+    let items_end = meta.start_pos
+        + read_item_keys(reader, meta, options)?
+            .iter()
+            .map(|(key, _, size)| 8 + key.len() as u64 + 1 + *size as u64)
+            .sum::<u64>();
+    if items_end < meta.end_pos {
+        warnings.push(Warning::Padding(meta.end_pos - items_end));
+    }
+    Ok(())
+}
+
+fn read_meta<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<[u8; APE_HEADER_SIZE as usize]> {
+    let mut data = [0; APE_HEADER_SIZE as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_with_warnings, Warning};
+    use crate::{
+        item::Item,
+        meta::Layout,
+        options::WriteOptions,
+        tag::{serialize, Tag},
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn warnings() {
+        let mut tag = Tag::new();
+        tag.add_item(Item::from_text("Title", "Title").unwrap());
+        tag.add_item(Item::from_text("ARTIST", "Artist").unwrap());
+        tag.add_item(Item::from_text("mood", "").unwrap());
+        let layout = Layout {
+            header: true,
+            footer: true,
+            at_start: false,
+        };
+        let mut bytes = serialize(&tag, &WriteOptions::new(), 10, layout).unwrap();
+
+        let (result, warnings) = read_with_warnings(&mut Cursor::new(&bytes)).unwrap();
+        assert!(result.has_same_items(&tag));
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::Padding(10),
+                // Items are sorted by size
+                Warning::EmptyValue(String::from("mood")),
+                Warning::KeyCapitalization(String::from("ARTIST")),
+            ]
+        );
+
+        // Item count of the header
+        bytes[16] += 1;
+        let (_, warnings) = read_with_warnings(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(warnings[0], Warning::HeaderMismatch);
+    }
+}